@@ -0,0 +1,370 @@
+//! Contains structural diff of FBX trees.
+//!
+//! Child nodes whose first property is an `i64` (such as objects in `Objects`) are matched by
+//! name and the value, i.e. object ID.
+//! Other child nodes are matched by name and order: the n-th child named `foo` of a node is
+//! compared to the n-th child named `foo` of the corresponding node.
+
+use std::collections::HashMap;
+use std::fmt;
+use property::Property;
+use tree::{Node, Tree};
+
+
+/// Diff options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffOptions {
+    /// Maximum absolute difference of floating-point values considered equal.
+    ///
+    /// Default is `0.0`.
+    pub float_tolerance: f64,
+    /// Names of nodes to ignore.
+    ///
+    /// Ignored nodes and their descendants are not compared.
+    /// Default is empty.
+    pub ignored_nodes: Vec<String>,
+}
+
+impl DiffOptions {
+    /// Creates a new options with default values.
+    pub fn new() -> Self {
+        DiffOptions {
+            float_tolerance: 0.0,
+            ignored_nodes: Vec::new(),
+        }
+    }
+
+    /// Sets the value of `float_tolerance` and returns the options.
+    pub fn float_tolerance(mut self, value: f64) -> Self {
+        self.float_tolerance = value;
+        self
+    }
+
+    /// Adds the given name to `ignored_nodes` and returns the options.
+    pub fn ignore_node<S: Into<String>>(mut self, name: S) -> Self {
+        self.ignored_nodes.push(name.into());
+        self
+    }
+
+    /// Adds names of nodes which usually differ for each export (such as timestamps) to
+    /// `ignored_nodes` and returns the options.
+    ///
+    /// `CreationTimeStamp`, `CreationTime` and `FileId` are added.
+    pub fn ignore_volatile_nodes(self) -> Self {
+        self.ignore_node("CreationTimeStamp")
+            .ignore_node("CreationTime")
+            .ignore_node("FileId")
+    }
+
+    fn is_ignored(&self, name: &str) -> bool {
+        self.ignored_nodes.iter().any(|ignored| ignored == name)
+    }
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions::new()
+    }
+}
+
+
+/// A difference between two trees.
+#[derive(Debug)]
+pub struct Difference<'a> {
+    /// Path to the node, such as `/Objects[0]/Geometry[#42]/Vertices[0]`.
+    ///
+    /// The number in brackets is an index among siblings with the same name, or the object ID
+    /// prefixed with `#` for nodes matched by ID.
+    /// Empty for the root.
+    pub path: String,
+    /// Kind of the difference.
+    pub kind: DifferenceKind<'a>,
+}
+
+/// A kind of a difference.
+#[derive(Debug)]
+pub enum DifferenceKind<'a> {
+    /// FBX version differs.
+    VersionChanged {
+        /// Version of the old tree.
+        old: i32,
+        /// Version of the new tree.
+        new: i32,
+    },
+    /// The node exists only in the new tree.
+    NodeAdded(&'a Node),
+    /// The node exists only in the old tree.
+    NodeRemoved(&'a Node),
+    /// The property exists only in the new node.
+    PropertyAdded {
+        /// Index of the property.
+        index: usize,
+        /// Value of the property.
+        value: Property<'a>,
+    },
+    /// The property exists only in the old node.
+    PropertyRemoved {
+        /// Index of the property.
+        index: usize,
+        /// Value of the property.
+        value: Property<'a>,
+    },
+    /// The property value differs.
+    PropertyChanged {
+        /// Index of the property.
+        index: usize,
+        /// Value of the property in the old node.
+        old: Property<'a>,
+        /// Value of the property in the new node.
+        new: Property<'a>,
+    },
+}
+
+impl<'a> fmt::Display for Difference<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            DifferenceKind::VersionChanged { old, new } => write!(f, "~ version: {} -> {}", old, new),
+            DifferenceKind::NodeAdded(_) => write!(f, "+ {}", self.path),
+            DifferenceKind::NodeRemoved(_) => write!(f, "- {}", self.path),
+            DifferenceKind::PropertyAdded { index, ref value } => write!(f, "+ {}:{}: {:?}", self.path, index, value),
+            DifferenceKind::PropertyRemoved { index, ref value } => write!(f, "- {}:{}: {:?}", self.path, index, value),
+            DifferenceKind::PropertyChanged { index, ref old, ref new } => write!(f, "~ {}:{}: {:?} -> {:?}", self.path, index, old, new),
+        }
+    }
+}
+
+
+/// Compares two trees.
+pub fn diff_trees<'a>(old: &'a Tree, new: &'a Tree, options: &DiffOptions) -> Vec<Difference<'a>> {
+    let mut diffs = Vec::new();
+    if old.version != new.version {
+        diffs.push(Difference {
+            path: String::new(),
+            kind: DifferenceKind::VersionChanged {
+                old: old.version,
+                new: new.version,
+            },
+        });
+    }
+    diff_children(&old.children, &new.children, "", options, &mut diffs);
+    diffs
+}
+
+/// Compares two nodes.
+///
+/// Node names are not compared, and paths of the differences are relative to the given nodes.
+pub fn diff_nodes<'a>(old: &'a Node, new: &'a Node, options: &DiffOptions) -> Vec<Difference<'a>> {
+    let mut diffs = Vec::new();
+    diff_node(old, new, String::new(), options, &mut diffs);
+    diffs
+}
+
+fn diff_node<'a>(old: &'a Node, new: &'a Node, path: String, options: &DiffOptions, diffs: &mut Vec<Difference<'a>>) {
+    let mut old_props = old.properties.iter();
+    let mut new_props = new.properties.iter();
+    let mut index = 0;
+    loop {
+        let kind = match (old_props.next(), new_props.next()) {
+            (Some(old), Some(new)) => {
+                if properties_equal(&old, &new, options.float_tolerance) {
+                    index += 1;
+                    continue;
+                }
+                DifferenceKind::PropertyChanged { index, old, new }
+            },
+            (Some(value), None) => DifferenceKind::PropertyRemoved { index, value },
+            (None, Some(value)) => DifferenceKind::PropertyAdded { index, value },
+            (None, None) => break,
+        };
+        diffs.push(Difference {
+            path: path.clone(),
+            kind,
+        });
+        index += 1;
+    }
+    diff_children(&old.children, &new.children, &path, options, diffs);
+}
+
+fn diff_children<'a>(old: &'a [Node], new: &'a [Node], path: &str, options: &DiffOptions, diffs: &mut Vec<Difference<'a>>) {
+    // Nodes with the same key in the new tree, in order of appearance.
+    let mut new_by_key = HashMap::<ChildKey, Vec<&'a Node>>::new();
+    for node in new.iter().filter(|node| !options.is_ignored(&node.name)) {
+        new_by_key.entry(ChildKey::of(node)).or_default().push(node);
+    }
+    let mut old_counts = HashMap::<ChildKey, usize>::new();
+    for old_node in old.iter().filter(|node| !options.is_ignored(&node.name)) {
+        let key = ChildKey::of(old_node);
+        let count = old_counts.entry(key).or_insert(0);
+        let child_path = format!("{}/{}", path, key.path_segment(*count));
+        match new_by_key.get(&key).and_then(|nodes| nodes.get(*count)) {
+            Some(new_node) => diff_node(old_node, new_node, child_path, options, diffs),
+            None => diffs.push(Difference {
+                path: child_path,
+                kind: DifferenceKind::NodeRemoved(old_node),
+            }),
+        }
+        *count += 1;
+    }
+    let mut new_counts = HashMap::<ChildKey, usize>::new();
+    for new_node in new.iter().filter(|node| !options.is_ignored(&node.name)) {
+        let key = ChildKey::of(new_node);
+        let count = new_counts.entry(key).or_insert(0);
+        if *count >= old_counts.get(&key).cloned().unwrap_or(0) {
+            diffs.push(Difference {
+                path: format!("{}/{}", path, key.path_segment(*count)),
+                kind: DifferenceKind::NodeAdded(new_node),
+            });
+        }
+        *count += 1;
+    }
+}
+
+/// Key to match child nodes of the old and the new trees.
+///
+/// Nodes with the same key are matched in order of appearance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct ChildKey<'a> {
+    /// Node name.
+    name: &'a str,
+    /// The first property if it is an `i64`, i.e. object ID.
+    id: Option<i64>,
+}
+
+impl<'a> ChildKey<'a> {
+    fn of(node: &'a Node) -> Self {
+        let id = match node.properties.iter().next() {
+            Some(Property::I64(id)) => Some(id),
+            _ => None,
+        };
+        ChildKey {
+            name: &node.name,
+            id,
+        }
+    }
+
+    /// Returns the path segment of the `count`-th node with the key.
+    fn path_segment(&self, count: usize) -> String {
+        match self.id {
+            // Object IDs should be unique, but the index is kept for duplicates.
+            Some(id) if count > 0 => format!("{}[#{}][{}]", self.name, id, count),
+            Some(id) => format!("{}[#{}]", self.name, id),
+            None => format!("{}[{}]", self.name, count),
+        }
+    }
+}
+
+/// Checks whether the two property values are equal.
+///
+/// Values of different types are never equal.
+fn properties_equal(old: &Property, new: &Property, tolerance: f64) -> bool {
+    fn float_eq(old: f64, new: f64, tolerance: f64) -> bool {
+        // Treat NaNs with the same bit pattern as equal.
+        old.to_bits() == new.to_bits() || (old - new).abs() <= tolerance
+    }
+    match (old, new) {
+        (Property::Bool(a), Property::Bool(b)) => a == b,
+        (Property::I16(a), Property::I16(b)) => a == b,
+        (Property::I32(a), Property::I32(b)) => a == b,
+        (Property::I64(a), Property::I64(b)) => a == b,
+        (Property::F32(a), Property::F32(b)) => float_eq(*a as f64, *b as f64, tolerance),
+        (Property::F64(a), Property::F64(b)) => float_eq(*a, *b, tolerance),
+        (Property::String(a), Property::String(b)) => a == b,
+        (Property::Binary(a), Property::Binary(b)) => a == b,
+        (Property::VecBool(a), Property::VecBool(b)) => a == b,
+        (Property::VecI32(a), Property::VecI32(b)) => a == b,
+        (Property::VecI64(a), Property::VecI64(b)) => a == b,
+        (Property::VecF32(a), Property::VecF32(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| float_eq(a as f64, b as f64, tolerance))
+        },
+        (Property::VecF64(a), Property::VecF64(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| float_eq(a, b, tolerance))
+        },
+        _ => false,
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use property::Property;
+    use test_util::{node, tree, TestNode};
+    use super::{DiffOptions, diff_trees};
+
+    fn value(v: f64) -> TestNode<'static> {
+        node("Value", vec![Property::F64(v)], vec![])
+    }
+
+    /// Returns differences in the display format.
+    fn diff(old: Vec<TestNode>, new: Vec<TestNode>, options: &DiffOptions) -> Vec<String> {
+        let (old, new) = (tree(7400, &old), tree(7400, &new));
+        diff_trees(&old, &new, options).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn reordered_children() {
+        let old = vec![node("Objects", vec![], vec![node("Model", vec![], vec![]), node("Geometry", vec![], vec![])])];
+        let new = vec![node("Objects", vec![], vec![node("Geometry", vec![], vec![]), node("Model", vec![], vec![])])];
+        assert!(diff(old, new, &DiffOptions::new()).is_empty());
+    }
+
+    #[test]
+    fn inserted_child() {
+        let old = vec![node("A", vec![], vec![]), node("B", vec![], vec![])];
+        let new = vec![node("A", vec![], vec![]), node("C", vec![], vec![]), node("B", vec![], vec![])];
+        assert_eq!(diff(old, new, &DiffOptions::new()), ["+ /C[0]"]);
+    }
+
+    #[test]
+    fn inserted_child_with_same_name() {
+        // Children with the same name and without IDs are matched by index.
+        let old = vec![value(1.0), value(2.0)];
+        let new = vec![value(0.0), value(1.0), value(2.0)];
+        assert_eq!(diff(old, new, &DiffOptions::new()), [
+            "~ /Value[0]:0: F64(1.0) -> F64(0.0)",
+            "~ /Value[1]:0: F64(2.0) -> F64(1.0)",
+            "+ /Value[2]",
+        ]);
+    }
+
+    #[test]
+    fn float_tolerance() {
+        let options = DiffOptions::new().float_tolerance(0.01);
+        assert!(diff(vec![value(1.0)], vec![value(1.005)], &options).is_empty());
+        assert_eq!(diff(vec![value(1.0)], vec![value(1.02)], &options), ["~ /Value[0]:0: F64(1.0) -> F64(1.02)"]);
+        // Exact comparison by default.
+        assert_eq!(diff(vec![value(1.0)], vec![value(1.005)], &DiffOptions::new()).len(), 1);
+
+        let array = |v: Vec<f32>| vec![node("Array", vec![Property::VecF32(v)], vec![])];
+        assert!(diff(array(vec![1.0, 2.0]), array(vec![1.005, 1.995]), &options).is_empty());
+        assert_eq!(diff(array(vec![1.0, 2.0]), array(vec![1.0, 2.5]), &options).len(), 1);
+        assert_eq!(diff(array(vec![1.0, 2.0]), array(vec![1.0]), &options).len(), 1);
+    }
+
+    #[test]
+    fn ignore_volatile_nodes() {
+        let header = |year: i32, file_id: &'static [u8]| vec![node("FBXHeaderExtension", vec![], vec![
+            node("CreationTimeStamp", vec![], vec![node("Year", vec![Property::I32(year)], vec![])]),
+            node("Creator", vec![Property::String(Ok("exporter"))], vec![]),
+        ]), node("FileId", vec![Property::Binary(file_id)], vec![])];
+        let options = DiffOptions::new().ignore_volatile_nodes();
+        assert!(diff(header(2020, b"a"), header(2021, b"b"), &options).is_empty());
+        assert_eq!(diff(header(2020, b"a"), header(2021, b"b"), &DiffOptions::new()).len(), 2);
+    }
+
+    #[test]
+    fn children_matched_by_id() {
+        let model = |id: i64, name: &'static str| node("Model", vec![Property::I64(id), Property::String(Ok(name))], vec![]);
+        let old = vec![node("Objects", vec![], vec![model(1, "Cube"), model(2, "Sphere"), value(1.0)])];
+        let new = vec![node("Objects", vec![], vec![model(3, "Cone"), model(2, "Ball"), model(1, "Cube"), value(2.0)])];
+        assert_eq!(diff(old, new, &DiffOptions::new()), [
+            "~ /Objects[0]/Model[#2]:1: String(Ok(\"Sphere\")) -> String(Ok(\"Ball\"))",
+            "~ /Objects[0]/Value[0]:0: F64(1.0) -> F64(2.0)",
+            "+ /Objects[0]/Model[#3]",
+        ]);
+
+        // Duplicate IDs fall back to order.
+        let old = vec![model(1, "Cube"), model(1, "Sphere")];
+        let new = vec![model(1, "Cube")];
+        assert_eq!(diff(old, new, &DiffOptions::new()), ["- /Model[#1][1]"]);
+    }
+}
@@ -68,12 +68,12 @@ impl error::Error for Error {
 impl Clone for Error {
     fn clone(&self) -> Self {
         use self::Error::*;
-        use std::error::Error;
         match *self {
             Utf8Error(ref e) => Utf8Error(e.clone()),
             InvalidMagic => InvalidMagic,
-            // `io::Error` (and an error wrapped by `io::Error`) cannot be cloned.
-            Io(ref e) => Io(io::Error::new(e.kind(), e.description())),
+            // `io::Error` (and an error wrapped by `io::Error`) cannot be cloned, so keep the
+            // kind and the message.
+            Io(ref e) => Io(io::Error::new(e.kind(), e.to_string())),
             DataError(ref e) => DataError(e.clone()),
             UnexpectedValue(ref e) => UnexpectedValue(e.clone()),
             UnexpectedEof => UnexpectedEof,
@@ -1,5 +1,10 @@
 //! Contains a type for reader event.
 
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::Arc;
 use property::DelayedProperties;
 
 
@@ -24,10 +29,170 @@ pub enum FbxEvent {
     /// Denotes beginning of a node.
     StartNode {
         /// Node name.
-        name: String,
+        ///
+        /// If node names are interned (see
+        /// [`ParserConfig::intern_node_names`](struct.ParserConfig.html#structfield.intern_node_names)),
+        /// nodes with the same name share the string.
+        name: NodeName,
         /// Node properties.
         properties: DelayedProperties,
     },
     /// Denotes end of a node.
     EndNode,
 }
+
+
+/// Name of a node.
+///
+/// Interned names are shared among nodes, and other names are owned strings, as `String` was
+/// used before interning was introduced.
+/// Either way, the name dereferences to `str`, and can be compared with `&str` and `String`.
+#[derive(Clone)]
+pub struct NodeName(NameRepr);
+
+#[derive(Clone)]
+enum NameRepr {
+    Shared(Arc<str>),
+    Owned(String),
+}
+
+impl NodeName {
+    /// Creates a name shared with other nodes.
+    pub fn shared(name: Arc<str>) -> Self {
+        NodeName(NameRepr::Shared(name))
+    }
+
+    /// Returns the name as `&str`.
+    pub fn as_str(&self) -> &str {
+        match self.0 {
+            NameRepr::Shared(ref name) => name,
+            NameRepr::Owned(ref name) => name,
+        }
+    }
+
+    /// Converts the name into `String`.
+    ///
+    /// Shared names are copied.
+    pub fn into_string(self) -> String {
+        match self.0 {
+            NameRepr::Shared(name) => (*name).to_owned(),
+            NameRepr::Owned(name) => name,
+        }
+    }
+
+    /// Returns whether the two names share the same string.
+    pub fn ptr_eq(a: &NodeName, b: &NodeName) -> bool {
+        match (a.shared_str(), b.shared_str()) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// Returns the shared string, if any.
+    fn shared_str(&self) -> Option<&Arc<str>> {
+        match self.0 {
+            NameRepr::Shared(ref name) => Some(name),
+            NameRepr::Owned(_) => None,
+        }
+    }
+}
+
+impl Deref for NodeName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for NodeName {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for NodeName {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<String> for NodeName {
+    fn from(name: String) -> Self {
+        NodeName(NameRepr::Owned(name))
+    }
+}
+
+impl<'a> From<&'a str> for NodeName {
+    fn from(name: &'a str) -> Self {
+        NodeName(NameRepr::Owned(name.to_owned()))
+    }
+}
+
+impl From<NodeName> for String {
+    fn from(name: NodeName) -> Self {
+        name.into_string()
+    }
+}
+
+impl fmt::Debug for NodeName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for NodeName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl PartialEq for NodeName {
+    fn eq(&self, other: &NodeName) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for NodeName {}
+
+impl Hash for NodeName {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialEq<str> for NodeName {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for NodeName {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for NodeName {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl PartialEq<NodeName> for str {
+    fn eq(&self, other: &NodeName) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl PartialEq<NodeName> for &str {
+    fn eq(&self, other: &NodeName) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl PartialEq<NodeName> for String {
+    fn eq(&self, other: &NodeName) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
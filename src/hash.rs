@@ -0,0 +1,313 @@
+//! Contains canonical content hashing of nodes.
+//!
+//! The hash is computed from decoded property values and child structure, so it does not depend
+//! on how the data is stored in the file: array encoding (raw or zlib compressed), compression
+//! level, and width of node record headers (which differs between FBX 7.4 and 7.5) don't affect
+//! the hash.
+//!
+//! Hashing functions feed a canonical byte sequence into any `std::hash::Hasher`.
+//! `Hasher` implementations in std (such as `DefaultHasher`) are not guaranteed to be stable
+//! across Rust releases, so use [`FnvHasher`](struct.FnvHasher.html) if the hash is persisted.
+
+use std::hash::Hasher;
+use std::io::Read;
+use error::{Error, Result};
+use event::FbxEvent;
+use property::{DelayedProperties, Property};
+use reader::EventReader;
+use tree::Node;
+
+
+/// Marker written at the beginning of a node.
+const NODE_START: u8 = b'N';
+/// Marker written at the end of a node.
+const NODE_END: u8 = b'E';
+
+
+/// 64-bit FNV-1a hasher.
+///
+/// The result is stable across platforms and crate versions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FnvHasher(u64);
+
+impl FnvHasher {
+    /// Creates a new hasher.
+    pub fn new() -> Self {
+        FnvHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Default for FnvHasher {
+    fn default() -> Self {
+        FnvHasher::new()
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+
+/// Hash options.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HashOptions {
+    /// Names of nodes to exclude.
+    ///
+    /// Excluded nodes and their descendants are not hashed, as if they don't exist.
+    /// Default is empty.
+    pub excluded_nodes: Vec<String>,
+}
+
+impl HashOptions {
+    /// Creates a new options with default values.
+    pub fn new() -> Self {
+        HashOptions::default()
+    }
+
+    /// Adds the given name to `excluded_nodes` and returns the options.
+    pub fn exclude_node<S: Into<String>>(mut self, name: S) -> Self {
+        self.excluded_nodes.push(name.into());
+        self
+    }
+
+    fn is_excluded(&self, name: &str) -> bool {
+        self.excluded_nodes.iter().any(|excluded| excluded == name)
+    }
+}
+
+
+/// Feeds the decoded property values into the hasher.
+pub fn hash_properties<H: Hasher>(properties: &DelayedProperties, state: &mut H) {
+    write_len(state, properties.num_properties());
+    for property in properties.iter() {
+        hash_property(&property, state);
+    }
+}
+
+/// Feeds the decoded property value into the hasher.
+pub fn hash_property<H: Hasher>(property: &Property, state: &mut H) {
+    match *property {
+        Property::Bool(v) => state.write(&[b'C', v as u8]),
+        Property::I16(v) => write_tagged(state, b'Y', &v.to_le_bytes()),
+        Property::I32(v) => write_tagged(state, b'I', &v.to_le_bytes()),
+        Property::I64(v) => write_tagged(state, b'L', &v.to_le_bytes()),
+        Property::F32(v) => write_tagged(state, b'F', &v.to_bits().to_le_bytes()),
+        Property::F64(v) => write_tagged(state, b'D', &v.to_bits().to_le_bytes()),
+        Property::String(v) => {
+            let bytes = match v {
+                Ok(s) => s.as_bytes(),
+                Err(raw) => raw,
+            };
+            state.write_u8(b'S');
+            write_len(state, bytes.len());
+            state.write(bytes);
+        },
+        Property::Binary(v) => {
+            state.write_u8(b'R');
+            write_len(state, v.len());
+            state.write(v);
+        },
+        Property::VecBool(ref v) => {
+            state.write_u8(b'b');
+            write_len(state, v.len());
+            for &elem in v {
+                state.write_u8(elem as u8);
+            }
+        },
+        Property::VecI32(ref v) => {
+            state.write_u8(b'i');
+            write_len(state, v.len());
+            for elem in v {
+                state.write(&elem.to_le_bytes());
+            }
+        },
+        Property::VecI64(ref v) => {
+            state.write_u8(b'l');
+            write_len(state, v.len());
+            for elem in v {
+                state.write(&elem.to_le_bytes());
+            }
+        },
+        Property::VecF32(ref v) => {
+            state.write_u8(b'f');
+            write_len(state, v.len());
+            for elem in v {
+                state.write(&elem.to_bits().to_le_bytes());
+            }
+        },
+        Property::VecF64(ref v) => {
+            state.write_u8(b'd');
+            write_len(state, v.len());
+            for elem in v {
+                state.write(&elem.to_bits().to_le_bytes());
+            }
+        },
+    }
+}
+
+/// Feeds the node subtree into the hasher.
+///
+/// `options.excluded_nodes` is applied to descendants, not to the given node itself.
+pub fn hash_node<H: Hasher>(node: &Node, options: &HashOptions, state: &mut H) {
+    hash_node_start(&node.name, &node.properties, state);
+    for child in node.children.iter().filter(|child| !options.is_excluded(&child.name)) {
+        hash_node(child, options, state);
+    }
+    state.write_u8(NODE_END);
+}
+
+/// Reads the node subtree from the reader and feeds it into the hasher.
+///
+/// `name` and `properties` should be the values of the `StartNode` event just read from the
+/// reader.
+/// This reads events up to and including the `EndNode` event of the node, and the result is the
+/// same as [`hash_node()`](fn.hash_node.html) for the same node.
+///
+/// `options.excluded_nodes` is applied to descendants, not to the given node itself.
+pub fn hash_subtree<R: Read, H: Hasher>(reader: &mut EventReader<R>, name: &str, properties: &DelayedProperties, options: &HashOptions, state: &mut H) -> Result<()> {
+    hash_node_start(name, properties, state);
+    // Depth of excluded subtree being skipped, if any.
+    let mut excluded_depth = 0;
+    let mut depth = 0;
+    loop {
+        match reader.next()? {
+            FbxEvent::StartNode { name, properties } => {
+                depth += 1;
+                if excluded_depth == 0 {
+                    if options.is_excluded(&name) {
+                        excluded_depth = depth;
+                    } else {
+                        hash_node_start(&name, &properties, state);
+                    }
+                }
+            },
+            FbxEvent::EndNode => {
+                if excluded_depth == 0 {
+                    state.write_u8(NODE_END);
+                } else if excluded_depth == depth {
+                    excluded_depth = 0;
+                }
+                if depth == 0 {
+                    return Ok(());
+                }
+                depth -= 1;
+            },
+            ev => return Err(Error::UnexpectedValue(format!("Expected `StartNode` or `EndNode` event, but got {:?}", ev))),
+        }
+    }
+}
+
+fn hash_node_start<H: Hasher>(name: &str, properties: &DelayedProperties, state: &mut H) {
+    state.write_u8(NODE_START);
+    write_len(state, name.len());
+    state.write(name.as_bytes());
+    hash_properties(properties, state);
+}
+
+fn write_tagged<H: Hasher>(state: &mut H, tag: u8, bytes: &[u8]) {
+    state.write_u8(tag);
+    state.write(bytes);
+}
+
+fn write_len<H: Hasher>(state: &mut H, len: usize) {
+    state.write(&(len as u64).to_le_bytes());
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::hash::Hasher;
+    use flate2::Compression;
+    use event::FbxEvent;
+    use property::Property;
+    use reader::EventReader;
+    use test_util::{fbx_with_compression, node, tree, TestNode};
+    use super::{FnvHasher, HashOptions, hash_node, hash_subtree};
+
+    /// Returns a node with arrays of every type and nested children.
+    fn geometry(children: Vec<TestNode<'static>>) -> Vec<TestNode<'static>> {
+        let mut children = children;
+        children.push(node("Vertices", vec![Property::VecF64(vec![0.0, 1.0, 2.0, 3.5])], vec![]));
+        children.push(node("PolygonVertexIndex", vec![Property::VecI32(vec![0, 1, -3])], vec![]));
+        children.push(node("Edges", vec![Property::VecI64(vec![1, 2, 3])], vec![]));
+        children.push(node("UV", vec![Property::VecF32(vec![0.25, 0.75])], vec![]));
+        children.push(node("Visible", vec![Property::VecBool(vec![true, false, true])], vec![]));
+        vec![node("Geometry", vec![Property::I64(1), Property::String(Ok("Cube")), Property::Bool(true)], children)]
+    }
+
+    /// Returns the hash of the first top-level node built from the tree.
+    fn tree_hash(version: i32, nodes: &[TestNode], options: &HashOptions) -> u64 {
+        let tree = tree(version, nodes);
+        let mut state = FnvHasher::new();
+        hash_node(&tree.children[0], options, &mut state);
+        state.finish()
+    }
+
+    /// Returns the hash of the first top-level node streamed from the FBX binary.
+    fn stream_hash(version: i32, nodes: &[TestNode], compression: Option<Compression>, options: &HashOptions) -> u64 {
+        let data = fbx_with_compression(version, nodes, compression);
+        let mut reader = EventReader::new(&data[..]);
+        assert!(matches!(reader.next().unwrap(), FbxEvent::StartFbx(_)));
+        let mut state = FnvHasher::new();
+        match reader.next().unwrap() {
+            FbxEvent::StartNode { name, properties } => hash_subtree(&mut reader, &name, &properties, options, &mut state).unwrap(),
+            ev => panic!("Expected `StartNode`, but got {:?}", ev),
+        }
+        // The whole subtree is consumed.
+        assert!(matches!(reader.next().unwrap(), FbxEvent::EndFbx));
+        state.finish()
+    }
+
+    #[test]
+    fn independent_of_encoding() {
+        let nodes = geometry(vec![]);
+        let options = HashOptions::new();
+        let expected = stream_hash(7400, &nodes, None, &options);
+        for &version in &[7400, 7500] {
+            for &compression in &[None, Some(Compression::None), Some(Compression::Fast), Some(Compression::Default), Some(Compression::Best)] {
+                assert_eq!(stream_hash(version, &nodes, compression, &options), expected, "version {}, compression {:?}", version, compression);
+            }
+        }
+    }
+
+    #[test]
+    fn stream_equals_tree() {
+        let nodes = geometry(vec![node("Layer", vec![Property::I32(0)], vec![node("Type", vec![Property::String(Ok("LayerElementNormal"))], vec![])])]);
+        let options = HashOptions::new();
+        for &version in &[7400, 7500] {
+            assert_eq!(stream_hash(version, &nodes, Some(Compression::Default), &options), tree_hash(version, &nodes, &options));
+        }
+    }
+
+    #[test]
+    fn content_changes_hash() {
+        let options = HashOptions::new();
+        let mut changed = geometry(vec![]);
+        changed[0].children[0] = node("Vertices", vec![Property::VecF64(vec![0.0, 1.0, 2.0, 3.0])], vec![]);
+        assert_ne!(tree_hash(7400, &changed, &options), tree_hash(7400, &geometry(vec![]), &options));
+    }
+
+    #[test]
+    fn exclude_node_skips_subtree() {
+        let excluded = node("Layer", vec![Property::I32(0)], vec![
+            node("Type", vec![Property::String(Ok("LayerElementNormal"))], vec![]),
+            node("Layer", vec![], vec![]),
+        ]);
+        let with = geometry(vec![excluded]);
+        let without = geometry(vec![]);
+        let options = HashOptions::new().exclude_node("Layer");
+        let expected = tree_hash(7400, &without, &HashOptions::new());
+        assert_eq!(tree_hash(7400, &with, &options), expected);
+        assert_eq!(stream_hash(7400, &with, None, &options), expected);
+        assert_ne!(tree_hash(7400, &with, &HashOptions::new()), expected);
+    }
+}
@@ -5,6 +5,8 @@
 //! It is similar to relation of XML and COLLADA. COLLADA is represented using XML, but XML DOM is
 //! difficult to use directly as COLLADA data.
 //! Compare FBX to COLLADA, this crate is XML reader, not COLLADA importer.
+//! Nevertheless, [`scene`](scene/index.html) module provides typed views of common FBX 7 data
+//! (such as objects and connections between them) on top of the node [`tree`](tree/index.html).
 //!
 //! This crate is specialized to read FBX binary format fastly and would *NOT* implement FBX ASCII
 //! reader or FBX writer.
@@ -15,11 +17,19 @@ extern crate flate2;
 extern crate log;
 
 pub use error::{Error, Result};
-pub use event::{FbxEvent, FbxHeaderInfo};
-pub use property::{DelayedProperties, Property, PropertiesIter};
-pub use reader::{Events, EventReader};
+pub use event::{FbxEvent, FbxHeaderInfo, NodeName};
+pub use property::{DelayedProperties, Property, PropertiesIter, split_ascii_name_class, split_name_class};
+pub use reader::{Events, EventReader, NodeSpan, ParserConfig};
+pub use tree::{Node, Tree};
 
+pub mod diff;
 pub mod error;
 pub mod event;
+pub mod hash;
 pub mod property;
 pub mod reader;
+pub mod scene;
+pub mod tree;
+
+#[cfg(test)]
+mod test_util;
@@ -271,7 +271,7 @@ impl ArrayHeader {
 /// | `vec_i64`       | `Vec<i64>`            |
 /// | `vec_f32`       | `Vec<f32>`            |
 /// | `vec_f64`       | `Vec<f64>`            |
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Property<'a> {
     /// Boolean.
     Bool(bool),
@@ -353,6 +353,56 @@ impl<'a> Property<'a> {
     }
 }
 
+impl<'a> Property<'a> {
+    /// Get string property value as object name and class, without consuming self.
+    ///
+    /// See [`split_name_class()`](fn.split_name_class.html) for detail.
+    pub fn get_name_class(&self) -> Option<(&'a str, Option<&'a str>)> {
+        self.get_string().map(split_name_class)
+    }
+}
+
+/// Splits object name string into name and class.
+///
+/// Binary FBX encodes object names as `"Cube\x00\x01Model"` (name, `"\x00\x01"`, class).
+/// The class is `None` if the string has no separator.
+/// Names may contain `"::"` (e.g. namespaces), which is not treated as a separator; use
+/// [`split_ascii_name_class()`](fn.split_ascii_name_class.html) for ASCII FBX names.
+///
+/// ```
+/// use fbx_binary_reader::property::split_name_class;
+///
+/// assert_eq!(split_name_class("Cube\u{0}\u{1}Model"), ("Cube", Some("Model")));
+/// assert_eq!(split_name_class("Character::Hips"), ("Character::Hips", None));
+/// assert_eq!(split_name_class("Cube"), ("Cube", None));
+/// ```
+pub fn split_name_class(s: &str) -> (&str, Option<&str>) {
+    match s.find("\u{0}\u{1}") {
+        Some(pos) => (&s[..pos], Some(&s[pos + 2..])),
+        None => (s, None),
+    }
+}
+
+/// Splits ASCII FBX object name string into name and class.
+///
+/// ASCII FBX encodes object names as `"Model::Cube"` (class, `"::"`, name).
+/// The string is split at the first `"::"`, so the name may contain `"::"`.
+/// The class is `None` if the string has no separator.
+///
+/// ```
+/// use fbx_binary_reader::property::split_ascii_name_class;
+///
+/// assert_eq!(split_ascii_name_class("Model::Cube"), ("Cube", Some("Model")));
+/// assert_eq!(split_ascii_name_class("Model::Character::Hips"), ("Character::Hips", Some("Model")));
+/// assert_eq!(split_ascii_name_class("Cube"), ("Cube", None));
+/// ```
+pub fn split_ascii_name_class(s: &str) -> (&str, Option<&str>) {
+    match s.find("::") {
+        Some(pos) => (&s[pos + 2..], Some(&s[..pos])),
+        None => (s, None),
+    }
+}
+
 
 // Not convert type, consume self.
 macro_rules! implement_getter_extract {
@@ -545,3 +595,46 @@ fn read_property_array_from_plain_stream<R: Read>(reader: &mut R, header: &Array
         _ => unreachable!(),
     })
 }
+
+
+#[cfg(test)]
+mod tests {
+    use test_util::{node, tree};
+    use super::{Property, split_ascii_name_class, split_name_class};
+
+    #[test]
+    fn bool_round_trip() {
+        let tree = tree(7400, &[node("B", vec![Property::Bool(true), Property::Bool(false)], vec![])]);
+        let values = tree.children[0].properties.iter().map(|prop| match prop {
+            Property::Bool(v) => v,
+            prop => panic!("Expected boolean, but got {:?}", prop),
+        }).collect::<Vec<_>>();
+        assert_eq!(values, [true, false]);
+    }
+
+    #[test]
+    fn binary_name_class() {
+        assert_eq!(split_name_class("Cube\u{0}\u{1}Model"), ("Cube", Some("Model")));
+        assert_eq!(split_name_class("\u{0}\u{1}NodeAttribute"), ("", Some("NodeAttribute")));
+        assert_eq!(split_name_class("Cube"), ("Cube", None));
+        assert_eq!(split_name_class(""), ("", None));
+    }
+
+    #[test]
+    fn binary_name_with_colons() {
+        assert_eq!(split_name_class("Character::Hips"), ("Character::Hips", None));
+        assert_eq!(split_name_class("Character::Hips\u{0}\u{1}Model"), ("Character::Hips", Some("Model")));
+    }
+
+    #[test]
+    fn ascii_name_class() {
+        assert_eq!(split_ascii_name_class("Model::Cube"), ("Cube", Some("Model")));
+        assert_eq!(split_ascii_name_class("NodeAttribute::"), ("", Some("NodeAttribute")));
+        assert_eq!(split_ascii_name_class("Cube"), ("Cube", None));
+    }
+
+    #[test]
+    fn ascii_name_with_colons() {
+        assert_eq!(split_ascii_name_class("Model::Character::Hips"), ("Character::Hips", Some("Model")));
+    }
+}
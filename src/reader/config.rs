@@ -0,0 +1,72 @@
+//! Contains parser configuration structure.
+
+use std::io::Read;
+use reader::EventReader;
+
+
+/// Parser configuration structure.
+///
+/// Use [`create_reader()`](#method.create_reader) or
+/// [`EventReader::new_with_config()`](struct.EventReader.html#method.new_with_config) to create
+/// a reader with the configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParserConfig {
+    /// Whether node names are interned.
+    ///
+    /// If true, node names are shared among all nodes with the same name (which is usual for
+    /// nodes such as `P`, `C` and `Properties70`), and each node name costs no allocations after
+    /// the first occurrence.
+    /// Well-known FBX node names are interned in advance.
+    /// The number of interned names is bounded, and names beyond the bound are allocated for
+    /// each node as if interning is disabled.
+    ///
+    /// If false, a new string is allocated for each node.
+    ///
+    /// Default is `true`.
+    pub intern_node_names: bool,
+    /// Maximum byte size of node properties to be read into memory.
+    ///
+    /// Properties of a node larger than this are skipped without being buffered, and the node is
+    /// reported with empty properties.
+    /// Use [`EventReader::skipped_properties()`](struct.EventReader.html#method.skipped_properties)
+    /// to know whether the properties of the last node were skipped and where they are.
+    ///
+    /// This is useful to scan files with huge embedded data (such as textures and movies) when
+    /// the data is not needed or is read separately from a seekable stream.
+    ///
+    /// Default is `None` (no limit).
+    pub max_property_buffer_size: Option<u64>,
+}
+
+impl ParserConfig {
+    /// Creates a new config with default options.
+    pub fn new() -> Self {
+        ParserConfig {
+            intern_node_names: true,
+            max_property_buffer_size: None,
+        }
+    }
+
+    /// Sets the value of `intern_node_names` and returns the config.
+    pub fn intern_node_names(mut self, value: bool) -> Self {
+        self.intern_node_names = value;
+        self
+    }
+
+    /// Sets the value of `max_property_buffer_size` and returns the config.
+    pub fn max_property_buffer_size(mut self, value: Option<u64>) -> Self {
+        self.max_property_buffer_size = value;
+        self
+    }
+
+    /// Creates an FBX reader with this configuration.
+    pub fn create_reader<R: Read>(self, source: R) -> EventReader<R> {
+        EventReader::new_with_config(source, self)
+    }
+}
+
+impl Default for ParserConfig {
+    fn default() -> Self {
+        ParserConfig::new()
+    }
+}
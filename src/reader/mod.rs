@@ -1,30 +1,194 @@
 //! Contains interface for a pull-based (StAX-like) FBX parser.
 
-use std::io::Read;
-use error::Result;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use error::{Error, Result};
 use event::FbxEvent;
 
+pub use self::config::ParserConfig;
+pub use self::span::{NodeSpan, relocate_node};
+
+mod config;
+mod names;
 mod parser;
+mod span;
 
 
 /// A wrapper around an `std::io::Read` instance which provides pull-based FBX parsing.
 pub struct EventReader<R: Read> {
     source: R,
     parser: parser::Parser,
+    /// An event read by `peek()` and not yet returned by `next()`, its node span and skipped
+    /// properties span.
+    peeked: Option<(Result<FbxEvent>, Option<NodeSpan>, Option<NodeSpan>)>,
+    /// Span of the node started by the last `StartNode` event returned by `next()`.
+    last_node_span: Option<NodeSpan>,
+    /// Span of the skipped properties of the node started by the last `StartNode` event.
+    skipped_properties: Option<NodeSpan>,
 }
 
 impl<R: Read> EventReader<R> {
     /// Creates a new reader, consuming the given stream.
     pub fn new(source: R) -> Self {
+        EventReader::new_with_config(source, ParserConfig::new())
+    }
+
+    /// Creates a new reader with the provided configuration, consuming the given stream.
+    pub fn new_with_config(source: R, config: ParserConfig) -> Self {
         EventReader {
             source: source,
-            parser: parser::Parser::new(),
+            parser: parser::Parser::new(config),
+            peeked: None,
+            last_node_span: None,
+            skipped_properties: None,
         }
     }
 
     /// Pulls and returns next FBX event from the stream.
+    ///
+    /// If the event is already read by [`peek()`](#method.peek), returns it.
     pub fn next(&mut self) -> Result<FbxEvent> {
-        self.parser.next(&mut self.source)
+        let (ev, span, skipped) = match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.pull(),
+        };
+        if let Ok(FbxEvent::StartNode { .. }) = ev {
+            self.last_node_span = span;
+            self.skipped_properties = skipped;
+        }
+        ev
+    }
+
+    /// Returns a reference to the next FBX event without consuming it.
+    ///
+    /// The event is buffered and will be returned by the following call of
+    /// [`next()`](#method.next).
+    pub fn peek(&mut self) -> Result<&FbxEvent> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.pull());
+        }
+        match self.peeked {
+            Some((Ok(ref ev), _, _)) => Ok(ev),
+            Some((Err(ref err), _, _)) => Err(err.clone()),
+            None => unreachable!(),
+        }
+    }
+
+    /// Skips the rest of the innermost open node.
+    ///
+    /// Reads and discards events up to and including the `EndNode` event which closes the
+    /// innermost node not closed yet.
+    /// Called right after a `StartNode` event, this skips that node (including its children).
+    /// Called right after an `EndNode` event, this skips the rest of the parent of the closed
+    /// node, i.e. its remaining siblings and then the parent's `EndNode`.
+    ///
+    /// An event buffered by [`peek()`](#method.peek) is regarded as not read yet, and is
+    /// skipped as well.
+    pub fn skip_node(&mut self) -> Result<()> {
+        let mut depth = 0_usize;
+        loop {
+            match self.next()? {
+                FbxEvent::StartNode { .. } => depth += 1,
+                FbxEvent::EndNode => {
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                    depth -= 1;
+                },
+                ev => return Err(Error::UnexpectedValue(format!("Expected `StartNode` or `EndNode` event, but got {:?}", ev))),
+            }
+        }
+    }
+
+    /// Returns the byte range of the node started by the last `StartNode` event returned by
+    /// [`next()`](#method.next).
+    ///
+    /// Returns `None` if no nodes are read yet.
+    ///
+    /// Use [`NodeSpan::slice()`](struct.NodeSpan.html#method.slice) to get the raw bytes of the
+    /// node from FBX data on memory, or [`copy_node_to()`](#method.copy_node_to) to get them
+    /// from seekable streams.
+    pub fn last_node_span(&self) -> Option<NodeSpan> {
+        self.last_node_span
+    }
+
+    /// Returns the byte range of the properties of the node started by the last `StartNode`
+    /// event, if they are skipped without being read.
+    ///
+    /// Properties are skipped when they are larger than
+    /// [`ParserConfig::max_property_buffer_size`](struct.ParserConfig.html#structfield.max_property_buffer_size).
+    /// Returns `None` if the properties are read as usual.
+    pub fn skipped_properties(&self) -> Option<NodeSpan> {
+        self.skipped_properties
+    }
+
+    /// Reads the next event from the parser, with the span of the node and of the skipped
+    /// properties if it is `StartNode`.
+    fn pull(&mut self) -> (Result<FbxEvent>, Option<NodeSpan>, Option<NodeSpan>) {
+        let ev = self.parser.next(&mut self.source);
+        match ev {
+            Ok(FbxEvent::StartNode { .. }) => (ev, self.parser.last_node_span(), self.parser.skipped_properties()),
+            _ => (ev, None, None),
+        }
+    }
+}
+
+impl<R: Read + Seek> EventReader<R> {
+    /// Copies the raw bytes of the node at the given span to the writer.
+    ///
+    /// The bytes include the node record header, name, properties and all children.
+    /// The source stream is restored to the current position after copying, so the reader can
+    /// continue parsing.
+    ///
+    /// The source stream should be positioned at the beginning of the FBX data when the reader
+    /// is created.
+    /// Note that end offsets in the copied data are absolute positions in the source FBX data;
+    /// use [`relocate_node()`](fn.relocate_node.html) to place the bytes at another position.
+    ///
+    /// Returns an error if `span.start` is after `span.end`.
+    pub fn copy_node_to<W: Write>(&mut self, span: NodeSpan, writer: &mut W) -> Result<u64> {
+        let copied = self.read_span_with(span, |source| Ok(io::copy(source, writer)?))?;
+        if copied != span.len() {
+            return Err(Error::UnexpectedEof);
+        }
+        Ok(copied)
+    }
+
+    /// Reads the raw bytes of the node at the given span.
+    ///
+    /// See [`copy_node_to()`](#method.copy_node_to) for detail.
+    pub fn read_node_bytes(&mut self, span: NodeSpan) -> Result<Vec<u8>> {
+        if span.start > span.end {
+            return Err(Error::DataError(format!("Invalid span: {:?}", span)));
+        }
+        let mut buffer = Vec::with_capacity(span.len() as usize);
+        self.copy_node_to(span, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Calls `f` with a stream of the bytes at the given span.
+    ///
+    /// The stream ends at the end of the span, and the source stream is restored to the current
+    /// position after `f` returns, so the reader can continue parsing.
+    /// This can be used to read properties skipped by the parser (see
+    /// [`skipped_properties()`](#method.skipped_properties)) without buffering them.
+    ///
+    /// The source stream should be positioned at the beginning of the FBX data when the reader
+    /// is created.
+    pub fn read_span_with<T, F>(&mut self, span: NodeSpan, f: F) -> Result<T>
+        where F: FnOnce(&mut dyn Read) -> Result<T>
+    {
+        if span.start > span.end {
+            return Err(Error::DataError(format!("Invalid span: {:?}", span)));
+        }
+        let current = self.source.stream_position()?;
+        let base = match current.checked_sub(self.parser.position()) {
+            Some(base) => base,
+            None => return Err(Error::DataError("Source stream is positioned before the beginning of the FBX data".to_owned())),
+        };
+        self.source.seek(SeekFrom::Start(base + span.start))?;
+        let result = f(&mut (&mut self.source).take(span.len()));
+        self.source.seek(SeekFrom::Start(current))?;
+        result
     }
 }
 
@@ -75,3 +239,132 @@ impl<R: Read> Iterator for Events<R> {
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use event::{FbxEvent, NodeName};
+    use reader::ParserConfig;
+    use test_util::{fbx, node, TestNode};
+    use super::EventReader;
+
+    /// Returns `A { B { C }, D }, E`.
+    fn nodes() -> Vec<TestNode<'static>> {
+        vec![
+            node("A", vec![], vec![
+                node("B", vec![], vec![node("C", vec![], vec![])]),
+                node("D", vec![], vec![]),
+            ]),
+            node("E", vec![], vec![]),
+        ]
+    }
+
+    /// Returns the name of the node if the event is `StartNode`, or the debug string otherwise.
+    fn describe(ev: &FbxEvent) -> String {
+        match *ev {
+            FbxEvent::StartNode { ref name, .. } => (**name).to_owned(),
+            ref ev => format!("{:?}", ev),
+        }
+    }
+
+    #[test]
+    fn peek_then_next() {
+        let data = fbx(7400, &nodes());
+        let mut plain = EventReader::new(&data[..]);
+        let mut peeking = EventReader::new(&data[..]);
+        loop {
+            let peeked = describe(peeking.peek().unwrap());
+            // Peeking again returns the same event.
+            assert_eq!(describe(peeking.peek().unwrap()), peeked);
+            let ev = peeking.next().unwrap();
+            assert_eq!(describe(&ev), peeked);
+            assert_eq!(describe(&plain.next().unwrap()), peeked);
+            assert_eq!(peeking.last_node_span(), plain.last_node_span());
+            assert_eq!(peeking.skipped_properties(), plain.skipped_properties());
+            if let FbxEvent::EndFbx = ev {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn peek_does_not_update_span() {
+        let data = fbx(7400, &nodes());
+        let mut reader = EventReader::new(&data[..]);
+        reader.next().unwrap();
+        reader.peek().unwrap();
+        assert_eq!(reader.last_node_span(), None);
+        reader.next().unwrap();
+        let span = reader.last_node_span().unwrap();
+        reader.peek().unwrap();
+        assert_eq!(reader.last_node_span(), Some(span));
+    }
+
+    #[test]
+    fn peek_error_is_returned_by_next() {
+        let mut data = fbx(7400, &nodes());
+        // Cut in the middle of the node name of `A`.
+        data.truncate(27 + 13);
+        let mut reader = EventReader::new(&data[..]);
+        reader.next().unwrap();
+        let peeked = reader.peek().unwrap_err().to_string();
+        assert_eq!(reader.next().unwrap_err().to_string(), peeked);
+    }
+
+    #[test]
+    fn skip_node_resumes_at_sibling() {
+        let data = fbx(7400, &nodes());
+        let mut reader = EventReader::new(&data[..]);
+        reader.next().unwrap();
+        assert_eq!(describe(&reader.next().unwrap()), "A");
+        assert_eq!(describe(&reader.next().unwrap()), "B");
+        reader.skip_node().unwrap();
+        assert_eq!(describe(&reader.next().unwrap()), "D");
+        assert!(matches!(reader.next().unwrap(), FbxEvent::EndNode));
+        reader.skip_node().unwrap();
+        assert_eq!(describe(&reader.next().unwrap()), "E");
+    }
+
+    #[test]
+    fn skip_node_after_peek() {
+        let data = fbx(7400, &nodes());
+        let mut reader = EventReader::new(&data[..]);
+        reader.next().unwrap();
+        reader.next().unwrap();
+        // The peeked `StartNode` of `B` is a child of `A`, and skipped with it.
+        assert_eq!(describe(reader.peek().unwrap()), "B");
+        reader.skip_node().unwrap();
+        assert_eq!(describe(&reader.next().unwrap()), "E");
+    }
+
+    /// Returns names of all `StartNode` events.
+    fn names(mut reader: EventReader<&[u8]>) -> Vec<NodeName> {
+        let mut names = Vec::new();
+        loop {
+            match reader.next().unwrap() {
+                FbxEvent::StartNode { name, .. } => names.push(name),
+                FbxEvent::EndFbx => return names,
+                _ => {},
+            }
+        }
+    }
+
+    #[test]
+    fn interned_names() {
+        let nodes = [node("Properties70", vec![], vec![
+            node("P", vec![], vec![]),
+            node("MyNode", vec![], vec![]),
+            node("P", vec![], vec![]),
+            node("MyNode", vec![], vec![]),
+        ])];
+        let data = fbx(7400, &nodes);
+        let interned = names(EventReader::new(&data[..]));
+        assert_eq!(interned.iter().map(|name| &**name).collect::<Vec<_>>(), ["Properties70", "P", "MyNode", "P", "MyNode"]);
+        assert!(NodeName::ptr_eq(&interned[1], &interned[3]));
+        assert!(NodeName::ptr_eq(&interned[2], &interned[4]));
+
+        let plain = names(ParserConfig::new().intern_node_names(false).create_reader(&data[..]));
+        assert_eq!(plain, interned);
+        assert!(!NodeName::ptr_eq(&plain[1], &plain[3]));
+    }
+}
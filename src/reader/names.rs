@@ -0,0 +1,128 @@
+//! Contains node name interner.
+
+use std::collections::HashSet;
+use std::str;
+use std::sync::Arc;
+use error::{Error, Result};
+use event::NodeName;
+
+
+/// Node names which appear frequently in FBX files exported by popular tools.
+const WELL_KNOWN_NAMES: &[&str] = &[
+    // Top-level nodes.
+    "FBXHeaderExtension", "FileId", "CreationTime", "Creator", "GlobalSettings", "Documents",
+    "References", "Definitions", "Objects", "Connections", "Takes",
+    // Header extension.
+    "FBXHeaderVersion", "FBXVersion", "EncryptionType", "CreationTimeStamp", "Version", "Year",
+    "Month", "Day", "Hour", "Minute", "Second", "Millisecond", "SceneInfo", "Type", "MetaData",
+    "Title", "Subject", "Author", "Keywords", "Revision", "Comment",
+    // Generic properties.
+    "Properties70", "P",
+    // Documents and definitions.
+    "Count", "Document", "RootNode", "ObjectType", "PropertyTemplate",
+    // Objects.
+    "NodeAttribute", "Geometry", "Model", "Material", "Texture", "Video", "Deformer", "Pose",
+    "AnimationStack", "AnimationLayer", "AnimationCurveNode", "AnimationCurve",
+    // Geometry.
+    "GeometryVersion", "Vertices", "PolygonVertexIndex", "Edges", "Name",
+    "MappingInformationType", "ReferenceInformationType", "LayerElementNormal", "Normals",
+    "NormalsW", "LayerElementBinormal", "Binormals", "BinormalsW", "LayerElementTangent",
+    "Tangents", "TangentsW", "LayerElementUV", "UV", "UVIndex", "LayerElementColor", "Colors",
+    "ColorIndex", "LayerElementMaterial", "Materials", "LayerElementSmoothing", "Smoothing",
+    "Layer", "LayerElement", "TypedIndex", "Shape", "Indexes",
+    // Models and materials.
+    "MultiLayer", "MultiTake", "Shading", "Culling", "ShadingModel",
+    // Textures and videos.
+    "TextureName", "Media", "FileName", "RelativeFilename", "ModelUVTranslation",
+    "ModelUVScaling", "Texture_Alpha_Source", "Cropping", "UseMipMap", "Filename", "Content",
+    // Deformers and poses.
+    "Weights", "Transform", "TransformLink", "Mode", "DeformPercent", "FullWeights",
+    "PoseNode", "Node", "Matrix", "NbPoseNodes",
+    // Animations.
+    "KeyVer", "Default", "KeyTime", "KeyValueFloat", "KeyAttrFlags", "KeyAttrDataFloat",
+    "KeyAttrRefCount",
+    // Connections and takes.
+    "C", "Current", "Take", "LocalTime", "ReferenceTime",
+];
+
+
+/// Maximum number of names interned in addition to well-known names.
+///
+/// Files with many distinct node names (which is unusual, or adversarial) would otherwise grow
+/// the table without bound.
+const MAX_INTERNED_NAMES: usize = 4096;
+
+
+/// Node name table.
+///
+/// If interning is disabled, the table is always empty and every name is an owned string.
+/// Once the table is full, names not in the table are owned strings without being interned.
+#[derive(Debug)]
+pub struct NameTable {
+    names: Option<HashSet<Arc<str>>>,
+    /// Maximum number of names in the table.
+    capacity: usize,
+}
+
+impl NameTable {
+    /// Constructs a name table.
+    pub fn new(intern: bool) -> Self {
+        let names = if intern {
+            Some(WELL_KNOWN_NAMES.iter().map(|&name| Arc::from(name)).collect())
+        } else {
+            None
+        };
+        NameTable {
+            names,
+            capacity: WELL_KNOWN_NAMES.len() + MAX_INTERNED_NAMES,
+        }
+    }
+
+    /// Returns the name for the given raw node name, shared if interned.
+    pub fn get(&mut self, raw: &[u8]) -> Result<NodeName> {
+        let name = str::from_utf8(raw).map_err(Error::Utf8Error)?;
+        let names = match self.names {
+            Some(ref mut names) => names,
+            None => return Ok(NodeName::from(name)),
+        };
+        if let Some(interned) = names.get(name) {
+            return Ok(NodeName::shared(interned.clone()));
+        }
+        if names.len() >= self.capacity {
+            return Ok(NodeName::from(name));
+        }
+        let interned: Arc<str> = Arc::from(name);
+        names.insert(interned.clone());
+        Ok(NodeName::shared(interned))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use event::NodeName;
+    use super::{MAX_INTERNED_NAMES, NameTable, WELL_KNOWN_NAMES};
+
+    #[test]
+    fn table_is_bounded() {
+        let mut table = NameTable::new(true);
+        for i in 0..MAX_INTERNED_NAMES + 10 {
+            table.get(format!("Node{}", i).as_bytes()).unwrap();
+        }
+        assert_eq!(table.names.as_ref().unwrap().len(), WELL_KNOWN_NAMES.len() + MAX_INTERNED_NAMES);
+        // Names interned before the table got full are still shared.
+        assert!(NodeName::ptr_eq(&table.get(b"Node0").unwrap(), &table.get(b"Node0").unwrap()));
+        assert!(NodeName::ptr_eq(&table.get(b"P").unwrap(), &table.get(b"P").unwrap()));
+        // Names after that are correct, but not shared.
+        let name = format!("Node{}", MAX_INTERNED_NAMES + 5);
+        let (a, b) = (table.get(name.as_bytes()).unwrap(), table.get(name.as_bytes()).unwrap());
+        assert_eq!((&*a, &*b), (&name[..], &name[..]));
+        assert!(!NodeName::ptr_eq(&a, &b));
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        assert!(NameTable::new(true).get(b"\xff").is_err());
+        assert!(NameTable::new(false).get(b"\xff").is_err());
+    }
+}
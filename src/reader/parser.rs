@@ -1,9 +1,12 @@
 //! Contains implementations of FBX parsers.
 
-use std::io::Read;
+use std::io::{self, Read};
 use error::{Error, Result};
 use event::{FbxEvent, FbxHeaderInfo};
 use property::DelayedProperties;
+use reader::config::ParserConfig;
+use reader::names::NameTable;
+use reader::span::NodeSpan;
 
 
 #[macro_use]
@@ -76,14 +79,6 @@ mod macros {
             try_read_generic!($reader.read_exact(buf), $pos, buf.len());
         })
     }
-    macro_rules! try_read_fixstr {
-        ($reader:expr, $pos:expr, $len:expr) => ({
-            let len = $len as usize;
-            let mut buf = vec![0; len];
-            try_read_generic!($reader.read_exact(&mut buf), $pos, len);
-            try!(String::from_utf8(buf))
-        })
-    }
 }
 
 
@@ -100,19 +95,48 @@ pub struct Parser {
     version: i32,
     pos: usize,
     end_offset_stack: Vec<u64>,
+    names: NameTable,
+    /// Buffer to read node names into, reused across nodes.
+    name_buf: Vec<u8>,
+    /// Span of the node most recently started.
+    last_node_span: Option<NodeSpan>,
+    /// Maximum byte size of properties to be buffered.
+    max_property_buffer_size: Option<u64>,
+    /// Span of the properties of the node most recently started, if they are skipped.
+    skipped_properties: Option<NodeSpan>,
 }
 
 impl Parser {
     /// Constructs a parser.
-    pub fn new() -> Self {
+    pub fn new(config: ParserConfig) -> Self {
         Parser {
             state: State::ReadingMagic,
             version: ::std::i32::MIN,
             pos: 0,
             end_offset_stack: vec![],
+            names: NameTable::new(config.intern_node_names),
+            name_buf: Vec::with_capacity(256),
+            last_node_span: None,
+            max_property_buffer_size: config.max_property_buffer_size,
+            skipped_properties: None,
         }
     }
 
+    /// Returns the current position in the stream.
+    pub fn position(&self) -> u64 {
+        self.pos as u64
+    }
+
+    /// Returns the span of the node most recently started.
+    pub fn last_node_span(&self) -> Option<NodeSpan> {
+        self.last_node_span
+    }
+
+    /// Returns the span of the properties of the node most recently started, if they are skipped.
+    pub fn skipped_properties(&self) -> Option<NodeSpan> {
+        self.skipped_properties
+    }
+
     /// Gets next `FbxEvent`.
     pub fn next<R: Read>(&mut self, reader: &mut R) -> Result<FbxEvent> {
         let result = match self.state {
@@ -161,6 +185,7 @@ impl Parser {
         }
         let version = try_read_i32!(reader, self.pos);
         debug!("magic binary read, FBX binary (version={})", version);
+        self.version = version;
         self.state = State::ReadingNodes;
 
         Ok(FbxEvent::StartFbx(FbxHeaderInfo {
@@ -179,6 +204,7 @@ impl Parser {
         }
 
         // Read a node record header.
+        let node_start = self.pos as u64;
         let node_record_header = try!(NodeRecordHeader::read_from(reader, &mut self.pos, self.version));
         if node_record_header.is_null_record() {
             // End of a node.
@@ -206,13 +232,35 @@ impl Parser {
         } else {
             // Start of a node.
             self.end_offset_stack.push(node_record_header.end_offset);
+            self.last_node_span = Some(NodeSpan {
+                start: node_start,
+                end: node_record_header.end_offset,
+            });
         }
 
         // Read the node name.
-        let name = try_read_fixstr!(reader, self.pos, node_record_header.name_len);
+        let name = {
+            self.name_buf.resize(node_record_header.name_len as usize, 0);
+            try_read_exact!(reader, self.pos, &mut self.name_buf[..]);
+            self.names.get(&self.name_buf)?
+        };
 
         // Read the properties.
-        let properties = {
+        let property_byte_len = node_record_header.property_byte_len;
+        self.skipped_properties = None;
+        let properties = if self.max_property_buffer_size.map_or(false, |max| property_byte_len > max) {
+            let start = self.pos as u64;
+            let skipped = io::copy(&mut reader.by_ref().take(property_byte_len), &mut io::sink())?;
+            self.pos += skipped as usize;
+            if skipped != property_byte_len {
+                return Err(Error::UnexpectedEof);
+            }
+            self.skipped_properties = Some(NodeSpan {
+                start,
+                end: start + property_byte_len,
+            });
+            DelayedProperties::from_vec_u8(vec![], self.version, 0)
+        } else {
             let mut properties_raw = vec![0; node_record_header.property_byte_len as usize];
             try_read_exact!(reader, self.pos, &mut properties_raw);
             DelayedProperties::from_vec_u8(properties_raw, self.version, node_record_header.num_properties as usize)
@@ -271,3 +319,104 @@ impl NodeRecordHeader {
             && self.name_len == 0
     }
 }
+
+
+/// Rewrites end offsets of the node record at the beginning of `buffer` and its descendants.
+///
+/// `buffer` should contain the whole node, and `delta` is added to all end offsets.
+/// Returns the length of the node.
+pub fn relocate_node_record(buffer: &mut [u8], fbx_version: i32, old_start: u64, delta: i64) -> Result<usize> {
+    use byteorder::{ByteOrder, LittleEndian};
+
+    let mut pos = 0;
+    let header = NodeRecordHeader::read_from(&mut &buffer[..], &mut pos, fbx_version)?;
+    if header.is_null_record() {
+        return Err(Error::DataError("Expected a node record, but got a null record".to_owned()));
+    }
+    let end = match header.end_offset.checked_sub(old_start) {
+        Some(end) if end as usize <= buffer.len() => end as usize,
+        _ => return Err(Error::DataError(format!("Node end offset {} is out of the node data", header.end_offset))),
+    };
+    let new_end_offset = (header.end_offset as i64).wrapping_add(delta);
+    if fbx_version < 7500 {
+        if new_end_offset < 0 || new_end_offset > u32::MAX as i64 {
+            return Err(Error::DataError(format!("Node end offset {} cannot be represented in FBX {}", new_end_offset, fbx_version)));
+        }
+        LittleEndian::write_u32(&mut buffer[0..4], new_end_offset as u32);
+    } else {
+        if new_end_offset < 0 {
+            return Err(Error::DataError(format!("Node end offset {} is negative", new_end_offset)));
+        }
+        LittleEndian::write_u64(&mut buffer[0..8], new_end_offset as u64);
+    }
+
+    // Skip the name and the properties.
+    pos += header.name_len as usize + header.property_byte_len as usize;
+    // Relocate children.
+    while pos < end {
+        let child_start = old_start + pos as u64;
+        let mut next_pos = pos;
+        let child_header = NodeRecordHeader::read_from(&mut &buffer[pos..end], &mut next_pos, fbx_version)?;
+        if child_header.is_null_record() {
+            pos = next_pos;
+            continue;
+        }
+        pos += relocate_node_record(&mut buffer[pos..end], fbx_version, child_start, delta)?;
+    }
+    if pos != end {
+        return Err(Error::DataError(format!("Node does not end at expected position (expected {}, now at {})", end, pos)));
+    }
+
+    Ok(end)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use event::FbxEvent;
+    use reader::EventReader;
+
+    /// Returns FBX binary with a single empty node named `Node`.
+    fn single_node_fbx(version: u32) -> Vec<u8> {
+        let mut data = b"Kaydara FBX Binary  \0\x1a\0".to_vec();
+        data.extend_from_slice(&version.to_le_bytes());
+        let header_len = if version < 7500 { 13 } else { 25 };
+        let end_offset = (data.len() + header_len + 4) as u64;
+        if version < 7500 {
+            for field in &[end_offset as u32, 0, 0] {
+                data.extend_from_slice(&field.to_le_bytes());
+            }
+        } else {
+            for field in &[end_offset, 0, 0] {
+                data.extend_from_slice(&field.to_le_bytes());
+            }
+        }
+        data.push(4);
+        data.extend_from_slice(b"Node");
+        // Null record as the end marker of the implicit root node.
+        data.extend(vec![0; header_len]);
+        data
+    }
+
+    fn read_node_names(data: &[u8]) -> Vec<String> {
+        let mut reader = EventReader::new(data);
+        let mut names = Vec::new();
+        loop {
+            match reader.next().unwrap() {
+                FbxEvent::StartNode { ref name, .. } => names.push((**name).to_owned()),
+                FbxEvent::EndFbx => return names,
+                _ => {},
+            }
+        }
+    }
+
+    #[test]
+    fn node_header_32bit() {
+        assert_eq!(read_node_names(&single_node_fbx(7400)), ["Node"]);
+    }
+
+    #[test]
+    fn node_header_64bit() {
+        assert_eq!(read_node_names(&single_node_fbx(7500)), ["Node"]);
+    }
+}
@@ -0,0 +1,167 @@
+//! Contains byte range of a node in an FBX stream.
+
+use error::{Error, Result};
+use reader::parser;
+
+
+/// Byte range of a node record in an FBX stream, including its children.
+///
+/// Offsets are relative to the beginning of the FBX data (i.e. the first byte of the magic
+/// binary).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NodeSpan {
+    /// Position of the beginning of the node record header.
+    pub start: u64,
+    /// Position of the end of the node (the end offset in the node record header).
+    pub end: u64,
+}
+
+impl NodeSpan {
+    /// Returns the byte length of the node.
+    ///
+    /// Returns `0` for an invalid span whose `start` is after `end`.
+    pub fn len(&self) -> u64 {
+        self.end.saturating_sub(self.start)
+    }
+
+    /// Returns whether the node is empty.
+    ///
+    /// Valid node records are never empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the raw bytes of the node in the given whole FBX data.
+    ///
+    /// Returns `None` if the span is out of the data.
+    pub fn slice<'a>(&self, fbx: &'a [u8]) -> Option<&'a [u8]> {
+        if self.start > self.end || self.end > fbx.len() as u64 {
+            return None;
+        }
+        Some(&fbx[self.start as usize..self.end as usize])
+    }
+}
+
+/// Rewrites end offsets in the raw node bytes to place them at the given position.
+///
+/// Node record headers have absolute end offsets, so raw bytes of a node (and its children)
+/// must be relocated before they are spliced into another position or another file.
+/// `node` should be the raw bytes of the node which was at `span`, and `fbx_version` should be
+/// the version of the FBX data (node record header width differs by version).
+pub fn relocate_node(node: &mut [u8], fbx_version: i32, span: NodeSpan, new_start: u64) -> Result<()> {
+    if span.start > span.end {
+        return Err(Error::DataError(format!("Invalid span: {:?}", span)));
+    }
+    if node.len() as u64 != span.len() {
+        return Err(Error::DataError(format!("Node data length {} does not match span length {}", node.len(), span.len())));
+    }
+    let delta = (new_start as i64).wrapping_sub(span.start as i64);
+    parser::relocate_node_record(node, fbx_version, span.start, delta)?;
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use event::FbxEvent;
+    use property::Property;
+    use reader::EventReader;
+    use test_util::{fbx, node, null_record_len, TestNode};
+    use super::{NodeSpan, relocate_node};
+
+    /// Length of the magic binary and the version.
+    const HEADER_LEN: u64 = 27;
+
+    fn filler() -> TestNode<'static> {
+        node("Filler", vec![Property::String(Ok("some padding"))], vec![])
+    }
+
+    fn target() -> TestNode<'static> {
+        node("Target", vec![Property::I32(1)], vec![
+            node("Child", vec![], vec![node("Grandchild", vec![Property::F64(2.0)], vec![])]),
+            node("Sibling", vec![Property::VecI32(vec![3, 4])], vec![]),
+        ])
+    }
+
+    /// Returns raw bytes of the top-level nodes, without the file header and the end marker.
+    fn raw_nodes(version: i32, nodes: &[TestNode]) -> Vec<u8> {
+        let data = fbx(version, nodes);
+        data[HEADER_LEN as usize..data.len() - null_record_len(version)].to_vec()
+    }
+
+    /// Returns events of the data as strings, with spans of the `StartNode` events.
+    fn events(data: &[u8]) -> Vec<(String, Option<NodeSpan>)> {
+        let mut reader = EventReader::new(data);
+        let mut events = Vec::new();
+        loop {
+            let ev = reader.next().unwrap();
+            match ev {
+                FbxEvent::StartNode { ref name, ref properties } => {
+                    let ev = format!("{} {:?}", name, properties.iter().collect::<Vec<_>>());
+                    events.push((ev, reader.last_node_span()));
+                },
+                FbxEvent::EndFbx => return events,
+                ref ev => events.push((format!("{:?}", ev), None)),
+            }
+        }
+    }
+
+    fn span_of(data: &[u8], name: &str) -> NodeSpan {
+        events(data).into_iter().find(|(ev, _)| ev.starts_with(name)).and_then(|(_, span)| span).unwrap()
+    }
+
+    /// Relocates `target()` in `old` to the end of `prefix`, and returns the new FBX data.
+    fn relocate(version: i32, old: &[TestNode], prefix: &[TestNode]) -> Vec<u8> {
+        let old_data = fbx(version, old);
+        let span = span_of(&old_data, "Target ");
+        let mut bytes = span.slice(&old_data).unwrap().to_vec();
+        let new_start = HEADER_LEN + raw_nodes(version, prefix).len() as u64;
+        relocate_node(&mut bytes, version, span, new_start).unwrap();
+
+        let mut data = fbx(version, prefix);
+        data.truncate(data.len() - null_record_len(version));
+        data.extend(bytes);
+        data.extend(vec![0; null_record_len(version)]);
+        data
+    }
+
+    fn check_round_trip(version: i32) {
+        let expected = fbx(version, &[filler(), target()]);
+        // Move forward.
+        let moved = relocate(version, &[target()], &[filler()]);
+        assert_eq!(moved, expected);
+        assert_eq!(events(&moved), events(&expected));
+        // Move backward.
+        let moved = relocate(version, &[filler(), target()], &[]);
+        assert_eq!(events(&moved), events(&fbx(version, &[target()])));
+    }
+
+    #[test]
+    fn relocate_32bit() {
+        check_round_trip(7400);
+    }
+
+    #[test]
+    fn relocate_64bit() {
+        check_round_trip(7500);
+    }
+
+    #[test]
+    fn relocate_length_mismatch() {
+        let data = fbx(7400, &[target()]);
+        let span = span_of(&data, "Target ");
+        let mut bytes = span.slice(&data).unwrap()[1..].to_vec();
+        assert!(relocate_node(&mut bytes, 7400, span, 0).is_err());
+        let mut bytes = span.slice(&data).unwrap().to_vec();
+        let invalid = NodeSpan { start: span.end, end: span.start };
+        assert!(relocate_node(&mut bytes, 7400, invalid, 0).is_err());
+    }
+
+    #[test]
+    fn span_len() {
+        assert_eq!(NodeSpan { start: 3, end: 10 }.len(), 7);
+        assert_eq!(NodeSpan { start: 10, end: 3 }.len(), 0);
+        assert!(NodeSpan { start: 10, end: 3 }.is_empty());
+        assert_eq!(NodeSpan { start: 10, end: 3 }.slice(&[0; 16]), None);
+    }
+}
@@ -0,0 +1,1060 @@
+//! Contains animation stacks, layers, curve nodes and curves.
+//!
+//! Animation objects have the following connections:
+//!
+//! ```text
+//! AnimStack <- AnimLayer <- AnimCurveNode <- AnimCurve
+//!                                 |
+//!                                 +-> Model (or other object), via `OP` with property name
+//! ```
+//!
+//! An `AnimCurveNode` animates one property (such as `Lcl Translation`) of an object, and has
+//! a channel for each component of the property (such as `d|X`, `d|Y` and `d|Z`).
+//! Each channel may be connected to an `AnimCurve`, which has keyframes.
+
+use std::cmp::Ordering;
+use error::{Error, Result};
+use scene::{check_class, child_property, child_vec_f32, child_vec_f64, child_vec_i32, child_vec_i64};
+use scene::object_graph::{Object, ObjectGraph};
+use scene::properties70::{Properties70, PropertyValue};
+use scene::time::{FbxTime, TICKS_PER_SECOND};
+
+
+/// Interpolation type of a keyframe segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Interpolation {
+    /// Holds the value of the key.
+    Constant,
+    /// Holds the value of the next key.
+    ConstantNext,
+    /// Linear interpolation.
+    Linear,
+    /// Cubic interpolation with tangents.
+    Cubic,
+}
+
+/// Tangent mode of a cubic keyframe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TangentMode {
+    /// Tangents computed from the neighboring keys (Catmull-Rom like).
+    Auto,
+    /// Auto tangents, flattened at local extrema to avoid overshooting.
+    AutoClamped,
+    /// User specified tangent, the same slope for both sides.
+    User,
+    /// User specified tangents, separate slopes for both sides.
+    Break,
+    /// Tangents computed from tension, continuity and bias (Kochanek-Bartels).
+    Tcb,
+}
+
+/// Bits of `KeyAttrFlags`, the same as `FbxAnimCurveDef` of FBX SDK.
+mod flags {
+    pub const INTERPOLATION_CONSTANT: i32 = 0x0000_0002;
+    pub const INTERPOLATION_LINEAR: i32 = 0x0000_0004;
+    pub const INTERPOLATION_CUBIC: i32 = 0x0000_0008;
+    pub const CONSTANT_NEXT: i32 = 0x0000_0100;
+    pub const TANGENT_TCB: i32 = 0x0000_0200;
+    pub const TANGENT_USER: i32 = 0x0000_0400;
+    pub const TANGENT_BREAK: i32 = 0x0000_0800;
+    pub const TANGENT_CLAMP: i32 = 0x0000_1000;
+    pub const TANGENT_CLAMP_PROGRESSIVE: i32 = 0x0000_4000;
+    pub const WEIGHTED_RIGHT: i32 = 0x0100_0000;
+    pub const WEIGHTED_NEXT_LEFT: i32 = 0x0200_0000;
+}
+
+/// Default tangent weight.
+const DEFAULT_WEIGHT: f64 = 1.0 / 3.0;
+
+
+/// A keyframe of an animation curve.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyframe {
+    /// Time of the key.
+    pub time: FbxTime,
+    /// Value of the key.
+    pub value: f64,
+    /// Interpolation of the segment from this key to the next key.
+    pub interpolation: Interpolation,
+    /// Tangent mode.
+    pub tangent_mode: TangentMode,
+    /// Slope on the right side of the key, in value per second.
+    pub right_slope: f64,
+    /// Slope on the left side of the next key, in value per second.
+    pub next_left_slope: f64,
+    /// Weight of the right tangent, relative to the segment duration.
+    ///
+    /// `1/3` if the tangent is not weighted.
+    pub right_weight: f64,
+    /// Weight of the left tangent of the next key, relative to the segment duration.
+    ///
+    /// `1/3` if the tangent is not weighted.
+    pub next_left_weight: f64,
+    /// Tension, continuity and bias, used with `TangentMode::Tcb`.
+    pub tcb: [f64; 3],
+}
+
+impl Keyframe {
+    /// Decodes a keyframe from `KeyAttrFlags` and `KeyAttrDataFloat` values.
+    fn new(time: FbxTime, value: f64, flags: i32, data: [f32; 4]) -> Self {
+        let interpolation = if flags & flags::INTERPOLATION_CONSTANT != 0 {
+            if flags & flags::CONSTANT_NEXT != 0 {
+                Interpolation::ConstantNext
+            } else {
+                Interpolation::Constant
+            }
+        } else if flags & flags::INTERPOLATION_LINEAR != 0 {
+            Interpolation::Linear
+        } else {
+            Interpolation::Cubic
+        };
+        let tangent_mode = if flags & flags::TANGENT_TCB != 0 {
+            TangentMode::Tcb
+        } else if flags & flags::TANGENT_USER != 0 {
+            if flags & flags::TANGENT_BREAK != 0 {
+                TangentMode::Break
+            } else {
+                TangentMode::User
+            }
+        } else if flags & (flags::TANGENT_CLAMP | flags::TANGENT_CLAMP_PROGRESSIVE) != 0 {
+            TangentMode::AutoClamped
+        } else {
+            TangentMode::Auto
+        };
+        // Weights are packed into the bits of the third value as two 16-bit fixed-point
+        // numbers (in units of 1/9999).
+        let weights = data[2].to_bits();
+        let right_weight = if flags & flags::WEIGHTED_RIGHT != 0 {
+            (weights & 0xffff) as f64 / 9999.0
+        } else {
+            DEFAULT_WEIGHT
+        };
+        let next_left_weight = if flags & flags::WEIGHTED_NEXT_LEFT != 0 {
+            (weights >> 16) as f64 / 9999.0
+        } else {
+            DEFAULT_WEIGHT
+        };
+        Keyframe {
+            time,
+            value,
+            interpolation,
+            tangent_mode,
+            right_slope: data[0] as f64,
+            next_left_slope: data[1] as f64,
+            right_weight,
+            next_left_weight,
+            tcb: [data[0] as f64, data[1] as f64, data[2] as f64],
+        }
+    }
+}
+
+
+/// `AnimationCurve` object.
+#[derive(Debug, Clone)]
+pub struct AnimationCurve<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Curve name.
+    pub name: &'a str,
+    /// Default value (`Default`).
+    pub default: f64,
+    /// Keyframes in order of time.
+    pub keys: Vec<Keyframe>,
+}
+
+impl<'a> AnimationCurve<'a> {
+    /// Decodes the `AnimationCurve` object.
+    ///
+    /// If key attributes are missing, keys are cubic with auto tangents.
+    pub fn from_object(object: &Object<'a>) -> Result<Self> {
+        check_class(object, "AnimCurve")?;
+        let node = object.node;
+        let times = child_vec_i64(node, "KeyTime")?.unwrap_or_default();
+        let values = child_vec_f64(node, "KeyValueFloat")?.unwrap_or_default();
+        if times.len() != values.len() {
+            return Err(Error::DataError(format!("Animation curve {} has {} key times but {} values", object.id, times.len(), values.len())));
+        }
+        let attr_flags = child_vec_i32(node, "KeyAttrFlags")?.unwrap_or_default();
+        let attr_data = child_vec_f32(node, "KeyAttrDataFloat")?.unwrap_or_default();
+        let attr_ref_counts = child_vec_i32(node, "KeyAttrRefCount")?.unwrap_or_default();
+        if attr_data.len() < attr_flags.len() * 4 || attr_ref_counts.len() < attr_flags.len() {
+            return Err(Error::DataError(format!("Animation curve {} has inconsistent key attributes", object.id)));
+        }
+
+        // Each key attribute is shared by consecutive keys, as many as its reference count.
+        let mut keys = Vec::with_capacity(times.len());
+        let mut attr = 0;
+        let mut rest = attr_ref_counts.first().cloned().unwrap_or(0);
+        for (&time, &value) in times.iter().zip(&values) {
+            while rest <= 0 && attr + 1 < attr_flags.len() {
+                attr += 1;
+                rest = attr_ref_counts[attr];
+            }
+            rest -= 1;
+            let (flags, data) = match attr_flags.get(attr) {
+                Some(&flags) => (flags, [attr_data[attr * 4], attr_data[attr * 4 + 1], attr_data[attr * 4 + 2], attr_data[attr * 4 + 3]]),
+                None => (flags::INTERPOLATION_CUBIC, [0.0; 4]),
+            };
+            keys.push(Keyframe::new(FbxTime(time), value, flags, data));
+        }
+        if keys.windows(2).any(|w| w[0].time > w[1].time) {
+            return Err(Error::DataError(format!("Keys of animation curve {} are not sorted by time", object.id)));
+        }
+
+        let default = child_property(node, "Default").and_then(|prop| prop.as_f64())
+            .or_else(|| keys.first().map(|key| key.value))
+            .unwrap_or(0.0);
+        Ok(AnimationCurve {
+            id: object.id,
+            name: object.name,
+            default,
+            keys,
+        })
+    }
+
+    /// Returns the time range of the keys.
+    pub fn time_range(&self) -> Option<(FbxTime, FbxTime)> {
+        match (self.keys.first(), self.keys.last()) {
+            (Some(first), Some(last)) => Some((first.time, last.time)),
+            _ => None,
+        }
+    }
+
+    /// Evaluates the curve at the given time.
+    ///
+    /// The value is held constant before the first key and after the last key.
+    /// Returns the default value if the curve has no keys.
+    pub fn evaluate(&self, time: FbxTime) -> f64 {
+        let keys = &self.keys;
+        // Index of the first key after `time`.
+        let next = match keys.binary_search_by(|key| if key.time <= time { Ordering::Less } else { Ordering::Greater }) {
+            Ok(pos) | Err(pos) => pos,
+        };
+        if next == 0 {
+            return keys.first().map_or(self.default, |key| key.value);
+        }
+        if next == keys.len() {
+            return keys[next - 1].value;
+        }
+        let (k0, k1) = (&keys[next - 1], &keys[next]);
+        let dt = (k1.time.0 - k0.time.0) as f64 / TICKS_PER_SECOND as f64;
+        let s = (time.0 - k0.time.0) as f64 / (k1.time.0 - k0.time.0) as f64;
+        match k0.interpolation {
+            Interpolation::Constant => k0.value,
+            Interpolation::ConstantNext => k1.value,
+            Interpolation::Linear => k0.value + (k1.value - k0.value) * s,
+            Interpolation::Cubic => {
+                let m0 = self.right_slope(next - 1);
+                let m1 = self.left_slope(next);
+                let (w0, w1) = (k0.right_weight, k0.next_left_weight);
+                let u = solve_bezier_x(s, w0, 1.0 - w1);
+                bezier(k0.value, k0.value + m0 * w0 * dt, k1.value - m1 * w1 * dt, k1.value, u)
+            },
+        }
+    }
+
+    /// Returns the slope on the right side of the key.
+    fn right_slope(&self, index: usize) -> f64 {
+        let key = &self.keys[index];
+        match key.tangent_mode {
+            TangentMode::User | TangentMode::Break => key.right_slope,
+            TangentMode::Auto | TangentMode::AutoClamped => self.auto_slope(index),
+            TangentMode::Tcb => self.tcb_slopes(index).1,
+        }
+    }
+
+    /// Returns the slope on the left side of the key.
+    fn left_slope(&self, index: usize) -> f64 {
+        let key = &self.keys[index];
+        match key.tangent_mode {
+            // The left slope is stored in the previous key.
+            TangentMode::User | TangentMode::Break => match index {
+                0 => key.right_slope,
+                _ => self.keys[index - 1].next_left_slope,
+            },
+            TangentMode::Auto | TangentMode::AutoClamped => self.auto_slope(index),
+            TangentMode::Tcb => self.tcb_slopes(index).0,
+        }
+    }
+
+    /// Returns slopes to the previous key and to the next key.
+    fn neighbor_slopes(&self, index: usize) -> (Option<f64>, Option<f64>) {
+        let slope = |a: &Keyframe, b: &Keyframe| {
+            let dt = (b.time.0 - a.time.0) as f64 / TICKS_PER_SECOND as f64;
+            if dt > 0.0 { (b.value - a.value) / dt } else { 0.0 }
+        };
+        let key = &self.keys[index];
+        let prev = if index > 0 { Some(slope(&self.keys[index - 1], key)) } else { None };
+        let next = self.keys.get(index + 1).map(|next| slope(key, next));
+        (prev, next)
+    }
+
+    /// Computes the auto tangent slope of the key.
+    ///
+    /// The first and the last keys have flat tangents.
+    fn auto_slope(&self, index: usize) -> f64 {
+        let keys = &self.keys;
+        if index == 0 || index + 1 >= keys.len() {
+            return 0.0;
+        }
+        let (prev, key, next) = (&keys[index - 1], &keys[index], &keys[index + 1]);
+        if key.tangent_mode == TangentMode::AutoClamped && (key.value - prev.value) * (next.value - key.value) <= 0.0 {
+            return 0.0;
+        }
+        let dt = (next.time.0 - prev.time.0) as f64 / TICKS_PER_SECOND as f64;
+        if dt > 0.0 { (next.value - prev.value) / dt } else { 0.0 }
+    }
+
+    /// Computes the left and right slopes of the TCB key.
+    fn tcb_slopes(&self, index: usize) -> (f64, f64) {
+        let [tension, continuity, bias] = self.keys[index].tcb;
+        let (prev, next) = match self.neighbor_slopes(index) {
+            (Some(prev), Some(next)) => (prev, next),
+            (Some(slope), None) | (None, Some(slope)) => (slope, slope),
+            (None, None) => return (0.0, 0.0),
+        };
+        let left = (1.0 - tension) * ((1.0 - continuity) * (1.0 + bias) * prev + (1.0 + continuity) * (1.0 - bias) * next) / 2.0;
+        let right = (1.0 - tension) * ((1.0 + continuity) * (1.0 + bias) * prev + (1.0 - continuity) * (1.0 - bias) * next) / 2.0;
+        (left, right)
+    }
+}
+
+/// Evaluates a 1D cubic Bezier curve.
+fn bezier(p0: f64, p1: f64, p2: f64, p3: f64, u: f64) -> f64 {
+    let v = 1.0 - u;
+    v * v * v * p0 + 3.0 * v * v * u * p1 + 3.0 * v * u * u * p2 + u * u * u * p3
+}
+
+/// Finds the parameter where the Bezier curve `(0, x1, x2, 1)` is `x`.
+fn solve_bezier_x(x: f64, x1: f64, x2: f64) -> f64 {
+    let (mut lo, mut hi) = (0.0_f64, 1.0_f64);
+    let mut u = x;
+    for _ in 0..32 {
+        let fx = bezier(0.0, x1, x2, 1.0, u) - x;
+        if fx.abs() < 1e-12 {
+            break;
+        }
+        if fx < 0.0 {
+            lo = u;
+        } else {
+            hi = u;
+        }
+        let v = 1.0 - u;
+        let dx = 3.0 * v * v * x1 + 6.0 * v * u * (x2 - x1) + 3.0 * u * u * (1.0 - x2);
+        let newton = u - fx / dx;
+        // Use Newton's method if it stays in the bracket, bisection otherwise.
+        u = if dx.abs() > 1e-12 && newton > lo && newton < hi { newton } else { (lo + hi) / 2.0 };
+    }
+    u
+}
+
+
+/// A channel of an animation curve node, such as `X` of `d|X`.
+#[derive(Debug, Clone)]
+pub struct AnimationChannel<'a> {
+    /// Channel name without `d|` prefix, such as `X`.
+    pub name: &'a str,
+    /// Value used when no curve is connected.
+    pub default: f64,
+    /// Connected curve.
+    pub curve: Option<AnimationCurve<'a>>,
+}
+
+impl<'a> AnimationChannel<'a> {
+    /// Evaluates the channel at the given time.
+    pub fn evaluate(&self, time: FbxTime) -> f64 {
+        self.curve.as_ref().map_or(self.default, |curve| curve.evaluate(time))
+    }
+}
+
+
+/// A property animated by a curve node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AnimationTarget<'a> {
+    /// ID of the animated object.
+    pub object: i64,
+    /// Name of the animated property, such as `Lcl Translation`.
+    pub property: &'a str,
+}
+
+
+/// `AnimationCurveNode` object.
+#[derive(Debug, Clone)]
+pub struct AnimationCurveNode<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Curve node name, such as `T`, `R` and `S`.
+    pub name: &'a str,
+    /// Channels in order of properties.
+    pub channels: Vec<AnimationChannel<'a>>,
+    /// The animated property.
+    pub target: Option<AnimationTarget<'a>>,
+}
+
+impl<'a> AnimationCurveNode<'a> {
+    /// Decodes the `AnimationCurveNode` object with its curves and finds the animated property.
+    pub fn from_object(object: &Object<'a>, graph: &ObjectGraph<'a>) -> Result<Self> {
+        check_class(object, "AnimCurveNode")?;
+        let properties = Properties70::from_parent(object.node)?;
+        let mut channels: Vec<_> = properties.iter()
+            .filter(|record| record.name.starts_with("d|"))
+            .map(|record| AnimationChannel {
+                name: &record.name[2..],
+                default: record.value.as_f64().unwrap_or(0.0),
+                curve: None,
+            })
+            .collect();
+        for connection in graph.children_of(object.id) {
+            let channel_name = match connection.parent_property {
+                Some(property) if property.starts_with("d|") => &property[2..],
+                _ => continue,
+            };
+            let curve = match graph.object(connection.child) {
+                Some(child) if child.class == "AnimCurve" => AnimationCurve::from_object(child)?,
+                _ => continue,
+            };
+            match channels.iter().position(|channel| channel.name == channel_name) {
+                Some(pos) => channels[pos].curve = Some(curve),
+                None => channels.push(AnimationChannel {
+                    name: channel_name,
+                    default: curve.default,
+                    curve: Some(curve),
+                }),
+            }
+        }
+        let target = graph.parents_of(object.id)
+            .filter_map(|connection| connection.parent_property.map(|property| AnimationTarget {
+                object: connection.parent,
+                property,
+            }))
+            .next();
+        Ok(AnimationCurveNode {
+            id: object.id,
+            name: object.name,
+            channels,
+            target,
+        })
+    }
+
+    /// Returns the channel with the given name.
+    pub fn channel(&self, name: &str) -> Option<&AnimationChannel<'a>> {
+        self.channels.iter().find(|channel| channel.name == name)
+    }
+
+    /// Evaluates all channels at the given time.
+    pub fn evaluate(&self, time: FbxTime) -> Vec<f64> {
+        self.channels.iter().map(|channel| channel.evaluate(time)).collect()
+    }
+
+    /// Evaluates `X`, `Y` and `Z` channels at the given time.
+    ///
+    /// Missing channels are `0.0`.
+    pub fn evaluate_vector3(&self, time: FbxTime) -> [f64; 3] {
+        let component = |name| self.channel(name).map_or(0.0, |channel| channel.evaluate(time));
+        [component("X"), component("Y"), component("Z")]
+    }
+
+    /// Returns the time range of the keys of all channels.
+    pub fn time_range(&self) -> Option<(FbxTime, FbxTime)> {
+        union_ranges(self.channels.iter().filter_map(|channel| channel.curve.as_ref().and_then(AnimationCurve::time_range)))
+    }
+}
+
+
+/// Blend mode of an animation layer (`BlendMode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerBlendMode {
+    /// The layer is added to the layers below (`0`).
+    Additive,
+    /// The layer overrides the layers below, blended by the weight (`1`).
+    Override,
+    /// The layer value is applied fully, and the layers below are kept by `1 - weight` (`2`).
+    OverridePassthrough,
+}
+
+impl LayerBlendMode {
+    /// Creates a blend mode from the `BlendMode` value.
+    pub fn from_i32(v: i32) -> Option<Self> {
+        match v {
+            0 => Some(LayerBlendMode::Additive),
+            1 => Some(LayerBlendMode::Override),
+            2 => Some(LayerBlendMode::OverridePassthrough),
+            _ => None,
+        }
+    }
+}
+
+
+/// `AnimationLayer` object.
+#[derive(Debug, Clone)]
+pub struct AnimationLayer<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Layer name.
+    pub name: &'a str,
+    /// Weight of the layer in percent (`Weight`).
+    pub weight: f64,
+    /// Whether the layer is muted (`Mute`).
+    pub mute: bool,
+    /// Whether the layer is soloed (`Solo`).
+    pub solo: bool,
+    /// Blend mode.
+    pub blend_mode: LayerBlendMode,
+    /// Curve nodes in order of connection.
+    pub curve_nodes: Vec<AnimationCurveNode<'a>>,
+}
+
+impl<'a> AnimationLayer<'a> {
+    /// Decodes the `AnimationLayer` object with its curve nodes.
+    pub fn from_object(object: &Object<'a>, graph: &ObjectGraph<'a>) -> Result<Self> {
+        check_class(object, "AnimLayer")?;
+        let properties = Properties70::from_parent(object.node)?;
+        let blend_mode = match properties.value("BlendMode").and_then(PropertyValue::as_i64) {
+            Some(mode) => LayerBlendMode::from_i32(mode as i32)
+                .ok_or_else(|| Error::UnexpectedValue(format!("Unknown animation layer blend mode: {}", mode)))?,
+            None => LayerBlendMode::Additive,
+        };
+        let mut curve_nodes = Vec::new();
+        for child in graph.child_objects(object.id) {
+            if child.class == "AnimCurveNode" {
+                curve_nodes.push(AnimationCurveNode::from_object(child, graph)?);
+            }
+        }
+        Ok(AnimationLayer {
+            id: object.id,
+            name: object.name,
+            weight: properties.value("Weight").and_then(PropertyValue::as_f64).unwrap_or(100.0),
+            mute: properties.value("Mute").and_then(PropertyValue::as_bool).unwrap_or(false),
+            solo: properties.value("Solo").and_then(PropertyValue::as_bool).unwrap_or(false),
+            blend_mode,
+            curve_nodes,
+        })
+    }
+
+    /// Returns the curve node animating the given property of the object.
+    pub fn curve_node(&self, object: i64, property: &str) -> Option<&AnimationCurveNode<'a>> {
+        self.curve_nodes.iter().find(|node| matches!(node.target, Some(target) if target.object == object && target.property == property))
+    }
+
+    /// Returns curve nodes animating the object.
+    pub fn curve_nodes_of<'l>(&'l self, object: i64) -> impl Iterator<Item = &'l AnimationCurveNode<'a>> + 'l {
+        self.curve_nodes.iter().filter(move |node| matches!(node.target, Some(target) if target.object == object))
+    }
+}
+
+
+/// `AnimationStack` object, i.e. a take.
+#[derive(Debug, Clone)]
+pub struct AnimationStack<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Stack name.
+    pub name: &'a str,
+    /// `LocalStart`.
+    pub local_start: Option<FbxTime>,
+    /// `LocalStop`.
+    pub local_stop: Option<FbxTime>,
+    /// `ReferenceStart`.
+    pub reference_start: Option<FbxTime>,
+    /// `ReferenceStop`.
+    pub reference_stop: Option<FbxTime>,
+    /// Layers in order of connection.
+    pub layers: Vec<AnimationLayer<'a>>,
+}
+
+impl<'a> AnimationStack<'a> {
+    /// Decodes the `AnimationStack` object with its layers.
+    pub fn from_object(object: &Object<'a>, graph: &ObjectGraph<'a>) -> Result<Self> {
+        check_class(object, "AnimStack")?;
+        let properties = Properties70::from_parent(object.node)?;
+        let time = |name| properties.value(name).and_then(PropertyValue::as_time);
+        let mut layers = Vec::new();
+        for child in graph.child_objects(object.id) {
+            if child.class == "AnimLayer" {
+                layers.push(AnimationLayer::from_object(child, graph)?);
+            }
+        }
+        Ok(AnimationStack {
+            id: object.id,
+            name: object.name,
+            local_start: time("LocalStart"),
+            local_stop: time("LocalStop"),
+            reference_start: time("ReferenceStart"),
+            reference_stop: time("ReferenceStop"),
+            layers,
+        })
+    }
+
+    /// Returns the time range of the stack.
+    ///
+    /// `LocalStart` and `LocalStop` are used if exist, otherwise the range of keys of all
+    /// curves.
+    pub fn time_range(&self) -> Option<(FbxTime, FbxTime)> {
+        match (self.local_start, self.local_stop) {
+            (Some(start), Some(stop)) if start <= stop => Some((start, stop)),
+            _ => self.key_range(),
+        }
+    }
+
+    /// Returns properties animated by the stack, in order of appearance.
+    pub fn targets(&self) -> Vec<AnimationTarget<'a>> {
+        let mut targets = Vec::new();
+        for target in self.layers.iter().flat_map(|layer| &layer.curve_nodes).filter_map(|node| node.target) {
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+        targets
+    }
+
+    /// Returns channel names of the animated property.
+    ///
+    /// The names are the channels of the first curve node for the property.
+    pub fn channel_names(&self, target: AnimationTarget) -> Vec<&'a str> {
+        self.layers.iter()
+            .filter_map(|layer| layer.curve_node(target.object, target.property))
+            .next()
+            .map_or_else(Vec::new, |node| node.channels.iter().map(|channel| channel.name).collect())
+    }
+
+    /// Returns default values (`d|X` and so on) of the channels of the animated property.
+    ///
+    /// The values are in order of [`channel_names()`](#method.channel_names), and taken from
+    /// the first curve node for the property, even if its layer is muted.
+    pub fn default_values(&self, target: AnimationTarget) -> Vec<f64> {
+        self.layers.iter()
+            .filter_map(|layer| layer.curve_node(target.object, target.property))
+            .next()
+            .map_or_else(Vec::new, |node| node.channels.iter().map(|channel| channel.default).collect())
+    }
+
+    /// Evaluates the animated property at the given time, blending all layers.
+    ///
+    /// Returns values in order of [`channel_names()`](#method.channel_names), or `None` if no
+    /// active layers animate the property.
+    /// Muted layers are ignored, and only soloed layers are used if any layer is soloed.
+    /// Channels missing in the bottom active layer have the
+    /// [default values](#method.default_values).
+    pub fn evaluate(&self, target: AnimationTarget, time: FbxTime) -> Option<Vec<f64>> {
+        let names = self.channel_names(target);
+        let defaults = self.default_values(target);
+        let any_solo = self.layers.iter().any(|layer| layer.solo);
+        let mut result: Option<Vec<f64>> = None;
+        for layer in &self.layers {
+            if layer.mute || (any_solo && !layer.solo) {
+                continue;
+            }
+            let node = match layer.curve_node(target.object, target.property) {
+                Some(node) => node,
+                None => continue,
+            };
+            let values = names.iter().map(|&name| node.channel(name).map(|channel| channel.evaluate(time)));
+            let weight = layer.weight / 100.0;
+            result = Some(match result {
+                // The bottom layer is used as is.
+                None => values.zip(&defaults).map(|(value, &default)| value.unwrap_or(default)).collect(),
+                Some(base) => base.iter().zip(values).map(|(&base, value)| match value {
+                    Some(value) => match layer.blend_mode {
+                        LayerBlendMode::Additive => base + value * weight,
+                        LayerBlendMode::Override => base * (1.0 - weight) + value * weight,
+                        LayerBlendMode::OverridePassthrough => base * (1.0 - weight) + value,
+                    },
+                    None => base,
+                }).collect(),
+            });
+        }
+        result
+    }
+
+    /// Returns the time range of keys of all curves.
+    pub fn key_range(&self) -> Option<(FbxTime, FbxTime)> {
+        union_ranges(self.layers.iter().flat_map(|layer| &layer.curve_nodes).filter_map(AnimationCurveNode::time_range))
+    }
+}
+
+/// Returns all animation stacks.
+pub fn animation_stacks<'a>(graph: &ObjectGraph<'a>) -> Result<Vec<AnimationStack<'a>>> {
+    let mut stacks = Vec::new();
+    for object in graph.objects().filter(|object| object.class == "AnimStack") {
+        stacks.push(AnimationStack::from_object(object, graph)?);
+    }
+    Ok(stacks)
+}
+
+
+fn union_ranges<I: Iterator<Item = (FbxTime, FbxTime)>>(ranges: I) -> Option<(FbxTime, FbxTime)> {
+    ranges.fold(None, |acc, (start, stop)| match acc {
+        Some((acc_start, acc_stop)) => Some((::std::cmp::min(acc_start, start), ::std::cmp::max(acc_stop, stop))),
+        None => Some((start, stop)),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use property::Property;
+    use test_util::{connection, node, object, op, p, tree, TestNode};
+
+    fn key(seconds: f64, value: f64, interpolation: Interpolation, slopes: (f64, f64)) -> Keyframe {
+        Keyframe {
+            time: FbxTime::from_seconds(seconds),
+            value,
+            interpolation,
+            tangent_mode: TangentMode::Break,
+            right_slope: slopes.0,
+            next_left_slope: slopes.1,
+            right_weight: DEFAULT_WEIGHT,
+            next_left_weight: DEFAULT_WEIGHT,
+            tcb: [0.0; 3],
+        }
+    }
+
+    /// Returns a curve with keys `0` at 1s, `10` at 2s and `4` at 3s.
+    fn curve(interpolation: Interpolation, slopes: (f64, f64)) -> AnimationCurve<'static> {
+        AnimationCurve {
+            id: 1,
+            name: "",
+            default: -1.0,
+            keys: vec![
+                key(1.0, 0.0, interpolation, slopes),
+                key(2.0, 10.0, interpolation, slopes),
+                key(3.0, 4.0, interpolation, slopes),
+            ],
+        }
+    }
+
+    fn eval(curve: &AnimationCurve, seconds: f64) -> f64 {
+        curve.evaluate(FbxTime::from_seconds(seconds))
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-6, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn constant() {
+        let curve = curve(Interpolation::Constant, (0.0, 0.0));
+        assert_close(eval(&curve, 0.5), 0.0);
+        assert_close(eval(&curve, 1.5), 0.0);
+        assert_close(eval(&curve, 2.0), 10.0);
+        assert_close(eval(&curve, 2.5), 10.0);
+        assert_close(eval(&curve, 3.5), 4.0);
+    }
+
+    #[test]
+    fn constant_next() {
+        let curve = curve(Interpolation::ConstantNext, (0.0, 0.0));
+        assert_close(eval(&curve, 0.5), 0.0);
+        assert_close(eval(&curve, 1.5), 10.0);
+        assert_close(eval(&curve, 2.5), 4.0);
+        assert_close(eval(&curve, 3.5), 4.0);
+    }
+
+    #[test]
+    fn linear() {
+        let curve = curve(Interpolation::Linear, (0.0, 0.0));
+        assert_close(eval(&curve, 0.5), 0.0);
+        assert_close(eval(&curve, 1.25), 2.5);
+        assert_close(eval(&curve, 1.5), 5.0);
+        assert_close(eval(&curve, 2.5), 7.0);
+        assert_close(eval(&curve, 3.5), 4.0);
+    }
+
+    #[test]
+    fn cubic_flat_tangents() {
+        let curve = curve(Interpolation::Cubic, (0.0, 0.0));
+        assert_close(eval(&curve, 0.5), 0.0);
+        assert_close(eval(&curve, 1.0), 0.0);
+        // Symmetric ease in and out.
+        assert_close(eval(&curve, 1.5), 5.0);
+        assert!(eval(&curve, 1.25) < 2.5);
+        assert!(eval(&curve, 1.75) > 7.5);
+        assert_close(eval(&curve, 2.0), 10.0);
+        assert_close(eval(&curve, 2.5), 7.0);
+        assert_close(eval(&curve, 3.5), 4.0);
+    }
+
+    #[test]
+    fn cubic_user_tangents() {
+        // Tangents along the first segment make it a straight line.
+        let mut curve = curve(Interpolation::Cubic, (10.0, 10.0));
+        assert_close(eval(&curve, 1.25), 2.5);
+        assert_close(eval(&curve, 1.5), 5.0);
+
+        // Steeper tangents overshoot the linear interpolation near the first key.
+        curve.keys[0].right_slope = 30.0;
+        assert!(eval(&curve, 1.25) > 2.5);
+        assert_close(eval(&curve, 0.5), 0.0);
+        assert_close(eval(&curve, 3.5), 4.0);
+    }
+
+    #[test]
+    fn cubic_auto_tangents() {
+        let mut curve = curve(Interpolation::Cubic, (0.0, 0.0));
+        for key in &mut curve.keys {
+            key.tangent_mode = TangentMode::Auto;
+        }
+        // The middle key has the slope between its neighbors, `(4 - 0) / 2s`.
+        assert_close(curve.right_slope(1), 2.0);
+        assert_close(curve.left_slope(1), 2.0);
+        assert_close(eval(&curve, 0.5), 0.0);
+        assert!(eval(&curve, 1.5) > 0.0 && eval(&curve, 1.5) < 10.0);
+        assert_close(eval(&curve, 3.5), 4.0);
+
+        // Clamped tangents are flat at the local maximum.
+        curve.keys[1].tangent_mode = TangentMode::AutoClamped;
+        assert_close(curve.right_slope(1), 0.0);
+    }
+
+    #[test]
+    fn empty_curve() {
+        let curve = AnimationCurve { id: 1, name: "", default: 3.0, keys: Vec::new() };
+        assert_close(eval(&curve, 1.0), 3.0);
+        assert_eq!(curve.time_range(), None);
+    }
+
+    #[test]
+    fn weighted_tangents() {
+        let mut curve = curve(Interpolation::Cubic, (0.0, 0.0));
+        curve.keys[0].right_weight = 0.1;
+        curve.keys[0].next_left_weight = 0.6;
+        // Control points of the first segment are `(0, 0)`, `(0.1, 0)`, `(0.4, 10)` and `(1, 10)`.
+        for &u in &[0.1, 0.3, 0.5, 0.8] {
+            let x = bezier(0.0, 0.1, 0.4, 1.0, u);
+            let y = bezier(0.0, 0.0, 10.0, 10.0, u);
+            assert_close(eval(&curve, 1.0 + x), y);
+        }
+        // Not symmetric any more, unlike `cubic_flat_tangents`.
+        assert!(eval(&curve, 1.5) > 5.5);
+        assert_close(solve_bezier_x(0.0, 0.1, 0.4), 0.0);
+        assert_close(solve_bezier_x(1.0, 0.1, 0.4), 1.0);
+    }
+
+    #[test]
+    fn tcb_tangents() {
+        let mut curve = curve(Interpolation::Cubic, (0.0, 0.0));
+        for key in &mut curve.keys {
+            key.tangent_mode = TangentMode::Tcb;
+        }
+        // Slopes to the neighbors of the middle key are `10` and `-6`.
+        assert_eq!(curve.tcb_slopes(1), (2.0, 2.0));
+        curve.keys[1].tcb = [0.5, 0.0, 0.0];
+        assert_eq!(curve.tcb_slopes(1), (1.0, 1.0));
+        curve.keys[1].tcb = [0.0, 0.5, 0.0];
+        assert_eq!(curve.tcb_slopes(1), (-2.0, 6.0));
+        curve.keys[1].tcb = [0.0, 0.0, 0.5];
+        assert_eq!(curve.tcb_slopes(1), (6.0, 6.0));
+        assert_eq!((curve.left_slope(1), curve.right_slope(1)), (6.0, 6.0));
+        // The first and the last keys use the slope to their only neighbor.
+        assert_eq!(curve.tcb_slopes(0), (10.0, 10.0));
+        assert_eq!(curve.tcb_slopes(2), (-6.0, -6.0));
+        curve.keys.truncate(1);
+        assert_eq!(curve.tcb_slopes(0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn key_attr_flags() {
+        let decode = |key_flags| {
+            let key = Keyframe::new(FbxTime(0), 0.0, key_flags, [0.0; 4]);
+            (key.interpolation, key.tangent_mode)
+        };
+        assert_eq!(decode(flags::INTERPOLATION_CONSTANT), (Interpolation::Constant, TangentMode::Auto));
+        assert_eq!(decode(flags::INTERPOLATION_CONSTANT | flags::CONSTANT_NEXT), (Interpolation::ConstantNext, TangentMode::Auto));
+        assert_eq!(decode(flags::INTERPOLATION_LINEAR), (Interpolation::Linear, TangentMode::Auto));
+        assert_eq!(decode(flags::INTERPOLATION_CUBIC), (Interpolation::Cubic, TangentMode::Auto));
+        assert_eq!(decode(0), (Interpolation::Cubic, TangentMode::Auto));
+        assert_eq!(decode(flags::INTERPOLATION_CUBIC | flags::TANGENT_CLAMP), (Interpolation::Cubic, TangentMode::AutoClamped));
+        assert_eq!(decode(flags::INTERPOLATION_CUBIC | flags::TANGENT_CLAMP_PROGRESSIVE), (Interpolation::Cubic, TangentMode::AutoClamped));
+        assert_eq!(decode(flags::INTERPOLATION_CUBIC | flags::TANGENT_USER), (Interpolation::Cubic, TangentMode::User));
+        assert_eq!(decode(flags::INTERPOLATION_CUBIC | flags::TANGENT_USER | flags::TANGENT_BREAK), (Interpolation::Cubic, TangentMode::Break));
+        assert_eq!(decode(flags::INTERPOLATION_CUBIC | flags::TANGENT_TCB), (Interpolation::Cubic, TangentMode::Tcb));
+
+        let tcb = Keyframe::new(FbxTime(0), 0.0, flags::INTERPOLATION_CUBIC | flags::TANGENT_TCB, [0.5, 0.25, -0.5, 0.0]);
+        assert_eq!(tcb.tcb, [0.5, 0.25, -0.5]);
+
+        // Right weight `9999` (1.0) in the low bits, next left weight `4999` in the high bits.
+        let weights = f32::from_bits(4999 << 16 | 9999);
+        let data = [1.5, -2.0, weights, 0.0];
+        let key = Keyframe::new(FbxTime(0), 0.0, flags::INTERPOLATION_CUBIC | flags::TANGENT_USER | flags::WEIGHTED_RIGHT, data);
+        assert_eq!((key.right_slope, key.next_left_slope), (1.5, -2.0));
+        assert_eq!((key.right_weight, key.next_left_weight), (1.0, DEFAULT_WEIGHT));
+        let key = Keyframe::new(FbxTime(0), 0.0, flags::INTERPOLATION_CUBIC | flags::TANGENT_USER | flags::WEIGHTED_NEXT_LEFT, data);
+        assert_eq!((key.right_weight, key.next_left_weight), (DEFAULT_WEIGHT, 4999.0 / 9999.0));
+    }
+
+    fn curve_node(id: i64, times: Vec<i64>, values: Vec<f32>, attrs: Vec<(i32, [f32; 4], i32)>) -> TestNode<'static> {
+        let mut children = vec![
+            node("KeyTime", vec![Property::VecI64(times)], vec![]),
+            node("KeyValueFloat", vec![Property::VecF32(values)], vec![]),
+        ];
+        if !attrs.is_empty() {
+            children.push(node("KeyAttrFlags", vec![Property::VecI32(attrs.iter().map(|attr| attr.0).collect())], vec![]));
+            children.push(node("KeyAttrDataFloat", vec![Property::VecF32(attrs.iter().flat_map(|attr| attr.1.to_vec()).collect())], vec![]));
+            children.push(node("KeyAttrRefCount", vec![Property::VecI32(attrs.iter().map(|attr| attr.2).collect())], vec![]));
+        }
+        object("AnimationCurve", id, "\0\x01AnimCurve", "", children)
+    }
+
+    fn decode_curve(curve: TestNode) -> Result<AnimationCurve<'static>> {
+        let tree = tree(7400, &[node("Objects", vec![], vec![curve])]);
+        let graph = ObjectGraph::from_tree(&tree);
+        let curve = AnimationCurve::from_object(graph.object(1).unwrap())?;
+        Ok(AnimationCurve { id: curve.id, name: "", default: curve.default, keys: curve.keys })
+    }
+
+    #[test]
+    fn curve_from_tree() {
+        let seconds = |s: i64| s * TICKS_PER_SECOND;
+        let curve = decode_curve(curve_node(1, (0..5).map(seconds).collect(), vec![1.0, 2.0, 3.0, 4.0, 5.0], vec![
+            (flags::INTERPOLATION_LINEAR, [0.0; 4], 2),
+            (flags::INTERPOLATION_CUBIC | flags::TANGENT_USER, [2.0, -1.0, 0.0, 0.0], 3),
+        ])).unwrap();
+        assert_eq!(curve.keys.iter().map(|key| key.time).collect::<Vec<_>>(), (0..5).map(|s| FbxTime(seconds(s))).collect::<Vec<_>>());
+        assert_eq!(curve.keys.iter().map(|key| key.value).collect::<Vec<_>>(), [1.0, 2.0, 3.0, 4.0, 5.0]);
+        // The first attribute is shared by two keys, and the second by three keys.
+        assert_eq!(curve.keys.iter().map(|key| key.interpolation).collect::<Vec<_>>(),
+                   [Interpolation::Linear, Interpolation::Linear, Interpolation::Cubic, Interpolation::Cubic, Interpolation::Cubic]);
+        assert_eq!(curve.keys[4].tangent_mode, TangentMode::User);
+        assert_eq!((curve.keys[4].right_slope, curve.keys[4].next_left_slope), (2.0, -1.0));
+        // No `Default` node, the value of the first key is used.
+        assert_eq!(curve.default, 1.0);
+        assert_eq!(curve.time_range(), Some((FbxTime(0), FbxTime(seconds(4)))));
+
+        // Missing attributes make cubic keys with auto tangents.
+        let curve = decode_curve(curve_node(1, vec![0, 1], vec![1.0, 2.0], vec![])).unwrap();
+        assert!(curve.keys.iter().all(|key| key.interpolation == Interpolation::Cubic && key.tangent_mode == TangentMode::Auto));
+    }
+
+    #[test]
+    fn invalid_curves() {
+        let linear = (flags::INTERPOLATION_LINEAR, [0.0; 4], 2);
+        assert!(decode_curve(curve_node(1, vec![0, 1, 2], vec![1.0, 2.0], vec![])).is_err());
+        assert!(decode_curve(curve_node(1, vec![0, 2, 1], vec![1.0, 2.0, 3.0], vec![])).is_err());
+        assert!(decode_curve(curve_node(1, vec![0, 1], vec![1.0, 2.0], vec![linear])).is_ok());
+
+        let mut curve = curve_node(1, vec![0, 1], vec![1.0, 2.0], vec![linear]);
+        curve.children.retain(|child| child.name != "KeyAttrRefCount");
+        assert!(decode_curve(curve).is_err());
+    }
+
+    fn number(name: &'static str, value: f64) -> TestNode<'static> {
+        p(name, "Number", "A", vec![Property::F64(value)])
+    }
+
+    fn layer(id: i64, weight: f64, blend_mode: i32, mute: bool, solo: bool) -> TestNode<'static> {
+        object("AnimationLayer", id, "\0\x01AnimLayer", "", vec![
+            node("Properties70", vec![], vec![
+                number("Weight", weight),
+                p("Mute", "bool", "", vec![Property::I32(mute as i32)]),
+                p("Solo", "bool", "", vec![Property::I32(solo as i32)]),
+                p("BlendMode", "enum", "", vec![Property::I32(blend_mode)]),
+            ]),
+        ])
+    }
+
+    /// Returns a stack animating `Lcl Translation` of the model 10 with four layers:
+    ///
+    /// * Base layer (200): `X` curve of `10`, `Y` of `2` and `Z` of `3`.
+    /// * Override layer (201) of 50%: `X` curve of `20`.
+    /// * Override passthrough layer (202) of 50%: `Y` of `4`.
+    /// * Muted additive layer (203): `Z` of `100`.
+    fn layered_stack(solo: bool) -> Vec<TestNode<'static>> {
+        let constant = |id, value| curve_node(id, vec![0], vec![value], vec![]);
+        vec![
+            node("Objects", vec![], vec![
+                object("Model", 10, "\0\x01Model", "Null", vec![]),
+                object("AnimationStack", 100, "Take\0\x01AnimStack", "", vec![
+                    node("Properties70", vec![], vec![
+                        p("LocalStart", "KTime", "Time", vec![Property::I64(0)]),
+                        p("LocalStop", "KTime", "Time", vec![Property::I64(TICKS_PER_SECOND)]),
+                    ]),
+                ]),
+                layer(200, 100.0, 0, false, false),
+                layer(201, 50.0, 1, false, false),
+                layer(202, 50.0, 2, false, solo),
+                layer(203, 100.0, 0, true, false),
+                object("AnimationCurveNode", 300, "T\0\x01AnimCurveNode", "", vec![
+                    node("Properties70", vec![], vec![number("d|X", 1.0), number("d|Y", 2.0), number("d|Z", 3.0)]),
+                ]),
+                // Channels only from connected curves.
+                object("AnimationCurveNode", 301, "T\0\x01AnimCurveNode", "", vec![]),
+                object("AnimationCurveNode", 302, "T\0\x01AnimCurveNode", "", vec![
+                    node("Properties70", vec![], vec![number("d|Y", 4.0)]),
+                ]),
+                object("AnimationCurveNode", 303, "T\0\x01AnimCurveNode", "", vec![
+                    node("Properties70", vec![], vec![number("d|Z", 100.0)]),
+                ]),
+                constant(400, 10.0),
+                constant(401, 20.0),
+            ]),
+            node("Connections", vec![], vec![
+                connection(200, 100),
+                connection(201, 100),
+                connection(202, 100),
+                connection(203, 100),
+                connection(300, 200),
+                connection(301, 201),
+                connection(302, 202),
+                connection(303, 203),
+                op(300, 10, "Lcl Translation"),
+                op(301, 10, "Lcl Translation"),
+                op(302, 10, "Lcl Translation"),
+                op(303, 10, "Lcl Translation"),
+                op(400, 300, "d|X"),
+                op(401, 301, "d|X"),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn curve_node_from_tree() {
+        let tree = tree(7400, &layered_stack(false));
+        let graph = ObjectGraph::from_tree(&tree);
+        let target = AnimationTarget { object: 10, property: "Lcl Translation" };
+
+        let node = AnimationCurveNode::from_object(graph.object(300).unwrap(), &graph).unwrap();
+        assert_eq!(node.target, Some(target));
+        assert_eq!(node.channels.iter().map(|channel| (channel.name, channel.default)).collect::<Vec<_>>(), [("X", 1.0), ("Y", 2.0), ("Z", 3.0)]);
+        assert_eq!(node.channel("X").unwrap().curve.as_ref().map(|curve| curve.id), Some(400));
+        assert!(node.channel("Y").unwrap().curve.is_none());
+        assert_eq!(node.evaluate_vector3(FbxTime(0)), [10.0, 2.0, 3.0]);
+
+        let node = AnimationCurveNode::from_object(graph.object(301).unwrap(), &graph).unwrap();
+        assert_eq!(node.channels.iter().map(|channel| (channel.name, channel.default)).collect::<Vec<_>>(), [("X", 20.0)]);
+        assert_eq!(node.evaluate_vector3(FbxTime(0)), [20.0, 0.0, 0.0]);
+
+        assert!(AnimationCurveNode::from_object(graph.object(400).unwrap(), &graph).is_err());
+    }
+
+    #[test]
+    fn layered_evaluation() {
+        let file = tree(7400, &layered_stack(false));
+        let graph = ObjectGraph::from_tree(&file);
+        let stacks = animation_stacks(&graph).unwrap();
+        assert_eq!(stacks.len(), 1);
+        let stack = &stacks[0];
+        assert_eq!(stack.layers.iter().map(|layer| (layer.id, layer.blend_mode, layer.mute)).collect::<Vec<_>>(), [
+            (200, LayerBlendMode::Additive, false),
+            (201, LayerBlendMode::Override, false),
+            (202, LayerBlendMode::OverridePassthrough, false),
+            (203, LayerBlendMode::Additive, true),
+        ]);
+        assert_eq!(stack.time_range(), Some((FbxTime(0), FbxTime(TICKS_PER_SECOND))));
+        assert_eq!(stack.key_range(), Some((FbxTime(0), FbxTime(0))));
+
+        let target = AnimationTarget { object: 10, property: "Lcl Translation" };
+        assert_eq!(stack.targets(), [target]);
+        assert_eq!(stack.channel_names(target), ["X", "Y", "Z"]);
+        assert_eq!(stack.default_values(target), [1.0, 2.0, 3.0]);
+        // `X`: `10 * 0.5 + 20 * 0.5`, `Y`: `2 * 0.5 + 4`, and the muted `Z` is ignored.
+        assert_eq!(stack.evaluate(target, FbxTime(0)), Some(vec![15.0, 5.0, 3.0]));
+        assert_eq!(stack.evaluate(AnimationTarget { object: 10, property: "Lcl Rotation" }, FbxTime(0)), None);
+
+        // Only the soloed layer, with default values for the missing channels.
+        let file = tree(7400, &layered_stack(true));
+        let graph = ObjectGraph::from_tree(&file);
+        let stack = &animation_stacks(&graph).unwrap()[0];
+        assert_eq!(stack.evaluate(target, FbxTime(0)), Some(vec![1.0, 4.0, 3.0]));
+    }
+}
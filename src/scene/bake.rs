@@ -0,0 +1,334 @@
+//! Contains baking of animation stacks into densely sampled tracks.
+//!
+//! The time range to bake is usually [`AnimationStack::time_range()`] (the take's range) or
+//! [`GlobalSettings::time_span()`].
+//!
+//! [`AnimationStack::time_range()`]: ../animation/struct.AnimationStack.html#method.time_range
+//! [`GlobalSettings::time_span()`]: ../global_settings/struct.GlobalSettings.html#method.time_span
+
+use error::{Error, Result};
+use scene::animation::{AnimationStack, AnimationTarget};
+use scene::time::{FbxTime, TICKS_PER_SECOND};
+
+
+/// Bake options.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BakeOptions {
+    /// Sampling rate in frames per second.
+    pub frame_rate: f64,
+    /// Maximum error allowed to remove keys by keyframe reduction.
+    ///
+    /// A key is removed if linear interpolation of its neighbors reproduces all channels of
+    /// removed keys within this tolerance.
+    /// Default is `None` (no reduction).
+    pub reduction_tolerance: Option<f64>,
+    /// Whether only transform properties (`Lcl Translation`, `Lcl Rotation` and
+    /// `Lcl Scaling`) are baked.
+    ///
+    /// Default is `false`.
+    pub transforms_only: bool,
+}
+
+impl BakeOptions {
+    /// Creates a new options with the given frame rate and default values.
+    pub fn new(frame_rate: f64) -> Self {
+        BakeOptions {
+            frame_rate,
+            reduction_tolerance: None,
+            transforms_only: false,
+        }
+    }
+
+    /// Sets the value of `reduction_tolerance` and returns the options.
+    pub fn reduction_tolerance(mut self, value: Option<f64>) -> Self {
+        self.reduction_tolerance = value;
+        self
+    }
+
+    /// Sets the value of `transforms_only` and returns the options.
+    pub fn transforms_only(mut self, value: bool) -> Self {
+        self.transforms_only = value;
+        self
+    }
+}
+
+
+/// A sampled animation of a property.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BakedTrack<'a> {
+    /// The animated property.
+    pub target: AnimationTarget<'a>,
+    /// Channel names, such as `["X", "Y", "Z"]`.
+    pub channels: Vec<&'a str>,
+    /// Times of samples.
+    pub times: Vec<FbxTime>,
+    /// Values of samples, with a value for each channel.
+    pub values: Vec<Vec<f64>>,
+}
+
+impl<'a> BakedTrack<'a> {
+    /// Returns whether the track animates a transform property (`Lcl Translation`,
+    /// `Lcl Rotation` or `Lcl Scaling`).
+    pub fn is_transform(&self) -> bool {
+        is_transform(self.target.property)
+    }
+
+    /// Returns the number of samples.
+    pub fn len(&self) -> usize {
+        self.times.len()
+    }
+
+    /// Returns whether the track has no samples.
+    pub fn is_empty(&self) -> bool {
+        self.times.is_empty()
+    }
+
+    /// Removes samples which can be reproduced by linear interpolation within the tolerance.
+    ///
+    /// The first and the last samples are always kept.
+    pub fn reduce(&mut self, tolerance: f64) {
+        if self.times.len() <= 2 {
+            return;
+        }
+        let mut kept = vec![0];
+        let mut anchor = 0;
+        for i in 1..self.times.len() - 1 {
+            // Try to skip key `i` by interpolating from the anchor to the next key.
+            if !self.fits_linear(anchor, i + 1, tolerance) {
+                kept.push(i);
+                anchor = i;
+            }
+        }
+        kept.push(self.times.len() - 1);
+        self.times = kept.iter().map(|&i| self.times[i]).collect();
+        self.values = kept.iter().map(|&i| self.values[i].clone()).collect();
+    }
+
+    /// Checks whether all samples between `start` and `end` are within the tolerance from
+    /// linear interpolation of the two samples.
+    fn fits_linear(&self, start: usize, end: usize, tolerance: f64) -> bool {
+        let (t0, t1) = (self.times[start].0 as f64, self.times[end].0 as f64);
+        (start + 1..end).all(|j| {
+            let s = if t1 > t0 { (self.times[j].0 as f64 - t0) / (t1 - t0) } else { 0.0 };
+            self.values[j].iter().enumerate().all(|(c, &v)| {
+                let (v0, v1) = (self.values[start][c], self.values[end][c]);
+                (v0 + (v1 - v0) * s - v).abs() <= tolerance
+            })
+        })
+    }
+}
+
+
+/// Returns sample times from `start` to `stop` (inclusive) at the frame rate.
+///
+/// `stop` is always included even if it is not on a frame.
+pub fn sample_times(start: FbxTime, stop: FbxTime, frame_rate: f64) -> Result<Vec<FbxTime>> {
+    if !(frame_rate > 0.0 && frame_rate.is_finite()) {
+        return Err(Error::UnexpectedValue(format!("Invalid frame rate: {}", frame_rate)));
+    }
+    if stop < start {
+        return Err(Error::UnexpectedValue(format!("Invalid time range: {:?} to {:?}", start, stop)));
+    }
+    let ticks_per_frame = TICKS_PER_SECOND as f64 / frame_rate;
+    let num_frames = ((stop.0 - start.0) as f64 / ticks_per_frame + 1e-6).floor() as i64;
+    let mut times: Vec<_> = (0..num_frames + 1)
+        .map(|frame| FbxTime(start.0 + (frame as f64 * ticks_per_frame).round() as i64))
+        .collect();
+    match times.last_mut() {
+        // Snap to `stop` if the last frame is almost at `stop`.
+        Some(last) if stop.0 - last.0 <= 1 => *last = stop,
+        _ => times.push(stop),
+    }
+    Ok(times)
+}
+
+/// Samples all properties animated by the stack from `start` to `stop`.
+///
+/// Properties animated only by muted (or not soloed) layers have the
+/// [default values](../animation/struct.AnimationStack.html#method.default_values) of the
+/// channels.
+pub fn bake_stack<'a>(stack: &AnimationStack<'a>, start: FbxTime, stop: FbxTime, options: &BakeOptions) -> Result<Vec<BakedTrack<'a>>> {
+    let times = sample_times(start, stop, options.frame_rate)?;
+    let mut tracks = Vec::new();
+    for target in stack.targets() {
+        if options.transforms_only && !is_transform(target.property) {
+            continue;
+        }
+        let channels = stack.channel_names(target);
+        let values = times.iter()
+            .map(|&time| stack.evaluate(target, time).unwrap_or_else(|| stack.default_values(target)))
+            .collect();
+        let mut track = BakedTrack {
+            target,
+            channels,
+            times: times.clone(),
+            values,
+        };
+        if let Some(tolerance) = options.reduction_tolerance {
+            track.reduce(tolerance);
+        }
+        tracks.push(track);
+    }
+    Ok(tracks)
+}
+
+
+fn is_transform(property: &str) -> bool {
+    matches!(property, "Lcl Translation" | "Lcl Rotation" | "Lcl Scaling")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use scene::animation::{AnimationChannel, AnimationCurve, AnimationCurveNode, AnimationLayer, AnimationStack, AnimationTarget,
+                           Interpolation, Keyframe, LayerBlendMode, TangentMode};
+    use scene::time::{FbxTime, TICKS_PER_SECOND};
+    use super::{BakeOptions, BakedTrack, bake_stack, sample_times};
+
+    const TRANSLATION: AnimationTarget<'static> = AnimationTarget { object: 1, property: "Lcl Translation" };
+    const SCALING: AnimationTarget<'static> = AnimationTarget { object: 1, property: "Lcl Scaling" };
+
+    /// Returns a linear curve from `from` at 0s to `to` at 1s.
+    fn curve(from: f64, to: f64) -> AnimationCurve<'static> {
+        let key = |seconds, value| Keyframe {
+            time: FbxTime::from_seconds(seconds),
+            value,
+            interpolation: Interpolation::Linear,
+            tangent_mode: TangentMode::Auto,
+            right_slope: 0.0,
+            next_left_slope: 0.0,
+            right_weight: 1.0 / 3.0,
+            next_left_weight: 1.0 / 3.0,
+            tcb: [0.0; 3],
+        };
+        AnimationCurve { id: 0, name: "", default: from, keys: vec![key(0.0, from), key(1.0, to)] }
+    }
+
+    /// Returns a curve node with the given channels, with a curve from `0` to `10` for channels
+    /// listed in `animated`.
+    fn curve_node(target: AnimationTarget<'static>, defaults: &[(&'static str, f64)], animated: &[&str]) -> AnimationCurveNode<'static> {
+        AnimationCurveNode {
+            id: 0,
+            name: "",
+            channels: defaults.iter().map(|&(name, default)| AnimationChannel {
+                name,
+                default,
+                curve: if animated.contains(&name) { Some(curve(0.0, 10.0)) } else { None },
+            }).collect(),
+            target: Some(target),
+        }
+    }
+
+    fn layer(mute: bool, curve_nodes: Vec<AnimationCurveNode<'static>>) -> AnimationLayer<'static> {
+        AnimationLayer {
+            id: 0,
+            name: "",
+            weight: 100.0,
+            mute,
+            solo: false,
+            blend_mode: LayerBlendMode::Additive,
+            curve_nodes,
+        }
+    }
+
+    fn stack(layers: Vec<AnimationLayer<'static>>) -> AnimationStack<'static> {
+        AnimationStack {
+            id: 0,
+            name: "",
+            local_start: None,
+            local_stop: None,
+            reference_start: None,
+            reference_stop: None,
+            layers,
+        }
+    }
+
+    fn track(values: &[[f64; 2]]) -> BakedTrack<'static> {
+        BakedTrack {
+            target: TRANSLATION,
+            channels: vec!["X", "Y"],
+            times: (0..values.len() as i64).map(FbxTime).collect(),
+            values: values.iter().map(|v| v.to_vec()).collect(),
+        }
+    }
+
+    #[test]
+    fn sample_times_on_frames() {
+        let times = sample_times(FbxTime(0), FbxTime::from_seconds(1.0), 4.0).unwrap();
+        let seconds: Vec<_> = times.iter().map(|time| time.seconds()).collect();
+        assert_eq!(seconds, [0.0, 0.25, 0.5, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn sample_times_snap_to_stop() {
+        let ticks_per_frame = TICKS_PER_SECOND / 10;
+        let start = FbxTime(100);
+        // The last frame is one tick before `stop`, and replaced with it.
+        let stop = FbxTime(start.0 + 2 * ticks_per_frame + 1);
+        assert_eq!(sample_times(start, stop, 10.0).unwrap(), [start, FbxTime(start.0 + ticks_per_frame), stop]);
+        // `stop` is appended if it is not near a frame.
+        let stop = FbxTime(start.0 + 2 * ticks_per_frame + 1000);
+        let times = sample_times(start, stop, 10.0).unwrap();
+        assert_eq!(times.len(), 4);
+        assert_eq!(times[2], FbxTime(start.0 + 2 * ticks_per_frame));
+        assert_eq!(times[3], stop);
+        // Zero length range.
+        assert_eq!(sample_times(start, start, 10.0).unwrap(), [start]);
+    }
+
+    #[test]
+    fn sample_times_invalid() {
+        assert!(sample_times(FbxTime(0), FbxTime(1), 0.0).is_err());
+        assert!(sample_times(FbxTime(0), FbxTime(1), f64::NAN).is_err());
+        assert!(sample_times(FbxTime(1), FbxTime(0), 30.0).is_err());
+    }
+
+    #[test]
+    fn reduce() {
+        let mut track = track(&[[0.0, 5.0], [1.0, 5.0], [2.0, 5.0], [3.0, 5.0], [3.0, 5.0], [3.0, 6.0]]);
+        track.reduce(1e-9);
+        assert_eq!(track.times, [FbxTime(0), FbxTime(3), FbxTime(4), FbxTime(5)]);
+        assert_eq!(track.values, [[0.0, 5.0], [3.0, 5.0], [3.0, 5.0], [3.0, 6.0]]);
+    }
+
+    #[test]
+    fn reduce_tolerance() {
+        let values = [[0.0, 0.0], [1.05, 0.0], [2.0, 0.0]];
+        let mut loose = track(&values);
+        loose.reduce(0.1);
+        assert_eq!(loose.values, [[0.0, 0.0], [2.0, 0.0]]);
+        let mut tight = track(&values);
+        tight.reduce(0.01);
+        assert_eq!(tight.len(), 3);
+        // Short tracks are kept as is.
+        let mut short = track(&values[..2]);
+        short.reduce(10.0);
+        assert_eq!(short.len(), 2);
+    }
+
+    #[test]
+    fn muted_layer_uses_defaults() {
+        let xyz = [("X", 1.0), ("Y", 1.0), ("Z", 1.0)];
+        let stack = stack(vec![
+            layer(false, vec![curve_node(TRANSLATION, &[("X", 0.0), ("Y", 0.0), ("Z", 0.0)], &["X"])]),
+            layer(true, vec![curve_node(SCALING, &xyz, &["X", "Y", "Z"])]),
+        ]);
+        let tracks = bake_stack(&stack, FbxTime(0), FbxTime::from_seconds(1.0), &BakeOptions::new(2.0)).unwrap();
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].target, TRANSLATION);
+        assert_eq!(tracks[0].values, [[0.0, 0.0, 0.0], [5.0, 0.0, 0.0], [10.0, 0.0, 0.0]]);
+        // Scaling is animated only by the muted layer, and is not collapsed to zero.
+        assert_eq!(tracks[1].target, SCALING);
+        assert_eq!(tracks[1].values, vec![vec![1.0; 3]; 3]);
+    }
+
+    #[test]
+    fn missing_channel_uses_default() {
+        let stack = stack(vec![
+            layer(true, vec![curve_node(SCALING, &[("X", 1.0), ("Y", 2.0), ("Z", 3.0)], &[])]),
+            layer(false, vec![curve_node(SCALING, &[("X", 1.0)], &["X"])]),
+        ]);
+        assert_eq!(stack.channel_names(SCALING), ["X", "Y", "Z"]);
+        assert_eq!(stack.evaluate(SCALING, FbxTime::from_seconds(0.5)), Some(vec![5.0, 2.0, 3.0]));
+    }
+}
@@ -0,0 +1,439 @@
+//! Contains blend shapes (morph targets).
+//!
+//! A mesh with blend shapes has the following connections:
+//!
+//! ```text
+//! Geometry (Mesh) <- Deformer (BlendShape) <- Deformer (BlendShapeChannel) <- Geometry (Shape)
+//! ```
+//!
+//! A channel has one shape usually, and more shapes for in-between targets.
+//! `FullWeights` of the channel are the deform percents where each shape is fully applied.
+//!
+//! Shapes are sparse: `Vertices` and `Normals` are offsets from the base mesh for control
+//! points listed in `Indexes`.
+
+use error::{Error, Result};
+use scene::{check_deformer, check_subclass, child_property, child_vec_f64, child_vec_i32, is_deformer, to_vec3};
+use scene::object_graph::{Object, ObjectGraph};
+use scene::properties70::{Properties70, PropertyValue};
+
+
+/// `Shape` geometry.
+#[derive(Debug, Clone)]
+pub struct Shape<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Shape name.
+    pub name: &'a str,
+    /// Indices of control points moved by the shape (`Indexes`).
+    pub indices: Vec<u32>,
+    /// Position offsets of the control points (`Vertices`).
+    pub vertices: Vec<[f64; 3]>,
+    /// Normal offsets of the control points (`Normals`), if exists.
+    pub normals: Option<Vec<[f64; 3]>>,
+}
+
+impl<'a> Shape<'a> {
+    /// Decodes the `Shape` geometry.
+    pub fn from_object(object: &Object<'a>) -> Result<Self> {
+        check_subclass(object, "Geometry", "Shape")?;
+        let node = object.node;
+        let indices = child_vec_i32(node, "Indexes")?.unwrap_or_default();
+        if let Some(&index) = indices.iter().find(|&&index| index < 0) {
+            return Err(Error::DataError(format!("Shape {} has a negative control point index {}", object.id, index)));
+        }
+        let vertices = to_vec3(&child_vec_f64(node, "Vertices")?.unwrap_or_default(), node, "Vertices")?;
+        if vertices.len() != indices.len() {
+            return Err(Error::DataError(format!("Shape {} has {} indices but {} vertices", object.id, indices.len(), vertices.len())));
+        }
+        let normals = match child_vec_f64(node, "Normals")? {
+            Some(normals) => {
+                let normals = to_vec3(&normals, node, "Normals")?;
+                if normals.len() != indices.len() {
+                    return Err(Error::DataError(format!("Shape {} has {} indices but {} normals", object.id, indices.len(), normals.len())));
+                }
+                Some(normals)
+            },
+            None => None,
+        };
+        Ok(Shape {
+            id: object.id,
+            name: object.name,
+            indices: indices.into_iter().map(|index| index as u32).collect(),
+            vertices,
+            normals,
+        })
+    }
+
+    /// Returns position offsets of all control points.
+    ///
+    /// Control points not moved by the shape have zero offsets.
+    pub fn deltas(&self, num_control_points: usize) -> Result<Vec<[f64; 3]>> {
+        let mut deltas = vec![[0.0; 3]; num_control_points];
+        self.add_deltas(&mut deltas, 1.0)?;
+        Ok(deltas)
+    }
+
+    /// Returns normal offsets of all control points, if the shape has normals.
+    pub fn normal_deltas(&self, num_control_points: usize) -> Result<Option<Vec<[f64; 3]>>> {
+        let normals = match self.normals {
+            Some(ref normals) => normals,
+            None => return Ok(None),
+        };
+        let mut deltas = vec![[0.0; 3]; num_control_points];
+        scatter_add(self.id, &self.indices, normals, &mut deltas, 1.0)?;
+        Ok(Some(deltas))
+    }
+
+    /// Returns absolute positions of all control points with the shape fully applied.
+    ///
+    /// `base` is the control points of the base mesh.
+    pub fn positions(&self, base: &[[f64; 3]]) -> Result<Vec<[f64; 3]>> {
+        let mut positions = base.to_vec();
+        self.add_deltas(&mut positions, 1.0)?;
+        Ok(positions)
+    }
+
+    /// Adds position offsets multiplied by `weight` to `targets`.
+    pub fn add_deltas(&self, targets: &mut [[f64; 3]], weight: f64) -> Result<()> {
+        scatter_add(self.id, &self.indices, &self.vertices, targets, weight)
+    }
+}
+
+fn scatter_add(id: i64, indices: &[u32], values: &[[f64; 3]], targets: &mut [[f64; 3]], weight: f64) -> Result<()> {
+    let len = targets.len();
+    for (&index, value) in indices.iter().zip(values) {
+        let target = targets.get_mut(index as usize).ok_or_else(|| {
+            Error::DataError(format!("Shape {} refers to control point {}, but the geometry has {}", id, index, len))
+        })?;
+        for (t, v) in target.iter_mut().zip(value) {
+            *t += v * weight;
+        }
+    }
+    Ok(())
+}
+
+
+/// `BlendShapeChannel` sub-deformer.
+#[derive(Debug, Clone)]
+pub struct BlendShapeChannel<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Channel name.
+    pub name: &'a str,
+    /// Current deform percent (`DeformPercent`), in the range of `0.0` to `100.0` usually.
+    pub deform_percent: f64,
+    /// Deform percents at which each shape is fully applied (`FullWeights`), in ascending order.
+    pub full_weights: Vec<f64>,
+    /// Shapes in order of `full_weights`.
+    ///
+    /// Shapes other than the last one are in-between shapes.
+    pub shapes: Vec<Shape<'a>>,
+}
+
+impl<'a> BlendShapeChannel<'a> {
+    /// Decodes the `BlendShapeChannel` deformer with its shapes.
+    ///
+    /// If `FullWeights` is missing or inconsistent with the number of shapes, shapes are placed
+    /// at even intervals up to 100 percent.
+    pub fn from_object(object: &Object<'a>, graph: &ObjectGraph<'a>) -> Result<Self> {
+        check_deformer(object, "BlendShapeChannel")?;
+        let node = object.node;
+        let properties = Properties70::from_parent(node)?;
+        let deform_percent = properties.value("DeformPercent").and_then(PropertyValue::as_f64)
+            .or_else(|| child_property(node, "DeformPercent").and_then(|prop| prop.as_f64()))
+            .unwrap_or(0.0);
+        let mut shapes = Vec::new();
+        for child in graph.child_objects(object.id) {
+            if child.class == "Geometry" && child.subclass == "Shape" {
+                shapes.push(Shape::from_object(child)?);
+            }
+        }
+        let mut full_weights = child_vec_f64(node, "FullWeights")?.unwrap_or_default();
+        if full_weights.len() != shapes.len() {
+            let n = shapes.len() as f64;
+            full_weights = (1..shapes.len() + 1).map(|i| 100.0 * i as f64 / n).collect();
+        }
+        // Sort shapes by full weights, keeping the order of connections for the same weights.
+        let mut targets: Vec<_> = full_weights.into_iter().zip(shapes).collect();
+        targets.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(::std::cmp::Ordering::Equal));
+        let (full_weights, shapes) = targets.into_iter().unzip();
+        Ok(BlendShapeChannel {
+            id: object.id,
+            name: object.name,
+            deform_percent,
+            full_weights,
+            shapes,
+        })
+    }
+
+    /// Returns the weight of each shape at the given deform percent.
+    ///
+    /// Below the first full weight, the first shape is scaled linearly from zero.
+    /// Between two full weights, the two adjacent shapes are blended linearly.
+    /// Above the last full weight, the last shape is extrapolated.
+    pub fn shape_weights(&self, percent: f64) -> Vec<(usize, f64)> {
+        let weights = &self.full_weights;
+        let last = match weights.len() {
+            0 => return Vec::new(),
+            n => n - 1,
+        };
+        if percent <= weights[0] || last == 0 {
+            return if weights[0] == 0.0 { Vec::new() } else { vec![(0, percent / weights[0])] };
+        }
+        if percent >= weights[last] {
+            return vec![(last, percent / weights[last])];
+        }
+        let upper = weights.iter().position(|&w| w > percent).unwrap_or(last);
+        let lower = upper - 1;
+        let t = (percent - weights[lower]) / (weights[upper] - weights[lower]);
+        vec![(lower, 1.0 - t), (upper, t)]
+    }
+
+    /// Returns position offsets of all control points at the given deform percent.
+    pub fn deltas(&self, num_control_points: usize, percent: f64) -> Result<Vec<[f64; 3]>> {
+        let mut deltas = vec![[0.0; 3]; num_control_points];
+        for (shape, weight) in self.shape_weights(percent) {
+            self.shapes[shape].add_deltas(&mut deltas, weight)?;
+        }
+        Ok(deltas)
+    }
+}
+
+
+/// `BlendShape` deformer.
+#[derive(Debug, Clone)]
+pub struct BlendShape<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Blend shape name.
+    pub name: &'a str,
+    /// Channels in order of connection.
+    pub channels: Vec<BlendShapeChannel<'a>>,
+    /// ID of the geometry deformed by the blend shape.
+    pub geometry: Option<i64>,
+}
+
+impl<'a> BlendShape<'a> {
+    /// Decodes the `BlendShape` deformer with its channels.
+    pub fn from_object(object: &Object<'a>, graph: &ObjectGraph<'a>) -> Result<Self> {
+        check_deformer(object, "BlendShape")?;
+        let mut channels = Vec::new();
+        for child in graph.child_objects(object.id) {
+            if is_deformer(child, "BlendShapeChannel") {
+                channels.push(BlendShapeChannel::from_object(child, graph)?);
+            }
+        }
+        let geometry = graph.parent_objects(object.id)
+            .find(|parent| parent.class == "Geometry")
+            .map(|geometry| geometry.id);
+        Ok(BlendShape {
+            id: object.id,
+            name: object.name,
+            channels,
+            geometry,
+        })
+    }
+
+    /// Returns positions of the control points with all channels applied at their current
+    /// deform percents.
+    ///
+    /// `base` is the control points of the base mesh.
+    pub fn deform(&self, base: &[[f64; 3]]) -> Result<Vec<[f64; 3]>> {
+        let mut positions = base.to_vec();
+        for channel in &self.channels {
+            for (shape, weight) in channel.shape_weights(channel.deform_percent) {
+                channel.shapes[shape].add_deltas(&mut positions, weight)?;
+            }
+        }
+        Ok(positions)
+    }
+}
+
+/// Returns blend shape deformers connected to the geometry.
+pub fn geometry_blend_shapes<'a>(graph: &ObjectGraph<'a>, geometry: i64) -> Result<Vec<BlendShape<'a>>> {
+    let mut blend_shapes = Vec::new();
+    for child in graph.child_objects(geometry) {
+        if is_deformer(child, "BlendShape") {
+            blend_shapes.push(BlendShape::from_object(child, graph)?);
+        }
+    }
+    Ok(blend_shapes)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use property::Property;
+    use test_util::{connection, node, object, p, tree, TestNode};
+
+    fn shape(id: i64, offset: f64) -> Shape<'static> {
+        Shape {
+            id,
+            name: "",
+            indices: vec![1],
+            vertices: vec![[offset, 0.0, 0.0]],
+            normals: None,
+        }
+    }
+
+    fn channel(full_weights: Vec<f64>) -> BlendShapeChannel<'static> {
+        BlendShapeChannel {
+            id: 1,
+            name: "",
+            deform_percent: 0.0,
+            shapes: (0..full_weights.len()).map(|i| shape(10 + i as i64, (i + 1) as f64)).collect(),
+            full_weights,
+        }
+    }
+
+    #[test]
+    fn single_shape() {
+        let channel = channel(vec![100.0]);
+        assert_eq!(channel.shape_weights(0.0), [(0, 0.0)]);
+        assert_eq!(channel.shape_weights(25.0), [(0, 0.25)]);
+        assert_eq!(channel.shape_weights(100.0), [(0, 1.0)]);
+        // Extrapolated.
+        assert_eq!(channel.shape_weights(150.0), [(0, 1.5)]);
+    }
+
+    #[test]
+    fn in_between_shapes() {
+        let channel = channel(vec![50.0, 100.0]);
+        // Below the first full weight.
+        assert_eq!(channel.shape_weights(25.0), [(0, 0.5)]);
+        assert_eq!(channel.shape_weights(50.0), [(0, 1.0)]);
+        // Between two full weights.
+        assert_eq!(channel.shape_weights(75.0), [(0, 0.5), (1, 0.5)]);
+        let weights = channel.shape_weights(60.0);
+        assert_eq!(weights.iter().map(|w| w.0).collect::<Vec<_>>(), [0, 1]);
+        assert!((weights[0].1 - 0.8).abs() < 1e-12 && (weights[1].1 - 0.2).abs() < 1e-12);
+        // At or above the last full weight.
+        assert_eq!(channel.shape_weights(100.0), [(1, 1.0)]);
+        assert_eq!(channel.shape_weights(120.0), [(1, 1.2)]);
+        // Deltas of control point 1 are blended: 0.5 * 1.0 + 0.5 * 2.0.
+        assert_eq!(channel.deltas(2, 75.0).unwrap(), [[0.0; 3], [1.5, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn three_shapes() {
+        let channel = channel(vec![25.0, 50.0, 100.0]);
+        assert_eq!(channel.shape_weights(30.0), [(0, 0.8), (1, 0.2)]);
+        assert_eq!(channel.shape_weights(50.0), [(1, 1.0), (2, 0.0)]);
+        assert_eq!(channel.shape_weights(75.0), [(1, 0.5), (2, 0.5)]);
+    }
+
+    #[test]
+    fn zero_first_weight() {
+        let channel = channel(vec![0.0, 100.0]);
+        assert!(channel.shape_weights(0.0).is_empty());
+        assert!(channel.shape_weights(-10.0).is_empty());
+        assert_eq!(channel.shape_weights(40.0), [(0, 0.6), (1, 0.4)]);
+        // A single shape fully applied at zero percent has no effect.
+        assert!(self::channel(vec![0.0]).shape_weights(50.0).is_empty());
+    }
+
+    #[test]
+    fn no_shapes() {
+        assert!(channel(vec![]).shape_weights(50.0).is_empty());
+    }
+
+    fn shape_node(id: i64, indices: Vec<i32>, vertices: Vec<f64>, normals: Option<Vec<f64>>) -> TestNode<'static> {
+        let mut children = vec![
+            node("Version", vec![Property::I32(100)], vec![]),
+            node("Indexes", vec![Property::VecI32(indices)], vec![]),
+            node("Vertices", vec![Property::VecF64(vertices)], vec![]),
+        ];
+        if let Some(normals) = normals {
+            children.push(node("Normals", vec![Property::VecF64(normals)], vec![]));
+        }
+        object("Geometry", id, "Smile\0\x01Geometry", "Shape", children)
+    }
+
+    /// Returns `Geometry (20) <- BlendShape (30) <- BlendShapeChannel (40, 41) <- Shape`.
+    fn morphed(shape_51: TestNode<'static>) -> Vec<TestNode<'static>> {
+        vec![
+            node("Objects", vec![], vec![
+                object("Geometry", 20, "\0\x01Geometry", "Mesh", vec![]),
+                object("Deformer", 30, "Face\0\x01Deformer", "BlendShape", vec![]),
+                // In-between shape 51 at 50%, connected after the full shape 50.
+                object("Deformer", 40, "Smile\0\x01SubDeformer", "BlendShapeChannel", vec![
+                    node("DeformPercent", vec![Property::F64(10.0)], vec![]),
+                    node("FullWeights", vec![Property::VecF64(vec![100.0, 50.0])], vec![]),
+                    node("Properties70", vec![], vec![p("DeformPercent", "Number", "A", vec![Property::F64(75.0)])]),
+                ]),
+                // No `FullWeights`, and `DeformPercent` only as a child node.
+                object("Deformer", 41, "Blink\0\x01SubDeformer", "BlendShapeChannel", vec![
+                    node("DeformPercent", vec![Property::F64(50.0)], vec![]),
+                ]),
+                shape_node(50, vec![0, 2], vec![1.0, 0.0, 0.0, 0.0, 2.0, 0.0], Some(vec![0.0, 0.0, 1.0, 0.0, 0.0, 1.0])),
+                shape_51,
+                shape_node(52, vec![1], vec![0.0, 0.0, 4.0], None),
+                shape_node(53, vec![1], vec![0.0, 0.0, 8.0], None),
+            ]),
+            node("Connections", vec![], vec![
+                connection(30, 20),
+                connection(40, 30),
+                connection(41, 30),
+                connection(50, 40),
+                connection(51, 40),
+                connection(52, 41),
+                connection(53, 41),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn blend_shapes_from_tree() {
+        let tree = tree(7400, &morphed(shape_node(51, vec![0], vec![0.5, 0.0, 0.0], None)));
+        let graph = ObjectGraph::from_tree(&tree);
+        let blend_shapes = geometry_blend_shapes(&graph, 20).unwrap();
+        assert_eq!(blend_shapes.len(), 1);
+        let blend_shape = &blend_shapes[0];
+        assert_eq!((blend_shape.id, blend_shape.name, blend_shape.geometry), (30, "Face", Some(20)));
+        assert_eq!(blend_shape.channels.iter().map(|c| c.id).collect::<Vec<_>>(), [40, 41]);
+
+        let smile = &blend_shape.channels[0];
+        // `Properties70` takes precedence over the child node.
+        assert_eq!(smile.deform_percent, 75.0);
+        // Shapes are sorted by full weights.
+        assert_eq!(smile.full_weights, [50.0, 100.0]);
+        assert_eq!(smile.shapes.iter().map(|s| s.id).collect::<Vec<_>>(), [51, 50]);
+        let full = &smile.shapes[1];
+        assert_eq!(full.indices, [0, 2]);
+        assert_eq!(full.vertices, [[1.0, 0.0, 0.0], [0.0, 2.0, 0.0]]);
+        assert_eq!(full.normal_deltas(3).unwrap(), Some(vec![[0.0, 0.0, 1.0], [0.0; 3], [0.0, 0.0, 1.0]]));
+        assert_eq!(smile.shapes[0].normal_deltas(3).unwrap(), None);
+
+        let blink = &blend_shape.channels[1];
+        assert_eq!(blink.deform_percent, 50.0);
+        // Evenly spaced without `FullWeights`.
+        assert_eq!(blink.full_weights, [50.0, 100.0]);
+        assert_eq!(blink.shapes.iter().map(|s| s.id).collect::<Vec<_>>(), [52, 53]);
+
+        // Smile at 75%: half of the in-between and half of the full shape.
+        // Blink at 50%: the first shape fully.
+        let base = [[0.0; 3]; 3];
+        assert_eq!(blend_shape.deform(&base).unwrap(), [[0.75, 0.0, 0.0], [0.0, 0.0, 4.0], [0.0, 1.0, 0.0]]);
+        assert!(geometry_blend_shapes(&graph, 50).unwrap().is_empty());
+    }
+
+    #[test]
+    fn shape_length_mismatch() {
+        let check = |shape: TestNode<'static>| {
+            let tree = tree(7400, &morphed(shape));
+            let graph = ObjectGraph::from_tree(&tree);
+            assert!(Shape::from_object(graph.object(51).unwrap()).is_err());
+            assert!(geometry_blend_shapes(&graph, 20).is_err());
+        };
+        // Two indices and one vertex.
+        check(shape_node(51, vec![0, 1], vec![0.5, 0.0, 0.0], None));
+        // One index and two normals.
+        check(shape_node(51, vec![0], vec![0.5, 0.0, 0.0], Some(vec![0.0; 6])));
+        // Vertices not a multiple of 3.
+        check(shape_node(51, vec![0], vec![0.5, 0.0], None));
+        // Negative index.
+        check(shape_node(51, vec![-1], vec![0.5, 0.0, 0.0], None));
+    }
+}
@@ -0,0 +1,322 @@
+//! Contains property templates of the `Definitions` section.
+//!
+//! `Definitions` has an `ObjectType` child for each class of objects, and it may have
+//! `PropertyTemplate` children with default property values:
+//!
+//! ```text
+//! Definitions:  {
+//!     Version: 100
+//!     Count: 3
+//!     ObjectType: "Model" {
+//!         Count: 2
+//!         PropertyTemplate: "FbxNode" {
+//!             Properties70:  { P: "Visibility", "Visibility", "", "A",1 ... }
+//!         }
+//!     }
+//!     ...
+//! }
+//! ```
+//!
+//! Writers omit `P` records of objects whose values are the same as the template, so the
+//! effective value of a property is the object's record if exists, otherwise the template's.
+//!
+//! Types decoding objects with properties, such as `Material`, `Camera`, `Light` and
+//! `TransformProperties`, have two constructors. `from_object()` reads only the records of the
+//! object, and missing properties have default values of FBX SDK even if the file has a
+//! template for them. `from_object_with()` applies the template chosen by
+//! [`Definitions::template_for()`](struct.Definitions.html#method.template_for) first, so the
+//! result is the same as FBX SDK unless the template differs from the SDK defaults.
+
+use std::slice;
+use error::Result;
+use property::Property;
+use scene::{child_i32, child_string};
+use scene::object_graph::Object;
+use scene::properties70::{Properties70, PropertyRecord, PropertyValue};
+use tree::{Node, Tree};
+
+
+/// A `PropertyTemplate` node.
+#[derive(Debug)]
+pub struct PropertyTemplate<'a> {
+    /// Template name, such as `FbxNode` and `FbxSurfacePhong`.
+    pub name: &'a str,
+    /// Default property values.
+    pub properties: Properties70<'a>,
+}
+
+impl<'a> PropertyTemplate<'a> {
+    /// Decodes the `PropertyTemplate` node.
+    pub fn from_node(node: &'a Node) -> Result<Self> {
+        Ok(PropertyTemplate {
+            name: first_string(node),
+            properties: Properties70::from_parent(node)?,
+        })
+    }
+
+    /// Returns whether the template is for objects of the given subclass or shading model.
+    ///
+    /// Comparison is case-insensitive and ignores the `Fbx` and `FbxSurface` prefixes, e.g.
+    /// `FbxCamera` matches `Camera` and `FbxSurfacePhong` matches `phong`.
+    fn matches(&self, hint: &str) -> bool {
+        let name = self.name.trim_start_matches("Fbx");
+        let name = name.strip_prefix("Surface").unwrap_or(name);
+        let hint = match hint {
+            // Skeleton attributes have subclasses of the model types.
+            "LimbNode" | "Root" => "Skeleton",
+            hint => hint,
+        };
+        name.eq_ignore_ascii_case(hint)
+    }
+}
+
+
+/// An `ObjectType` node.
+#[derive(Debug)]
+pub struct ObjectType<'a> {
+    /// Class name of objects, such as `Model` and `Material`.
+    pub name: &'a str,
+    /// Number of objects of the class (`Count`).
+    pub count: i32,
+    /// Property templates.
+    ///
+    /// Usually there is at most one template, but some classes have a template for each
+    /// subclass (e.g. `FbxSurfaceLambert` and `FbxSurfacePhong` of `Material`).
+    pub templates: Vec<PropertyTemplate<'a>>,
+}
+
+impl<'a> ObjectType<'a> {
+    /// Decodes the `ObjectType` node.
+    pub fn from_node(node: &'a Node) -> Result<Self> {
+        Ok(ObjectType {
+            name: first_string(node),
+            count: child_i32(node, "Count")?.unwrap_or(0),
+            templates: node.children_by_name("PropertyTemplate").map(PropertyTemplate::from_node).collect::<Result<_>>()?,
+        })
+    }
+}
+
+
+/// Data of `Definitions` node.
+#[derive(Debug, Default)]
+pub struct Definitions<'a> {
+    /// `Version`.
+    pub version: Option<i32>,
+    /// Total number of objects (`Count`).
+    pub count: i32,
+    object_types: Vec<ObjectType<'a>>,
+}
+
+impl<'a> Definitions<'a> {
+    /// Decodes the `Definitions` node.
+    pub fn from_node(node: &'a Node) -> Result<Self> {
+        Ok(Definitions {
+            version: child_i32(node, "Version")?,
+            count: child_i32(node, "Count")?.unwrap_or(0),
+            object_types: node.children_by_name("ObjectType").map(ObjectType::from_node).collect::<Result<_>>()?,
+        })
+    }
+
+    /// Decodes the `Definitions` top-level node of the tree.
+    ///
+    /// Returns empty definitions if the tree has no `Definitions` node.
+    pub fn from_tree(tree: &'a Tree) -> Result<Self> {
+        match tree.first_child_by_name("Definitions") {
+            Some(node) => Definitions::from_node(node),
+            None => Ok(Definitions::default()),
+        }
+    }
+
+    /// Returns an iterator of the object types.
+    pub fn object_types(&self) -> slice::Iter<'_, ObjectType<'a>> {
+        self.object_types.iter()
+    }
+
+    /// Returns the first object type with the given name.
+    ///
+    /// Object types are named after the node names of objects, such as `Model` and
+    /// `AnimationStack`.
+    pub fn object_type(&self, name: &str) -> Option<&ObjectType<'a>> {
+        self.object_types.iter().find(|object_type| object_type.name == name)
+    }
+
+    /// Returns property templates of all object types with the given name.
+    pub fn templates<'d>(&'d self, name: &'d str) -> impl Iterator<Item = &'d PropertyTemplate<'a>> + 'd {
+        self.object_types.iter()
+            .filter(move |object_type| object_type.name == name)
+            .flat_map(|object_type| &object_type.templates)
+    }
+
+    /// Returns the property template applied to the object.
+    ///
+    /// The template is chosen by the object's subclass (or the shading model for materials),
+    /// and `None` is returned if no template matches the object, even if its object type has
+    /// only one template.
+    pub fn template_for(&self, object: &Object) -> Result<Option<&PropertyTemplate<'a>>> {
+        let hint = match &*object.node.name {
+            "Material" => child_string(object.node, "ShadingModel")?.unwrap_or_else(|| "lambert".to_owned()),
+            "NodeAttribute" | "Geometry" | "Deformer" => object.subclass.to_owned(),
+            // Templates of these classes are not named after the class.
+            "Model" => "Node".to_owned(),
+            "Texture" => "FileTexture".to_owned(),
+            _ => object.class.to_owned(),
+        };
+        let mut templates = self.object_types.iter()
+            .filter(|object_type| *object_type.name == *object.node.name)
+            .flat_map(|object_type| &object_type.templates);
+        Ok(templates.find(|template| template.matches(&hint)))
+    }
+
+    /// Returns properties of the object, with default values from the template.
+    pub fn properties_of<'d>(&'d self, object: &Object<'a>) -> Result<EffectiveProperties<'d, 'a>> {
+        Ok(EffectiveProperties {
+            properties: Properties70::from_parent(object.node)?,
+            template: self.template_for(object)?,
+        })
+    }
+}
+
+
+/// Properties of an object with fallback to a property template.
+#[derive(Debug)]
+pub struct EffectiveProperties<'d, 'a: 'd> {
+    /// Properties of the object itself.
+    pub properties: Properties70<'a>,
+    /// The property template applied to the object.
+    pub template: Option<&'d PropertyTemplate<'a>>,
+}
+
+impl<'d, 'a> EffectiveProperties<'d, 'a> {
+    /// Returns the record with the given name, from the object or the template.
+    pub fn get(&self, name: &str) -> Option<&PropertyRecord<'a>> {
+        self.properties.get(name).or_else(|| self.template.and_then(|template| template.properties.get(name)))
+    }
+
+    /// Returns the effective value of the property with the given name.
+    pub fn value(&self, name: &str) -> Option<&PropertyValue<'a>> {
+        self.get(name).map(|record| &record.value)
+    }
+
+    /// Returns whether the property is omitted in the object and its value comes from the
+    /// template.
+    pub fn is_default(&self, name: &str) -> bool {
+        self.properties.get(name).is_none() && matches!(self.template, Some(template) if template.properties.get(name).is_some())
+    }
+
+    /// Returns all effective records as `Properties70`.
+    ///
+    /// Template records not overridden by the object are cloned.
+    pub fn to_properties(&self) -> Properties70<'a> {
+        self.iter().cloned().collect()
+    }
+
+    /// Returns an iterator of all effective records.
+    ///
+    /// Records of the object come first, followed by template records not overridden by the
+    /// object.
+    pub fn iter(&self) -> impl Iterator<Item = &PropertyRecord<'a>> + '_ {
+        let defaults = self.template.into_iter()
+            .flat_map(|template| template.properties.iter())
+            .filter(move |record| self.properties.get(record.name).is_none());
+        self.properties.iter().chain(defaults)
+    }
+}
+
+
+fn first_string(node: &Node) -> &str {
+    node.properties.iter().next().as_ref().and_then(Property::get_string).unwrap_or("")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scene::object_graph::ObjectGraph;
+    use test_util::{node, object, p, string, tree, TestNode};
+
+
+    fn object_type(name: &'static str, templates: &[&'static str]) -> TestNode<'static> {
+        let templates = templates.iter().map(|&template| {
+            node("PropertyTemplate", vec![string(template)], vec![node("Properties70", vec![], vec![])])
+        });
+        node("ObjectType", vec![string(name)], templates.collect())
+    }
+
+    fn shading_model(name: &'static str) -> TestNode<'static> {
+        node("ShadingModel", vec![string(name)], vec![])
+    }
+
+    #[test]
+    fn template_for() {
+        let tree = tree(7400, &[
+            node("Definitions", vec![], vec![
+                object_type("Model", &["FbxNode"]),
+                object_type("Texture", &["FbxFileTexture"]),
+                object_type("NodeAttribute", &["FbxCamera", "FbxSkeleton"]),
+                object_type("Material", &["FbxSurfacePhong", "FbxSurfaceLambert"]),
+                object_type("Deformer", &[]),
+            ]),
+            node("Objects", vec![], vec![
+                object("Model", 1, "Mesh\0\x01Model", "Mesh", vec![]),
+                object("Texture", 2, "Texture\0\x01Texture", "", vec![]),
+                object("NodeAttribute", 3, "\0\x01NodeAttribute", "LimbNode", vec![]),
+                object("NodeAttribute", 4, "\0\x01NodeAttribute", "Root", vec![]),
+                object("NodeAttribute", 5, "\0\x01NodeAttribute", "Camera", vec![]),
+                object("NodeAttribute", 6, "\0\x01NodeAttribute", "Light", vec![]),
+                object("Material", 7, "Phong\0\x01Material", "", vec![shading_model("Phong")]),
+                object("Material", 8, "Lambert\0\x01Material", "", vec![shading_model("lambert")]),
+                object("Material", 9, "Default\0\x01Material", "", vec![]),
+                object("Material", 10, "Unknown\0\x01Material", "", vec![shading_model("unknown")]),
+                object("Deformer", 11, "Skin\0\x01Deformer", "Skin", vec![]),
+            ]),
+        ]);
+        let definitions = Definitions::from_tree(&tree).unwrap();
+        let graph = ObjectGraph::from_tree(&tree);
+        let template = |id| definitions.template_for(graph.object(id).unwrap()).unwrap().map(|template| template.name);
+        assert_eq!(template(1), Some("FbxNode"));
+        assert_eq!(template(2), Some("FbxFileTexture"));
+        assert_eq!(template(3), Some("FbxSkeleton"));
+        assert_eq!(template(4), Some("FbxSkeleton"));
+        assert_eq!(template(5), Some("FbxCamera"));
+        assert_eq!(template(6), None);
+        assert_eq!(template(7), Some("FbxSurfacePhong"));
+        assert_eq!(template(8), Some("FbxSurfaceLambert"));
+        assert_eq!(template(9), Some("FbxSurfaceLambert"));
+        assert_eq!(template(10), None);
+        assert_eq!(template(11), None);
+    }
+
+    #[test]
+    fn effective_properties() {
+        let tree = tree(7400, &[
+            node("Definitions", vec![], vec![
+                node("ObjectType", vec![string("Model")], vec![
+                    node("PropertyTemplate", vec![string("FbxNode")], vec![
+                        node("Properties70", vec![], vec![p("A", "Number", "A", vec![Property::F64(1.0)]), p("B", "Number", "A", vec![Property::F64(2.0)]), p("C", "Number", "A", vec![Property::F64(3.0)])]),
+                    ]),
+                ]),
+            ]),
+            node("Objects", vec![], vec![
+                object("Model", 1, "Mesh\0\x01Model", "Mesh", vec![
+                    node("Properties70", vec![], vec![p("D", "Number", "A", vec![Property::F64(40.0)]), p("B", "Number", "A", vec![Property::F64(20.0)])]),
+                ]),
+            ]),
+        ]);
+        let definitions = Definitions::from_tree(&tree).unwrap();
+        let graph = ObjectGraph::from_tree(&tree);
+        let properties = definitions.properties_of(graph.object(1).unwrap()).unwrap();
+        let value = |name| properties.get(name).and_then(|record| record.value.as_f64());
+        assert_eq!(value("A"), Some(1.0));
+        assert_eq!(value("B"), Some(20.0));
+        assert_eq!(value("D"), Some(40.0));
+        assert_eq!(value("E"), None);
+        assert!(properties.is_default("A"));
+        assert!(!properties.is_default("B"));
+        assert!(!properties.is_default("D"));
+        assert!(!properties.is_default("E"));
+        // Records of the object first, then template records not overridden.
+        let names: Vec<_> = properties.iter().map(|record| record.name).collect();
+        assert_eq!(names, ["D", "B", "A", "C"]);
+        assert_eq!(properties.to_properties().len(), 4);
+    }
+}
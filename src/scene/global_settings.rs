@@ -0,0 +1,320 @@
+//! Contains `GlobalSettings` node data.
+
+use error::{Error, Result};
+use scene::properties70::{Properties70, PropertyValue};
+use scene::time::{FbxTime, TimeMode};
+use tree::{Node, Tree};
+
+
+/// Axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Axis {
+    /// X axis.
+    X,
+    /// Y axis.
+    Y,
+    /// Z axis.
+    Z,
+}
+
+impl Axis {
+    /// Converts the FBX axis value (`0` for X, `1` for Y, `2` for Z) to `Axis`.
+    pub fn from_i32(value: i32) -> Option<Self> {
+        match value {
+            0 => Some(Axis::X),
+            1 => Some(Axis::Y),
+            2 => Some(Axis::Z),
+            _ => None,
+        }
+    }
+
+    /// Returns the index of the axis (`0` for X, `1` for Y, `2` for Z).
+    pub fn index(self) -> usize {
+        match self {
+            Axis::X => 0,
+            Axis::Y => 1,
+            Axis::Z => 2,
+        }
+    }
+}
+
+
+/// Axis with direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SignedAxis {
+    /// Axis.
+    pub axis: Axis,
+    /// Direction, `1` for positive or `-1` for negative.
+    pub sign: i32,
+}
+
+impl SignedAxis {
+    /// Returns the unit vector of the axis.
+    pub fn to_vector(self) -> [f64; 3] {
+        let mut v = [0.0; 3];
+        v[self.axis.index()] = self.sign as f64;
+        v
+    }
+}
+
+
+/// Coordinate axis system.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AxisSystem {
+    /// Up axis (`UpAxis` and `UpAxisSign`).
+    pub up: SignedAxis,
+    /// Front axis (`FrontAxis` and `FrontAxisSign`).
+    pub front: SignedAxis,
+    /// Coordinate axis (`CoordAxis` and `CoordAxisSign`).
+    pub coord: SignedAxis,
+}
+
+impl AxisSystem {
+    /// Returns whether the axis system is right-handed.
+    ///
+    /// The coordinate axis is the X axis of the system, the up axis is Y and the front axis
+    /// is Z.
+    pub fn is_right_handed(&self) -> bool {
+        let x = self.coord.to_vector();
+        let y = self.up.to_vector();
+        let z = self.front.to_vector();
+        let cross = [x[1] * y[2] - x[2] * y[1], x[2] * y[0] - x[0] * y[2], x[0] * y[1] - x[1] * y[0]];
+        cross[0] * z[0] + cross[1] * z[1] + cross[2] * z[2] > 0.0
+    }
+}
+
+impl Default for AxisSystem {
+    /// Returns the Y-up right-handed axis system, which is the default of FBX.
+    fn default() -> Self {
+        AxisSystem {
+            up: SignedAxis { axis: Axis::Y, sign: 1 },
+            front: SignedAxis { axis: Axis::Z, sign: 1 },
+            coord: SignedAxis { axis: Axis::X, sign: 1 },
+        }
+    }
+}
+
+
+/// Data of `GlobalSettings` node.
+///
+/// Settings missing in the file have default values of FBX SDK.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlobalSettings {
+    /// Axis system.
+    pub axis_system: AxisSystem,
+    /// `UnitScaleFactor`, size of a unit in centimeters.
+    pub unit_scale_factor: f64,
+    /// `OriginalUnitScaleFactor`.
+    pub original_unit_scale_factor: f64,
+    /// `TimeMode`.
+    pub time_mode: TimeMode,
+    /// `CustomFrameRate`, used when `time_mode` is `TimeMode::Custom`.
+    ///
+    /// `-1.0` if missing in the file.
+    pub custom_frame_rate: f64,
+    /// `TimeSpanStart`.
+    pub time_span_start: FbxTime,
+    /// `TimeSpanStop`.
+    pub time_span_stop: FbxTime,
+    /// `DefaultCamera`.
+    pub default_camera: String,
+}
+
+impl GlobalSettings {
+    /// Constructs `GlobalSettings` from the `GlobalSettings` node.
+    ///
+    /// Unknown `TimeMode` values are logged and treated as `TimeMode::Default`.
+    pub fn from_node(node: &Node) -> Result<Self> {
+        let mut settings = GlobalSettings::default();
+        for record in Properties70::from_parent(node)?.iter() {
+            let name = record.name;
+            let value = &record.value;
+            let axis_system = &mut settings.axis_system;
+            match name {
+                "UpAxis" => axis_system.up.axis = axis(name, value)?,
+                "UpAxisSign" => axis_system.up.sign = sign(name, value)?,
+                "FrontAxis" => axis_system.front.axis = axis(name, value)?,
+                "FrontAxisSign" => axis_system.front.sign = sign(name, value)?,
+                "CoordAxis" => axis_system.coord.axis = axis(name, value)?,
+                "CoordAxisSign" => axis_system.coord.sign = sign(name, value)?,
+                "UnitScaleFactor" => settings.unit_scale_factor = f64_value(name, value)?,
+                "OriginalUnitScaleFactor" => settings.original_unit_scale_factor = f64_value(name, value)?,
+                "TimeMode" => {
+                    let mode = value.as_i64().ok_or_else(|| unexpected_value(name, value))?;
+                    settings.time_mode = TimeMode::from_i32(mode as i32).unwrap_or_else(|| {
+                        warn!("Unknown time mode {}, using the default time mode", mode);
+                        TimeMode::Default
+                    });
+                },
+                "CustomFrameRate" => settings.custom_frame_rate = f64_value(name, value)?,
+                "TimeSpanStart" => settings.time_span_start = time_value(name, value)?,
+                "TimeSpanStop" => settings.time_span_stop = time_value(name, value)?,
+                "DefaultCamera" => {
+                    settings.default_camera = value.as_str().ok_or_else(|| unexpected_value(name, value))?.to_owned();
+                },
+                _ => {},
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Constructs `GlobalSettings` from the `GlobalSettings` top-level node of the tree.
+    ///
+    /// Returns `Ok(None)` if the tree has no `GlobalSettings` node.
+    pub fn from_tree(tree: &Tree) -> Result<Option<Self>> {
+        match tree.first_child_by_name("GlobalSettings") {
+            Some(node) => GlobalSettings::from_node(node).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the time span (`TimeSpanStart` and `TimeSpanStop`).
+    pub fn time_span(&self) -> (FbxTime, FbxTime) {
+        (self.time_span_start, self.time_span_stop)
+    }
+
+    /// Returns the frame rate in frames per second.
+    ///
+    /// Returns `CustomFrameRate` for `TimeMode::Custom`, and 30 frames/s for
+    /// `TimeMode::Default`.
+    /// Returns `None` for `TimeMode::Custom` if `CustomFrameRate` is missing or not positive.
+    pub fn frame_rate(&self) -> Option<f64> {
+        match self.time_mode {
+            TimeMode::Default => Some(30.0),
+            TimeMode::Custom if self.custom_frame_rate > 0.0 => Some(self.custom_frame_rate),
+            TimeMode::Custom => None,
+            mode => mode.frame_rate(),
+        }
+    }
+}
+
+impl Default for GlobalSettings {
+    fn default() -> Self {
+        GlobalSettings {
+            axis_system: AxisSystem::default(),
+            unit_scale_factor: 1.0,
+            original_unit_scale_factor: 1.0,
+            time_mode: TimeMode::Default,
+            custom_frame_rate: -1.0,
+            time_span_start: FbxTime(0),
+            time_span_stop: FbxTime(0),
+            default_camera: "Producer Perspective".to_owned(),
+        }
+    }
+}
+
+
+fn unexpected_value(name: &str, value: &PropertyValue) -> Error {
+    Error::UnexpectedValue(format!("Unexpected value for global setting `{}`: {:?}", name, value))
+}
+
+fn f64_value(name: &str, value: &PropertyValue) -> Result<f64> {
+    value.as_f64().ok_or_else(|| unexpected_value(name, value))
+}
+
+fn time_value(name: &str, value: &PropertyValue) -> Result<FbxTime> {
+    value.as_time().ok_or_else(|| unexpected_value(name, value))
+}
+
+fn axis(name: &str, value: &PropertyValue) -> Result<Axis> {
+    value.as_i64().and_then(|v| Axis::from_i32(v as i32)).ok_or_else(|| unexpected_value(name, value))
+}
+
+fn sign(name: &str, value: &PropertyValue) -> Result<i32> {
+    match value.as_i64() {
+        Some(v) if v > 0 => Ok(1),
+        Some(v) if v < 0 => Ok(-1),
+        _ => Err(unexpected_value(name, value)),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use property::Property;
+    use scene::time::{FbxTime, TimeMode};
+    use test_util::{node, p, tree, TestNode};
+    use super::{Axis, AxisSystem, GlobalSettings, SignedAxis};
+
+
+    fn settings(records: Vec<TestNode<'static>>) -> GlobalSettings {
+        let tree = tree(7400, &[node("GlobalSettings", vec![], vec![
+            node("Version", vec![Property::I32(1000)], vec![]),
+            node("Properties70", vec![], records),
+        ])]);
+        GlobalSettings::from_tree(&tree).unwrap().unwrap()
+    }
+
+    #[test]
+    fn default_settings() {
+        let settings = settings(vec![]);
+        assert_eq!(settings, GlobalSettings::default());
+        assert!(settings.axis_system.is_right_handed());
+        assert!(GlobalSettings::from_tree(&tree(7400, &[])).unwrap().is_none());
+    }
+
+    #[test]
+    fn axis_system() {
+        // Z-up right-handed (such as 3ds Max): up +Z, front -Y, coord +X.
+        let settings = settings(vec![
+            p("UpAxis", "int", "", vec![Property::I32(2)]),
+            p("UpAxisSign", "int", "", vec![Property::I32(1)]),
+            p("FrontAxis", "int", "", vec![Property::I32(1)]),
+            p("FrontAxisSign", "int", "", vec![Property::I32(-1)]),
+            p("CoordAxis", "int", "", vec![Property::I32(0)]),
+            p("CoordAxisSign", "int", "", vec![Property::I32(1)]),
+            p("UnitScaleFactor", "double", "", vec![Property::F64(2.54)]),
+        ]);
+        assert_eq!(settings.axis_system, AxisSystem {
+            up: SignedAxis { axis: Axis::Z, sign: 1 },
+            front: SignedAxis { axis: Axis::Y, sign: -1 },
+            coord: SignedAxis { axis: Axis::X, sign: 1 },
+        });
+        assert!(settings.axis_system.is_right_handed());
+        assert_eq!(settings.axis_system.front.to_vector(), [0.0, -1.0, 0.0]);
+        assert_eq!(settings.unit_scale_factor, 2.54);
+
+        // Y-up left-handed: front -Z.
+        let settings = self::settings(vec![p("FrontAxisSign", "int", "", vec![Property::I32(-1)])]);
+        assert!(!settings.axis_system.is_right_handed());
+    }
+
+    #[test]
+    fn invalid_axis() {
+        let tree = tree(7400, &[node("GlobalSettings", vec![], vec![
+            node("Properties70", vec![], vec![p("UpAxis", "int", "", vec![Property::I32(3)])]),
+        ])]);
+        assert!(GlobalSettings::from_tree(&tree).is_err());
+    }
+
+    #[test]
+    fn frame_rate() {
+        assert_eq!(settings(vec![]).frame_rate(), Some(30.0));
+        let pal = settings(vec![p("TimeMode", "enum", "", vec![Property::I32(10)])]);
+        assert_eq!(pal.time_mode, TimeMode::Pal);
+        assert_eq!(pal.frame_rate(), Some(25.0));
+        let ntsc = settings(vec![p("TimeMode", "enum", "", vec![Property::I32(8)])]);
+        assert_eq!(ntsc.frame_rate(), Some(30000.0 / 1001.0));
+
+        let custom = settings(vec![
+            p("TimeMode", "enum", "", vec![Property::I32(14)]),
+            p("CustomFrameRate", "double", "", vec![Property::F64(12.5)]),
+        ]);
+        assert_eq!(custom.frame_rate(), Some(12.5));
+        // `CustomFrameRate` is missing.
+        let custom = settings(vec![p("TimeMode", "enum", "", vec![Property::I32(14)])]);
+        assert_eq!(custom.frame_rate(), None);
+    }
+
+    #[test]
+    fn unknown_time_mode() {
+        let settings = settings(vec![
+            p("TimeMode", "enum", "", vec![Property::I32(100)]),
+            p("TimeSpanStop", "KTime", "", vec![Property::I64(46_186_158_000)]),
+        ]);
+        assert_eq!(settings.time_mode, TimeMode::Default);
+        assert_eq!(settings.frame_rate(), Some(30.0));
+        // Following settings are still read.
+        assert_eq!(settings.time_span(), (FbxTime(0), FbxTime(46_186_158_000)));
+    }
+}
@@ -0,0 +1,252 @@
+//! Contains `FBXHeaderExtension` node data.
+
+use std::io::Read;
+use error::{Error, Result};
+use event::FbxEvent;
+use reader::EventReader;
+use scene::{child_i32, child_string};
+use tree::Node;
+
+
+/// Data of `FBXHeaderExtension` node.
+///
+/// Fields are `None` if the corresponding child nodes don't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderExtension {
+    /// `FBXHeaderVersion`.
+    pub header_version: Option<i32>,
+    /// `FBXVersion`.
+    pub fbx_version: Option<i32>,
+    /// `EncryptionType`.
+    pub encryption_type: Option<i32>,
+    /// `CreationTimeStamp`.
+    pub creation_time_stamp: Option<TimeStamp>,
+    /// `Creator`, usually name and version of the exporter.
+    pub creator: Option<String>,
+    /// `SceneInfo`.
+    pub scene_info: Option<SceneInfo>,
+}
+
+impl HeaderExtension {
+    /// Constructs `HeaderExtension` from the `FBXHeaderExtension` node.
+    pub fn from_node(node: &Node) -> Result<Self> {
+        Ok(HeaderExtension {
+            header_version: child_i32(node, "FBXHeaderVersion")?,
+            fbx_version: child_i32(node, "FBXVersion")?,
+            encryption_type: child_i32(node, "EncryptionType")?,
+            creation_time_stamp: match node.first_child_by_name("CreationTimeStamp") {
+                Some(child) => Some(TimeStamp::from_node(child)?),
+                None => None,
+            },
+            creator: child_string(node, "Creator")?,
+            scene_info: match node.first_child_by_name("SceneInfo") {
+                Some(child) => Some(SceneInfo::from_node(child)?),
+                None => None,
+            },
+        })
+    }
+}
+
+
+/// Data of `CreationTimeStamp` node.
+///
+/// Missing fields are filled with `0`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeStamp {
+    /// `Version`.
+    pub version: i32,
+    /// `Year`.
+    pub year: i32,
+    /// `Month` (1-12).
+    pub month: i32,
+    /// `Day` (1-31).
+    pub day: i32,
+    /// `Hour` (0-23).
+    pub hour: i32,
+    /// `Minute` (0-59).
+    pub minute: i32,
+    /// `Second` (0-59).
+    pub second: i32,
+    /// `Millisecond` (0-999).
+    pub millisecond: i32,
+}
+
+impl TimeStamp {
+    /// Constructs `TimeStamp` from the `CreationTimeStamp` node.
+    pub fn from_node(node: &Node) -> Result<Self> {
+        let field = |name| child_i32(node, name).map(|v| v.unwrap_or(0));
+        Ok(TimeStamp {
+            version: field("Version")?,
+            year: field("Year")?,
+            month: field("Month")?,
+            day: field("Day")?,
+            hour: field("Hour")?,
+            minute: field("Minute")?,
+            second: field("Second")?,
+            millisecond: field("Millisecond")?,
+        })
+    }
+}
+
+
+/// Data of `SceneInfo` node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneInfo {
+    /// Name of the scene info object, usually `"GlobalInfo\u{0}\u{1}SceneInfo"`.
+    pub name: Option<String>,
+    /// `Type`, usually `"UserData"`.
+    pub type_: Option<String>,
+    /// `Version`.
+    pub version: Option<i32>,
+    /// `MetaData`.
+    pub meta_data: MetaData,
+}
+
+impl SceneInfo {
+    /// Constructs `SceneInfo` from the `SceneInfo` node.
+    pub fn from_node(node: &Node) -> Result<Self> {
+        Ok(SceneInfo {
+            name: node.properties.iter().next().and_then(|p| p.get_string()).map(|s| s.to_owned()),
+            type_: child_string(node, "Type")?,
+            version: child_i32(node, "Version")?,
+            meta_data: match node.first_child_by_name("MetaData") {
+                Some(child) => MetaData::from_node(child)?,
+                None => MetaData::default(),
+            },
+        })
+    }
+}
+
+
+/// Data of `MetaData` node in `SceneInfo`.
+///
+/// Fields are `None` if the corresponding child nodes don't exist.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MetaData {
+    /// `Version`.
+    pub version: Option<i32>,
+    /// `Title`.
+    pub title: Option<String>,
+    /// `Subject`.
+    pub subject: Option<String>,
+    /// `Author`.
+    pub author: Option<String>,
+    /// `Keywords`.
+    pub keywords: Option<String>,
+    /// `Revision`.
+    pub revision: Option<String>,
+    /// `Comment`.
+    pub comment: Option<String>,
+}
+
+impl MetaData {
+    /// Constructs `MetaData` from the `MetaData` node.
+    pub fn from_node(node: &Node) -> Result<Self> {
+        Ok(MetaData {
+            version: child_i32(node, "Version")?,
+            title: child_string(node, "Title")?,
+            subject: child_string(node, "Subject")?,
+            author: child_string(node, "Author")?,
+            keywords: child_string(node, "Keywords")?,
+            revision: child_string(node, "Revision")?,
+            comment: child_string(node, "Comment")?,
+        })
+    }
+}
+
+
+/// Reads `FBXHeaderExtension` node from the reader and stops.
+///
+/// The reader should be at the beginning of the FBX stream, or right after the `StartFbx` event.
+/// `FBXHeaderExtension` is always the first top-level node, so the reader stops right after
+/// the `EndNode` event of `FBXHeaderExtension`, or right before the first top-level node if it
+/// is not `FBXHeaderExtension`.
+///
+/// Returns `Ok(None)` if the FBX data has no `FBXHeaderExtension` node.
+pub fn read_header_extension<R: Read>(reader: &mut EventReader<R>) -> Result<Option<HeaderExtension>> {
+    if let FbxEvent::StartFbx(_) = *reader.peek()? {
+        reader.next()?;
+    }
+    match *reader.peek()? {
+        FbxEvent::StartNode { ref name, .. } if &**name == "FBXHeaderExtension" => {},
+        FbxEvent::StartNode { .. } | FbxEvent::EndFbx => return Ok(None),
+        ref ev => return Err(Error::UnexpectedValue(format!("Expected `StartNode` or `EndFbx` event, but got {:?}", ev))),
+    }
+    match reader.next()? {
+        FbxEvent::StartNode { name, properties } => {
+            let node = Node::read_from(reader, name, properties)?;
+            HeaderExtension::from_node(&node).map(Some)
+        },
+        _ => unreachable!("The peeked event should be `StartNode`"),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use event::FbxEvent;
+    use property::Property;
+    use reader::EventReader;
+    use test_util::{fbx, node, null_record_len, TestNode};
+    use super::read_header_extension;
+
+    fn header_extension() -> TestNode<'static> {
+        node("FBXHeaderExtension", vec![], vec![
+            node("FBXHeaderVersion", vec![Property::I32(1003)], vec![]),
+            node("FBXVersion", vec![Property::I32(7400)], vec![]),
+            node("CreationTimeStamp", vec![], vec![
+                node("Year", vec![Property::I32(2020)], vec![]),
+                node("Month", vec![Property::I32(4)], vec![]),
+            ]),
+            node("Creator", vec![Property::String(Ok("exporter"))], vec![]),
+        ])
+    }
+
+    fn objects() -> TestNode<'static> {
+        node("Objects", vec![], vec![node("Model", vec![Property::I64(1)], vec![])])
+    }
+
+    #[test]
+    fn stops_after_header_extension() {
+        for &version in &[7400, 7500] {
+            // The header extension followed by bytes which are not a valid node.
+            let header_only = fbx(version, &[header_extension()]);
+            let mut data = header_only[..header_only.len() - null_record_len(version)].to_vec();
+            data.extend(vec![0xff; 64]);
+
+            let mut reader = EventReader::new(&data[..]);
+            let header = read_header_extension(&mut reader).unwrap().unwrap();
+            assert_eq!(header.fbx_version, Some(7400));
+            assert_eq!(header.creator.as_ref().map(|s| &s[..]), Some("exporter"));
+            let time_stamp = header.creation_time_stamp.unwrap();
+            assert_eq!((time_stamp.year, time_stamp.month, time_stamp.day), (2020, 4, 0));
+            // The broken bytes are read only when the reader is resumed.
+            assert!(reader.next().is_err());
+        }
+    }
+
+    #[test]
+    fn resume_after_header_extension() {
+        let data = fbx(7400, &[header_extension(), objects()]);
+        let mut reader = EventReader::new(&data[..]);
+        assert!(read_header_extension(&mut reader).unwrap().is_some());
+        match reader.next().unwrap() {
+            FbxEvent::StartNode { ref name, .. } => assert_eq!(&**name, "Objects"),
+            ev => panic!("Expected `StartNode`, but got {:?}", ev),
+        }
+    }
+
+    #[test]
+    fn no_header_extension() {
+        let data = fbx(7400, &[objects(), header_extension()]);
+        let mut reader = EventReader::new(&data[..]);
+        assert_eq!(read_header_extension(&mut reader).unwrap(), None);
+        // The first top-level node is not consumed.
+        match reader.next().unwrap() {
+            FbxEvent::StartNode { ref name, .. } => assert_eq!(&**name, "Objects"),
+            ev => panic!("Expected `StartNode`, but got {:?}", ev),
+        }
+        let data = fbx(7400, &[]);
+        assert_eq!(read_header_extension(&mut EventReader::new(&data[..])).unwrap(), None);
+    }
+}
@@ -0,0 +1,310 @@
+//! Contains the hierarchy of models.
+//!
+//! Models are connected to their parent models by `OO` connections, and top-level models are
+//! connected to the root (object ID `0`).
+
+use std::collections::HashMap;
+use std::slice;
+use error::{Error, Result};
+use scene::object_graph::{ObjectGraph, ROOT_ID, parent_model};
+use scene::properties70::{Properties70, PropertyValue};
+
+
+/// Type of a model, i.e. its subclass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeType {
+    /// Mesh (`Mesh`).
+    Mesh,
+    /// Skeleton bone (`LimbNode`).
+    LimbNode,
+    /// Skeleton root (`Root`).
+    Root,
+    /// Empty transform node (`Null`).
+    Null,
+    /// Camera (`Camera`).
+    Camera,
+    /// Light (`Light`).
+    Light,
+    /// Other types.
+    Other,
+}
+
+impl NodeType {
+    /// Returns the node type of the model subclass.
+    pub fn from_subclass(subclass: &str) -> Self {
+        match subclass {
+            "Mesh" => NodeType::Mesh,
+            "LimbNode" => NodeType::LimbNode,
+            "Root" => NodeType::Root,
+            "Null" => NodeType::Null,
+            "Camera" => NodeType::Camera,
+            "Light" => NodeType::Light,
+            _ => NodeType::Other,
+        }
+    }
+}
+
+
+/// A model in the hierarchy.
+#[derive(Debug, Clone)]
+pub struct SceneNode<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Model name.
+    pub name: &'a str,
+    /// Model subclass, such as `Mesh` and `LimbNode`.
+    pub subclass: &'a str,
+    /// Node type.
+    pub node_type: NodeType,
+    /// Whether the model is visible.
+    ///
+    /// False if `Show` is false or `Visibility` is zero.
+    /// Visibility of ancestors is not taken into account.
+    pub visible: bool,
+    /// Index of the parent node, or `None` for top-level nodes.
+    pub parent: Option<usize>,
+    /// Indices of the child nodes, in order of connection.
+    pub children: Vec<usize>,
+    /// IDs of attached `NodeAttribute` objects.
+    pub attributes: Vec<i64>,
+    /// ID of the attached `Geometry` object.
+    pub geometry: Option<i64>,
+}
+
+
+/// Hierarchy of models.
+#[derive(Debug, Clone)]
+pub struct Hierarchy<'a> {
+    nodes: Vec<SceneNode<'a>>,
+    roots: Vec<usize>,
+    indices: HashMap<i64, usize>,
+}
+
+impl<'a> Hierarchy<'a> {
+    /// Builds the hierarchy of all models in the object graph.
+    ///
+    /// Models without parent models are top-level nodes.
+    pub fn from_graph(graph: &ObjectGraph<'a>) -> Result<Self> {
+        let mut nodes = Vec::new();
+        let mut indices = HashMap::new();
+        for object in graph.objects().filter(|object| object.class == "Model") {
+            let properties = Properties70::from_parent(object.node)?;
+            let show = properties.value("Show").and_then(PropertyValue::as_bool).unwrap_or(true);
+            let visibility = properties.value("Visibility").and_then(PropertyValue::as_f64).unwrap_or(1.0);
+            let mut attributes = Vec::new();
+            let mut geometry = None;
+            for child in graph.child_objects(object.id) {
+                match child.class {
+                    "NodeAttribute" => attributes.push(child.id),
+                    "Geometry" if geometry.is_none() => geometry = Some(child.id),
+                    _ => {},
+                }
+            }
+            indices.insert(object.id, nodes.len());
+            nodes.push(SceneNode {
+                id: object.id,
+                name: object.name,
+                subclass: object.subclass,
+                node_type: NodeType::from_subclass(object.subclass),
+                visible: show && visibility != 0.0,
+                parent: None,
+                children: Vec::new(),
+                attributes,
+                geometry,
+            });
+        }
+
+        let mut roots = Vec::new();
+        for index in 0..nodes.len() {
+            match parent_model(graph, nodes[index].id).and_then(|id| indices.get(&id).cloned()) {
+                Some(parent) => {
+                    nodes[index].parent = Some(parent);
+                    nodes[parent].children.push(index);
+                },
+                None => roots.push(index),
+            }
+        }
+        // Order top-level nodes and children in order of connection to their parents (the root
+        // for top-level nodes). Other connections of the same child are not relevant.
+        let mut order: HashMap<(i64, i64), usize> = HashMap::new();
+        for (i, connection) in graph.connections().enumerate().filter(|&(_, connection)| connection.parent_property.is_none()) {
+            order.entry((connection.child, connection.parent)).or_insert(i);
+        }
+        let key = |child: &SceneNode, parent: i64| order.get(&(child.id, parent)).cloned().unwrap_or(usize::MAX);
+        roots.sort_by_key(|&index| key(&nodes[index], ROOT_ID));
+        for index in 0..nodes.len() {
+            let mut children = ::std::mem::take(&mut nodes[index].children);
+            children.sort_by_key(|&child| key(&nodes[child], nodes[index].id));
+            nodes[index].children = children;
+        }
+
+        let hierarchy = Hierarchy { nodes, roots, indices };
+        // Every node should be reachable from the roots unless parents form a cycle.
+        let reachable = hierarchy.depth_first().count();
+        if reachable != hierarchy.nodes.len() {
+            return Err(Error::DataError(format!("Model hierarchy has a cycle ({} of {} models are reachable from the root)", reachable, hierarchy.nodes.len())));
+        }
+        Ok(hierarchy)
+    }
+
+    /// Returns all nodes in order of objects.
+    pub fn nodes(&self) -> slice::Iter<'_, SceneNode<'a>> {
+        self.nodes.iter()
+    }
+
+    /// Returns the number of nodes.
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns whether the hierarchy has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns the node at the index.
+    pub fn node(&self, index: usize) -> Option<&SceneNode<'a>> {
+        self.nodes.get(index)
+    }
+
+    /// Returns indices of top-level nodes, i.e. children of the root (ID `0`).
+    pub fn roots(&self) -> &[usize] {
+        &self.roots
+    }
+
+    /// Returns the index of the node with the given object ID.
+    pub fn index_of(&self, id: i64) -> Option<usize> {
+        if id == ROOT_ID {
+            return None;
+        }
+        self.indices.get(&id).cloned()
+    }
+
+    /// Returns the node with the given object ID.
+    pub fn find_by_id(&self, id: i64) -> Option<&SceneNode<'a>> {
+        self.index_of(id).map(|index| &self.nodes[index])
+    }
+
+    /// Returns the first node with the given name, in depth-first order.
+    pub fn find_by_name(&self, name: &str) -> Option<&SceneNode<'a>> {
+        self.depth_first().map(|(_, node)| node).find(|node| node.name == name)
+    }
+
+    /// Returns all nodes with the given name, in depth-first order.
+    pub fn find_all_by_name<'h>(&'h self, name: &'h str) -> impl Iterator<Item = &'h SceneNode<'a>> + 'h {
+        self.depth_first().map(|(_, node)| node).filter(move |node| node.name == name)
+    }
+
+    /// Returns the path of names from the top-level node to the node at the index.
+    pub fn path(&self, index: usize) -> Vec<&'a str> {
+        let mut path = Vec::new();
+        let mut current = self.nodes.get(index);
+        while let Some(node) = current {
+            path.push(node.name);
+            current = node.parent.map(|parent| &self.nodes[parent]);
+        }
+        path.reverse();
+        path
+    }
+
+    /// Returns an iterator over all nodes in depth-first pre-order, with their depths.
+    ///
+    /// Top-level nodes have depth `0`.
+    pub fn depth_first(&self) -> DepthFirst<'_, 'a> {
+        DepthFirst {
+            hierarchy: self,
+            stack: self.roots.iter().rev().map(|&index| (0, index)).collect(),
+        }
+    }
+
+    /// Returns an iterator over the node at the index and its descendants in depth-first
+    /// pre-order, with depths relative to the node.
+    pub fn descendants(&self, index: usize) -> DepthFirst<'_, 'a> {
+        DepthFirst {
+            hierarchy: self,
+            stack: if index < self.nodes.len() { vec![(0, index)] } else { Vec::new() },
+        }
+    }
+}
+
+
+/// Depth-first iterator over nodes of a hierarchy.
+#[derive(Debug, Clone)]
+pub struct DepthFirst<'h, 'a: 'h> {
+    hierarchy: &'h Hierarchy<'a>,
+    /// Nodes to visit, with their depths.
+    stack: Vec<(usize, usize)>,
+}
+
+impl<'h, 'a> Iterator for DepthFirst<'h, 'a> {
+    type Item = (usize, &'h SceneNode<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (depth, index) = self.stack.pop()?;
+        let node = &self.hierarchy.nodes[index];
+        self.stack.extend(node.children.iter().rev().map(|&child| (depth + 1, child)));
+        Some((depth, node))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_util::{connection, node, object, tree, TestNode};
+
+    fn model(id: i64, name_class: &'static str) -> TestNode<'static> {
+        object("Model", id, name_class, "Null", vec![])
+    }
+
+    #[test]
+    fn order_by_connection_to_parent() {
+        let tree = tree(7400, &[
+            node("Objects", vec![], vec![
+                model(1, "A\0\x01Model"),
+                model(2, "B\0\x01Model"),
+                model(3, "C\0\x01Model"),
+                model(4, "D\0\x01Model"),
+                object("CollectionExclusive", 99, "Layer\0\x01DisplayLayer", "DisplayLayer", vec![]),
+            ]),
+            // Connections to the display layer must not affect the order of nodes.
+            node("Connections", vec![], vec![
+                connection(3, 99),
+                connection(1, 99),
+                connection(2, 0),
+                connection(1, 0),
+                connection(4, 1),
+                connection(3, 1),
+                connection(2, 99),
+                connection(4, 99),
+            ]),
+        ]);
+        let graph = ObjectGraph::from_tree(&tree);
+        let hierarchy = Hierarchy::from_graph(&graph).unwrap();
+        let names = |indices: &[usize]| indices.iter().map(|&i| hierarchy.node(i).unwrap().name).collect::<Vec<_>>();
+        assert_eq!(names(hierarchy.roots()), ["B", "A"]);
+        assert_eq!(names(&hierarchy.find_by_name("A").unwrap().children), ["D", "C"]);
+        assert_eq!(hierarchy.find_by_name("C").unwrap().parent, hierarchy.index_of(1));
+    }
+
+    #[test]
+    fn cycle() {
+        let tree = tree(7400, &[
+            node("Objects", vec![], vec![
+                model(1, "A\0\x01Model"),
+                model(2, "B\0\x01Model"),
+                model(3, "C\0\x01Model"),
+            ]),
+            node("Connections", vec![], vec![
+                connection(3, 0),
+                connection(1, 2),
+                connection(2, 1),
+            ]),
+        ]);
+        let graph = ObjectGraph::from_tree(&tree);
+        match Hierarchy::from_graph(&graph) {
+            Err(Error::DataError(_)) => {},
+            result => panic!("Expected cycle error, but got {:?}", result),
+        }
+    }
+}
@@ -0,0 +1,594 @@
+//! Contains layer elements of mesh geometry.
+//!
+//! Layer elements are per-vertex (or per-polygon, per-edge, ...) attributes of meshes such as
+//! normals and UVs.
+//! Each layer element has a mapping mode (`MappingInformationType`) and a reference mode
+//! (`ReferenceInformationType`):
+//!
+//! * Mapping mode determines what each value is bound to.
+//! * Reference mode determines whether values are stored directly (`Direct`), or indirectly
+//!   through an index array (`IndexToDirect`).
+//!
+//! [`LayerElement::resolve()`](struct.LayerElement.html#method.resolve) converts them into
+//! per-polygon-vertex values.
+
+use std::collections::HashMap;
+use error::{Error, Result};
+use scene::{child_property, child_string, child_vec_f64, child_vec_i32};
+use scene::mesh::Mesh;
+use tree::Node;
+
+
+/// Kind of a layer element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerElementKind {
+    /// Normals (`LayerElementNormal`).
+    Normal,
+    /// Binormals (`LayerElementBinormal`).
+    Binormal,
+    /// Tangents (`LayerElementTangent`).
+    Tangent,
+    /// UVs (`LayerElementUV`).
+    Uv,
+    /// Vertex colors (`LayerElementColor`).
+    Color,
+    /// Smoothing groups or flags (`LayerElementSmoothing`).
+    Smoothing,
+}
+
+impl LayerElementKind {
+    /// Returns the kind for the given node name.
+    pub fn from_node_name(name: &str) -> Option<Self> {
+        match name {
+            "LayerElementNormal" => Some(LayerElementKind::Normal),
+            "LayerElementBinormal" => Some(LayerElementKind::Binormal),
+            "LayerElementTangent" => Some(LayerElementKind::Tangent),
+            "LayerElementUV" => Some(LayerElementKind::Uv),
+            "LayerElementColor" => Some(LayerElementKind::Color),
+            "LayerElementSmoothing" => Some(LayerElementKind::Smoothing),
+            _ => None,
+        }
+    }
+
+    /// Returns the node name of the layer element.
+    pub fn node_name(self) -> &'static str {
+        match self {
+            LayerElementKind::Normal => "LayerElementNormal",
+            LayerElementKind::Binormal => "LayerElementBinormal",
+            LayerElementKind::Tangent => "LayerElementTangent",
+            LayerElementKind::Uv => "LayerElementUV",
+            LayerElementKind::Color => "LayerElementColor",
+            LayerElementKind::Smoothing => "LayerElementSmoothing",
+        }
+    }
+
+    /// Returns the names of the data node and the index node.
+    fn data_node_names(self) -> (&'static str, &'static str) {
+        match self {
+            LayerElementKind::Normal => ("Normals", "NormalsIndex"),
+            LayerElementKind::Binormal => ("Binormals", "BinormalsIndex"),
+            LayerElementKind::Tangent => ("Tangents", "TangentsIndex"),
+            LayerElementKind::Uv => ("UV", "UVIndex"),
+            LayerElementKind::Color => ("Colors", "ColorIndex"),
+            LayerElementKind::Smoothing => ("Smoothing", "SmoothingIndex"),
+        }
+    }
+
+    /// Returns the number of components of each value.
+    pub fn num_components(self) -> usize {
+        match self {
+            LayerElementKind::Normal | LayerElementKind::Binormal | LayerElementKind::Tangent => 3,
+            LayerElementKind::Uv => 2,
+            LayerElementKind::Color => 4,
+            LayerElementKind::Smoothing => 1,
+        }
+    }
+}
+
+
+/// Mapping mode (`MappingInformationType`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MappingMode {
+    /// A value for each polygon vertex (`ByPolygonVertex`).
+    ByPolygonVertex,
+    /// A value for each control point (`ByControlPoint`, `ByVertice` or `ByVertex`).
+    ByControlPoint,
+    /// A value for each polygon (`ByPolygon`).
+    ByPolygon,
+    /// A value for each edge (`ByEdge`).
+    ByEdge,
+    /// A value for the whole mesh (`AllSame`).
+    AllSame,
+}
+
+impl MappingMode {
+    /// Parses the `MappingInformationType` value.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "ByPolygonVertex" => Some(MappingMode::ByPolygonVertex),
+            "ByControlPoint" | "ByVertice" | "ByVertex" => Some(MappingMode::ByControlPoint),
+            "ByPolygon" => Some(MappingMode::ByPolygon),
+            "ByEdge" => Some(MappingMode::ByEdge),
+            "AllSame" => Some(MappingMode::AllSame),
+            _ => None,
+        }
+    }
+}
+
+
+/// Reference mode (`ReferenceInformationType`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReferenceMode {
+    /// Values are stored directly (`Direct`).
+    Direct,
+    /// Values are referred through an index array (`IndexToDirect` or `Index`).
+    IndexToDirect,
+}
+
+impl ReferenceMode {
+    /// Parses the `ReferenceInformationType` value.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "Direct" => Some(ReferenceMode::Direct),
+            "IndexToDirect" | "Index" => Some(ReferenceMode::IndexToDirect),
+            _ => None,
+        }
+    }
+}
+
+
+/// A layer element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerElement {
+    /// Kind.
+    pub kind: LayerElementKind,
+    /// Layer index (the first property of the layer element node).
+    pub index: i32,
+    /// Name (`Name`), such as UV set name.
+    pub name: String,
+    /// Mapping mode.
+    pub mapping: MappingMode,
+    /// Reference mode.
+    pub reference: ReferenceMode,
+    /// Values, flattened.
+    ///
+    /// Each value has `kind.num_components()` components.
+    /// Smoothing values are integers converted to `f64`.
+    pub data: Vec<f64>,
+    /// Index array, for `ReferenceMode::IndexToDirect`.
+    pub indices: Vec<i32>,
+}
+
+impl LayerElement {
+    /// Decodes the layer element node.
+    pub fn from_node(node: &Node) -> Result<Self> {
+        let kind = LayerElementKind::from_node_name(&node.name)
+            .ok_or_else(|| Error::UnexpectedValue(format!("Unknown layer element: `{}`", node.name)))?;
+        let index = node.properties.iter().next().and_then(|p| p.as_i32()).unwrap_or(0);
+        let mapping_str = child_string(node, "MappingInformationType")?.unwrap_or_default();
+        let mapping = MappingMode::parse(&mapping_str)
+            .ok_or_else(|| Error::UnexpectedValue(format!("Unknown mapping mode of `{}`: {:?}", node.name, mapping_str)))?;
+        let reference_str = child_string(node, "ReferenceInformationType")?.unwrap_or_else(|| "Direct".to_owned());
+        let reference = ReferenceMode::parse(&reference_str)
+            .ok_or_else(|| Error::UnexpectedValue(format!("Unknown reference mode of `{}`: {:?}", node.name, reference_str)))?;
+        let (data_name, index_name) = kind.data_node_names();
+        let data = if kind == LayerElementKind::Smoothing {
+            match child_property(node, data_name) {
+                Some(prop) => prop.as_vec_i64().map(|v| v.iter().map(|&v| v as f64).collect())
+                    .ok_or_else(|| Error::UnexpectedValue(format!("Expected integer array for `{}/{}`, but got {:?}", node.name, data_name, prop)))?,
+                None => Vec::new(),
+            }
+        } else {
+            child_vec_f64(node, data_name)?.unwrap_or_default()
+        };
+        if data.len() % kind.num_components() != 0 {
+            return Err(Error::DataError(format!("Length of `{}/{}` is not a multiple of {}: {}", node.name, data_name, kind.num_components(), data.len())));
+        }
+        let indices = match reference {
+            ReferenceMode::Direct => Vec::new(),
+            ReferenceMode::IndexToDirect => child_vec_i32(node, index_name)?.unwrap_or_default(),
+        };
+        Ok(LayerElement {
+            kind,
+            index,
+            name: child_string(node, "Name")?.unwrap_or_default(),
+            mapping,
+            reference,
+            data,
+            indices,
+        })
+    }
+
+    /// Returns the number of values.
+    pub fn len(&self) -> usize {
+        self.data.len() / self.kind.num_components()
+    }
+
+    /// Returns whether there are no values.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the value.
+    pub fn value(&self, index: usize) -> &[f64] {
+        let num_components = self.kind.num_components();
+        &self.data[index * num_components..(index + 1) * num_components]
+    }
+
+    /// Returns the value index for each polygon vertex of the mesh.
+    pub fn resolve_indices(&self, mesh: &Mesh) -> Result<Vec<usize>> {
+        let num_polygon_vertices = mesh.polygon_vertices.len();
+        // Index into the mapped sequence (polygon vertices, control points, ...).
+        let mapped: Vec<usize> = match self.mapping {
+            MappingMode::ByPolygonVertex => (0..num_polygon_vertices).collect(),
+            MappingMode::ByControlPoint => mesh.polygon_vertices.iter().map(|&v| v as usize).collect(),
+            MappingMode::ByPolygon => {
+                let mut mapped = Vec::with_capacity(num_polygon_vertices);
+                for polygon in 0..mesh.num_polygons() {
+                    let len = mesh.polygon_range(polygon).len();
+                    mapped.extend((0..len).map(|_| polygon));
+                }
+                mapped
+            },
+            MappingMode::ByEdge => {
+                let mut edges = HashMap::with_capacity(mesh.edges.len());
+                for edge in 0..mesh.edges.len() {
+                    let [a, b] = mesh.edge_vertices(edge);
+                    edges.entry((a.min(b), a.max(b))).or_insert(edge);
+                }
+                let mut mapped = Vec::with_capacity(num_polygon_vertices);
+                for polygon in 0..mesh.num_polygons() {
+                    let vertices = mesh.polygon(polygon);
+                    for (i, &a) in vertices.iter().enumerate() {
+                        let b = vertices[(i + 1) % vertices.len()];
+                        let edge = edges.get(&(a.min(b), a.max(b)))
+                            .ok_or_else(|| Error::DataError(format!("Edge ({}, {}) of polygon {} is not in `Edges`", a, b, polygon)))?;
+                        mapped.push(*edge);
+                    }
+                }
+                mapped
+            },
+            MappingMode::AllSame => vec![0; num_polygon_vertices],
+        };
+        let resolved = match self.reference {
+            ReferenceMode::Direct => mapped,
+            ReferenceMode::IndexToDirect => {
+                let mut resolved = Vec::with_capacity(mapped.len());
+                for i in mapped {
+                    match self.indices.get(i) {
+                        Some(&index) if index >= 0 => resolved.push(index as usize),
+                        Some(&index) => return Err(Error::DataError(format!("Negative index {} in `{}`", index, self.kind.node_name()))),
+                        None => return Err(Error::DataError(format!("Index array of `{}` is too short: {}", self.kind.node_name(), self.indices.len()))),
+                    }
+                }
+                resolved
+            },
+        };
+        let len = self.len();
+        if let Some(&index) = resolved.iter().find(|&&index| index >= len) {
+            return Err(Error::DataError(format!("Value index {} of `{}` is out of range (number of values is {})", index, self.kind.node_name(), len)));
+        }
+        Ok(resolved)
+    }
+
+    /// Returns the values for each polygon vertex of the mesh, flattened.
+    ///
+    /// Each value has `kind.num_components()` components.
+    pub fn resolve(&self, mesh: &Mesh) -> Result<Vec<f64>> {
+        let indices = self.resolve_indices(mesh)?;
+        let mut values = Vec::with_capacity(indices.len() * self.kind.num_components());
+        for index in indices {
+            values.extend_from_slice(self.value(index));
+        }
+        Ok(values)
+    }
+
+    /// Returns 2D values (such as UVs) for each polygon vertex of the mesh.
+    pub fn resolve_vec2(&self, mesh: &Mesh) -> Result<Vec<[f64; 2]>> {
+        self.resolve_fixed(mesh, 2, |v| [v[0], v[1]])
+    }
+
+    /// Returns 3D values (such as normals) for each polygon vertex of the mesh.
+    pub fn resolve_vec3(&self, mesh: &Mesh) -> Result<Vec<[f64; 3]>> {
+        self.resolve_fixed(mesh, 3, |v| [v[0], v[1], v[2]])
+    }
+
+    /// Returns 4D values (such as colors) for each polygon vertex of the mesh.
+    pub fn resolve_vec4(&self, mesh: &Mesh) -> Result<Vec<[f64; 4]>> {
+        self.resolve_fixed(mesh, 4, |v| [v[0], v[1], v[2], v[3]])
+    }
+
+    fn resolve_fixed<T, F: Fn(&[f64]) -> T>(&self, mesh: &Mesh, num_components: usize, f: F) -> Result<Vec<T>> {
+        if self.kind.num_components() != num_components {
+            return Err(Error::UnexpectedValue(format!("`{}` has {} components, not {}", self.kind.node_name(), self.kind.num_components(), num_components)));
+        }
+        Ok(self.resolve_indices(mesh)?.into_iter().map(|index| f(self.value(index))).collect())
+    }
+}
+
+
+/// Decodes all layer elements of the kind in the `Geometry` node, sorted by layer index.
+pub fn layer_elements(geometry: &Node, kind: LayerElementKind) -> Result<Vec<LayerElement>> {
+    let mut elements = geometry.children_by_name(kind.node_name())
+        .map(LayerElement::from_node)
+        .collect::<Result<Vec<_>>>()?;
+    elements.sort_by_key(|element| element.index);
+    Ok(elements)
+}
+
+/// Decodes the layer element of the kind with the given name (such as UV set name) in the
+/// `Geometry` node.
+pub fn layer_element_by_name(geometry: &Node, kind: LayerElementKind, name: &str) -> Result<Option<LayerElement>> {
+    for node in geometry.children_by_name(kind.node_name()) {
+        if child_string(node, "Name")?.as_ref().map(|s| &s[..]) == Some(name) {
+            return LayerElement::from_node(node).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+
+/// A material layer element (`LayerElementMaterial`).
+///
+/// Unlike other layer elements, material layer has no values but material indices
+/// (`Materials`), which refer to materials connected to the model in order of connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaterialLayer {
+    /// Layer index (the first property of the layer element node).
+    pub index: i32,
+    /// Name (`Name`).
+    pub name: String,
+    /// Mapping mode, usually `ByPolygon` or `AllSame`.
+    pub mapping: MappingMode,
+    /// Material indices (`Materials`).
+    pub materials: Vec<i32>,
+}
+
+impl MaterialLayer {
+    /// Decodes the `LayerElementMaterial` node.
+    pub fn from_node(node: &Node) -> Result<Self> {
+        let mapping_str = child_string(node, "MappingInformationType")?.unwrap_or_default();
+        let mapping = MappingMode::parse(&mapping_str)
+            .ok_or_else(|| Error::UnexpectedValue(format!("Unknown mapping mode of `{}`: {:?}", node.name, mapping_str)))?;
+        Ok(MaterialLayer {
+            index: node.properties.iter().next().and_then(|p| p.as_i32()).unwrap_or(0),
+            name: child_string(node, "Name")?.unwrap_or_default(),
+            mapping,
+            materials: child_vec_i32(node, "Materials")?.unwrap_or_default(),
+        })
+    }
+
+    /// Returns the material index for each polygon of the mesh.
+    pub fn polygon_materials(&self, mesh: &Mesh) -> Result<Vec<usize>> {
+        let num_polygons = mesh.num_polygons();
+        let material = |i: usize| match self.materials.get(i) {
+            Some(&material) if material >= 0 => Ok(material as usize),
+            Some(&material) => Err(Error::DataError(format!("Negative material index {}", material))),
+            None => Err(Error::DataError(format!("Material index array is too short: {}", self.materials.len()))),
+        };
+        match self.mapping {
+            MappingMode::AllSame => Ok(vec![material(0)?; num_polygons]),
+            MappingMode::ByPolygon => (0..num_polygons).map(material).collect(),
+            mapping => Err(Error::Unimplemented(format!("Mapping mode {:?} for material layer", mapping))),
+        }
+    }
+}
+
+/// Decodes the first material layer in the `Geometry` node.
+///
+/// Returns `Ok(None)` if the geometry has no material layers.
+pub fn material_layer(geometry: &Node) -> Result<Option<MaterialLayer>> {
+    let mut layers = geometry.children_by_name("LayerElementMaterial")
+        .map(MaterialLayer::from_node)
+        .collect::<Result<Vec<_>>>()?;
+    layers.sort_by_key(|layer| layer.index);
+    Ok(layers.into_iter().next())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{layer_element_by_name, layer_elements, LayerElement, LayerElementKind, MappingMode, ReferenceMode};
+    use property::Property;
+    use scene::mesh::Mesh;
+    use test_util::{node, string, tree, TestNode};
+    use tree::Tree;
+
+    /// Returns a mesh with a triangle `[0, 1, 2]` and a quad `[2, 1, 3, 4]`.
+    fn mesh() -> Mesh {
+        Mesh {
+            vertices: vec![[0.0; 3]; 5],
+            polygon_vertices: vec![0, 1, 2, 2, 1, 3, 4],
+            polygon_starts: vec![0, 3, 7],
+            edges: Vec::new(),
+        }
+    }
+
+    fn uv(mapping: MappingMode, reference: ReferenceMode, data: Vec<f64>, indices: Vec<i32>) -> LayerElement {
+        LayerElement {
+            kind: LayerElementKind::Uv,
+            index: 0,
+            name: "map1".to_owned(),
+            mapping,
+            reference,
+            data,
+            indices,
+        }
+    }
+
+    #[test]
+    fn by_polygon_vertex_index_to_direct() {
+        let element = uv(MappingMode::ByPolygonVertex, ReferenceMode::IndexToDirect,
+                         vec![0.0, 0.0, 1.0, 0.0, 1.0, 1.0],
+                         vec![0, 1, 2, 2, 1, 0, 0]);
+        let mesh = mesh();
+        assert_eq!(element.resolve_indices(&mesh).unwrap(), [0, 1, 2, 2, 1, 0, 0]);
+        assert_eq!(element.resolve_vec2(&mesh).unwrap(),
+                   [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [1.0, 1.0], [1.0, 0.0], [0.0, 0.0], [0.0, 0.0]]);
+    }
+
+    #[test]
+    fn by_control_point_direct() {
+        let element = uv(MappingMode::ByControlPoint, ReferenceMode::Direct,
+                         vec![0.0, 0.0, 0.1, 0.1, 0.2, 0.2, 0.3, 0.3, 0.4, 0.4],
+                         Vec::new());
+        assert_eq!(element.resolve_indices(&mesh()).unwrap(), [0, 1, 2, 2, 1, 3, 4]);
+    }
+
+    #[test]
+    fn by_polygon_and_all_same() {
+        let by_polygon = uv(MappingMode::ByPolygon, ReferenceMode::Direct, vec![0.0; 4], Vec::new());
+        assert_eq!(by_polygon.resolve_indices(&mesh()).unwrap(), [0, 0, 0, 1, 1, 1, 1]);
+        let all_same = uv(MappingMode::AllSame, ReferenceMode::Direct, vec![0.0; 2], Vec::new());
+        assert_eq!(all_same.resolve_indices(&mesh()).unwrap(), [0; 7]);
+    }
+
+    #[test]
+    fn index_array_too_short() {
+        let element = uv(MappingMode::ByPolygonVertex, ReferenceMode::IndexToDirect,
+                         vec![0.0, 0.0, 1.0, 0.0],
+                         vec![0, 1, 1]);
+        assert!(element.resolve_indices(&mesh()).is_err());
+    }
+
+    #[test]
+    fn value_array_too_short() {
+        // Seven polygon vertices, but only six values.
+        let element = uv(MappingMode::ByPolygonVertex, ReferenceMode::Direct, vec![0.0; 12], Vec::new());
+        assert!(element.resolve_indices(&mesh()).is_err());
+    }
+
+    #[test]
+    fn index_out_of_range() {
+        let element = uv(MappingMode::ByPolygonVertex, ReferenceMode::IndexToDirect,
+                         vec![0.0, 0.0, 1.0, 0.0],
+                         vec![0, 1, 2, 0, 0, 0, 0]);
+        assert!(element.resolve_indices(&mesh()).is_err());
+        let negative = uv(MappingMode::ByPolygonVertex, ReferenceMode::IndexToDirect,
+                          vec![0.0, 0.0],
+                          vec![0, 0, -1, 0, 0, 0, 0]);
+        assert!(negative.resolve_indices(&mesh()).is_err());
+    }
+
+    #[test]
+    fn wrong_component_count() {
+        let element = uv(MappingMode::AllSame, ReferenceMode::Direct, vec![0.0; 2], Vec::new());
+        assert!(element.resolve_vec3(&mesh()).is_err());
+    }
+
+    /// Returns a tree with a `Geometry` node of the mesh returned by `mesh()`.
+    fn geometry(children: Vec<TestNode<'static>>) -> Tree {
+        let mut geometry = vec![
+            node("Vertices", vec![Property::VecF64(vec![0.0; 15])], vec![]),
+            node("PolygonVertexIndex", vec![Property::VecI32(vec![0, 1, -3, 2, 1, 3, -5])], vec![]),
+        ];
+        geometry.extend(children);
+        tree(7400, &[node("Geometry", vec![Property::I64(1), string("\0\x01Geometry"), string("Mesh")], geometry)])
+    }
+
+    fn element(name: &'static str, index: i32, children: Vec<TestNode<'static>>) -> TestNode<'static> {
+        node(name, vec![Property::I32(index)], children)
+    }
+
+    fn child(name: &'static str, value: Property<'static>) -> TestNode<'static> {
+        node(name, vec![value], vec![])
+    }
+
+    #[test]
+    fn from_node() {
+        let tree = geometry(vec![
+            element("LayerElementNormal", 0, vec![
+                child("MappingInformationType", string("ByVertice")),
+                child("ReferenceInformationType", string("Index")),
+                child("Normals", Property::VecF64(vec![0.0, 0.0, 1.0, 0.0, 1.0, 0.0])),
+                child("NormalsIndex", Property::VecI32(vec![1, 0, 0, 1, 1])),
+            ]),
+            // `ReferenceInformationType` defaults to `Direct`.
+            element("LayerElementColor", 0, vec![
+                child("Name", string("colorSet1")),
+                child("MappingInformationType", string("AllSame")),
+                child("Colors", Property::VecF64(vec![1.0, 0.5, 0.25, 1.0])),
+            ]),
+        ]);
+        let geometry = &tree.children[0];
+        let normal = LayerElement::from_node(geometry.first_child_by_name("LayerElementNormal").unwrap()).unwrap();
+        assert_eq!((normal.kind, normal.mapping, normal.reference), (LayerElementKind::Normal, MappingMode::ByControlPoint, ReferenceMode::IndexToDirect));
+        assert_eq!((normal.len(), normal.value(1)), (2, &[0.0, 1.0, 0.0][..]));
+        assert_eq!(normal.indices, [1, 0, 0, 1, 1]);
+        let mesh = Mesh::from_node(geometry).unwrap();
+        assert_eq!(normal.resolve_vec3(&mesh).unwrap()[..3], [[0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0]]);
+
+        let color = LayerElement::from_node(geometry.first_child_by_name("LayerElementColor").unwrap()).unwrap();
+        assert_eq!((color.name.as_str(), color.mapping, color.reference), ("colorSet1", MappingMode::AllSame, ReferenceMode::Direct));
+        assert_eq!(color.resolve_vec4(&mesh).unwrap(), [[1.0, 0.5, 0.25, 1.0]; 7]);
+        assert!(LayerElement::from_node(geometry.first_child_by_name("Vertices").unwrap()).is_err());
+    }
+
+    #[test]
+    fn invalid_nodes() {
+        let decode = |children| {
+            let tree = geometry(vec![element("LayerElementUV", 0, children)]);
+            LayerElement::from_node(tree.children[0].first_child_by_name("LayerElementUV").unwrap()).map(|_| ())
+        };
+        assert!(decode(vec![child("MappingInformationType", string("ByPolygon"))]).is_ok());
+        assert!(decode(vec![child("MappingInformationType", string("ByFace"))]).is_err());
+        assert!(decode(vec![]).is_err());
+        assert!(decode(vec![
+            child("MappingInformationType", string("ByPolygon")),
+            child("ReferenceInformationType", string("Indirect")),
+        ]).is_err());
+        // Three values are not a multiple of two components.
+        assert!(decode(vec![
+            child("MappingInformationType", string("ByPolygon")),
+            child("UV", Property::VecF64(vec![0.0; 3])),
+        ]).is_err());
+    }
+
+    #[test]
+    fn smoothing_by_edge() {
+        // Edges `(0, 1)`, `(1, 2)`, `(2, 0)`, `(1, 3)`, `(3, 4)` and `(4, 2)`.
+        // The edge `(2, 1)` of the quad is shared with the triangle.
+        let smoothing = |data| geometry(vec![
+            node("Edges", vec![Property::VecI32(vec![0, 1, 2, 4, 5, 6])], vec![]),
+            element("LayerElementSmoothing", 0, vec![
+                child("MappingInformationType", string("ByEdge")),
+                child("ReferenceInformationType", string("Direct")),
+                child("Smoothing", data),
+            ]),
+        ]);
+        let tree = smoothing(Property::VecI32(vec![1, 0, 1, 1, 0, 1]));
+        let geometry = &tree.children[0];
+        let element = LayerElement::from_node(geometry.first_child_by_name("LayerElementSmoothing").unwrap()).unwrap();
+        assert_eq!(element.data, [1.0, 0.0, 1.0, 1.0, 0.0, 1.0]);
+        let mut mesh = Mesh::from_node(geometry).unwrap();
+        assert_eq!(element.resolve_indices(&mesh).unwrap(), [0, 1, 2, 1, 3, 4, 5]);
+        assert_eq!(element.resolve(&mesh).unwrap(), [1.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0]);
+        // Without the edge `(4, 2)`.
+        mesh.edges.pop();
+        assert!(element.resolve_indices(&mesh).is_err());
+
+        // Smoothing values must be integers.
+        let tree = smoothing(Property::VecF64(vec![1.0; 6]));
+        assert!(LayerElement::from_node(tree.children[0].first_child_by_name("LayerElementSmoothing").unwrap()).is_err());
+    }
+
+    #[test]
+    fn uv_sets() {
+        let uv_set = |index, name| element("LayerElementUV", index, vec![
+            child("Name", string(name)),
+            child("MappingInformationType", string("ByPolygonVertex")),
+            child("ReferenceInformationType", string("Direct")),
+            child("UV", Property::VecF64(vec![index as f64; 14])),
+        ]);
+        let tree = geometry(vec![uv_set(1, "lightmap"), uv_set(0, "map1")]);
+        let geometry = &tree.children[0];
+        let uvs = layer_elements(geometry, LayerElementKind::Uv).unwrap();
+        assert_eq!(uvs.iter().map(|uv| (uv.index, uv.name.as_str())).collect::<Vec<_>>(), [(0, "map1"), (1, "lightmap")]);
+        assert!(layer_elements(geometry, LayerElementKind::Normal).unwrap().is_empty());
+
+        let lightmap = layer_element_by_name(geometry, LayerElementKind::Uv, "lightmap").unwrap().unwrap();
+        assert_eq!(lightmap, uvs[1]);
+        assert_eq!(layer_element_by_name(geometry, LayerElementKind::Uv, "map2").unwrap(), None);
+        assert_eq!(layer_element_by_name(geometry, LayerElementKind::Color, "map1").unwrap(), None);
+    }
+}
@@ -0,0 +1,473 @@
+//! Contains `Material`, `Texture` and `LayeredTexture` objects.
+//!
+//! Textures are connected to material properties by `OP` connections, such as
+//! `C: "OP", texture_id, material_id, "DiffuseColor"`.
+//! A `LayeredTexture` object may be connected instead of a `Texture`, and it blends textures
+//! connected to it in order of connection.
+
+use error::Result;
+use scene::{check_class, child_property, child_string, number, vector3};
+use scene::definitions::Definitions;
+use scene::object_graph::{Object, ObjectGraph};
+use scene::properties70::{Properties70, PropertyValue};
+
+
+/// `Material` object.
+///
+/// Properties missing in the file have default values of FBX SDK.
+#[derive(Debug)]
+pub struct Material<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Material name.
+    pub name: &'a str,
+    /// Shading model (`ShadingModel`) in lowercase, such as `"lambert"` and `"phong"`.
+    pub shading_model: String,
+    /// `AmbientColor`.
+    pub ambient_color: [f64; 3],
+    /// `AmbientFactor`.
+    pub ambient_factor: f64,
+    /// `DiffuseColor`.
+    pub diffuse_color: [f64; 3],
+    /// `DiffuseFactor`.
+    pub diffuse_factor: f64,
+    /// `SpecularColor`.
+    pub specular_color: [f64; 3],
+    /// `SpecularFactor`.
+    pub specular_factor: f64,
+    /// `EmissiveColor`.
+    pub emissive_color: [f64; 3],
+    /// `EmissiveFactor`.
+    pub emissive_factor: f64,
+    /// `Shininess` (or `ShininessExponent`).
+    pub shininess: f64,
+    /// Opacity.
+    ///
+    /// `Opacity` property if exists, otherwise computed from `TransparentColor` and
+    /// `TransparencyFactor`.
+    pub opacity: f64,
+    /// All properties.
+    pub properties: Properties70<'a>,
+}
+
+impl<'a> Material<'a> {
+    /// Decodes the `Material` object.
+    ///
+    /// Property templates are not applied (see [`definitions`](../definitions/index.html)).
+    pub fn from_object(object: &Object<'a>) -> Result<Self> {
+        check_class(object, "Material")?;
+        Material::from_properties(object, Properties70::from_parent(object.node)?)
+    }
+
+    /// Decodes the `Material` object, with default values from the property template of the
+    /// shading model in `definitions`.
+    ///
+    /// `properties` has template records not overridden by the object, too.
+    pub fn from_object_with(object: &Object<'a>, definitions: &Definitions<'a>) -> Result<Self> {
+        check_class(object, "Material")?;
+        Material::from_properties(object, definitions.properties_of(object)?.to_properties())
+    }
+
+    fn from_properties(object: &Object<'a>, properties: Properties70<'a>) -> Result<Self> {
+        let node = object.node;
+        let opacity = match properties.value("Opacity").and_then(PropertyValue::as_f64) {
+            Some(opacity) => opacity,
+            None => {
+                let color = vector3(&properties, "TransparentColor", [0.0; 3]);
+                let factor = number(&properties, "TransparencyFactor", 0.0);
+                1.0 - factor * (color[0] + color[1] + color[2]) / 3.0
+            },
+        };
+        Ok(Material {
+            id: object.id,
+            name: object.name,
+            shading_model: child_string(node, "ShadingModel")?.unwrap_or_else(|| "lambert".to_owned()).to_lowercase(),
+            ambient_color: vector3(&properties, "AmbientColor", [0.2; 3]),
+            ambient_factor: number(&properties, "AmbientFactor", 1.0),
+            diffuse_color: vector3(&properties, "DiffuseColor", [0.8; 3]),
+            diffuse_factor: number(&properties, "DiffuseFactor", 1.0),
+            specular_color: vector3(&properties, "SpecularColor", [0.2; 3]),
+            specular_factor: number(&properties, "SpecularFactor", 1.0),
+            emissive_color: vector3(&properties, "EmissiveColor", [0.0; 3]),
+            emissive_factor: number(&properties, "EmissiveFactor", 1.0),
+            shininess: properties.value("Shininess").or_else(|| properties.value("ShininessExponent"))
+                .and_then(PropertyValue::as_f64)
+                .unwrap_or(20.0),
+            opacity,
+            properties,
+        })
+    }
+
+    /// Returns textures connected to the material properties.
+    pub fn textures(&self, graph: &ObjectGraph<'a>) -> Result<Vec<MaterialTexture<'a>>> {
+        let mut textures = Vec::new();
+        for connection in graph.children_of(self.id) {
+            let property = match connection.parent_property {
+                Some(property) => property,
+                None => continue,
+            };
+            let object = match graph.object(connection.child) {
+                Some(object) => object,
+                None => continue,
+            };
+            let texture = match object.class {
+                "Texture" => TextureSource::Texture(Texture::from_object(object)?),
+                "LayeredTexture" => TextureSource::Layered(LayeredTexture::from_object(object, graph)?),
+                _ => continue,
+            };
+            textures.push(MaterialTexture { property, texture });
+        }
+        Ok(textures)
+    }
+}
+
+
+/// A texture connected to a material property.
+#[derive(Debug)]
+pub struct MaterialTexture<'a> {
+    /// Name of the material property, such as `DiffuseColor` and `NormalMap`.
+    pub property: &'a str,
+    /// Texture.
+    pub texture: TextureSource<'a>,
+}
+
+/// A texture or a stack of textures.
+#[derive(Debug)]
+pub enum TextureSource<'a> {
+    /// Single texture.
+    Texture(Texture<'a>),
+    /// Layered texture.
+    Layered(LayeredTexture<'a>),
+}
+
+
+/// Texture wrap mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WrapMode {
+    /// Repeat (`0`).
+    Repeat,
+    /// Clamp (`1`).
+    Clamp,
+}
+
+
+/// `Texture` object.
+#[derive(Debug)]
+pub struct Texture<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Texture name.
+    pub name: &'a str,
+    /// Absolute file name (`FileName`).
+    pub file_name: Option<String>,
+    /// File name relative to the FBX file (`RelativeFilename`).
+    pub relative_file_name: Option<String>,
+    /// UV set name (`UVSet`).
+    pub uv_set: &'a str,
+    /// Wrap mode of U coordinate (`WrapModeU`).
+    pub wrap_u: WrapMode,
+    /// Wrap mode of V coordinate (`WrapModeV`).
+    pub wrap_v: WrapMode,
+    /// UV translation (`Translation`).
+    pub translation: [f64; 3],
+    /// UV rotation in degrees (`Rotation`).
+    pub rotation: [f64; 3],
+    /// UV scaling (`Scaling`).
+    pub scaling: [f64; 3],
+    /// All properties.
+    pub properties: Properties70<'a>,
+}
+
+impl<'a> Texture<'a> {
+    /// Decodes the `Texture` object.
+    pub fn from_object(object: &Object<'a>) -> Result<Self> {
+        check_class(object, "Texture")?;
+        let node = object.node;
+        let properties = Properties70::from_parent(node)?;
+        let wrap_mode = |name| match properties.value(name).and_then(PropertyValue::as_i64) {
+            Some(1) => WrapMode::Clamp,
+            _ => WrapMode::Repeat,
+        };
+        Ok(Texture {
+            id: object.id,
+            name: object.name,
+            file_name: child_string(node, "FileName")?,
+            relative_file_name: child_string(node, "RelativeFilename")?,
+            uv_set: properties.value("UVSet").and_then(PropertyValue::as_str).unwrap_or("default"),
+            wrap_u: wrap_mode("WrapModeU"),
+            wrap_v: wrap_mode("WrapModeV"),
+            translation: vector3(&properties, "Translation", [0.0; 3]),
+            rotation: vector3(&properties, "Rotation", [0.0; 3]),
+            scaling: vector3(&properties, "Scaling", [1.0; 3]),
+            properties,
+        })
+    }
+}
+
+
+/// Blend mode of a layered texture.
+///
+/// Values correspond to `FbxLayeredTexture::EBlendMode` in FBX SDK.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Translucent (`0`).
+    Translucent,
+    /// Additive (`1`).
+    Additive,
+    /// Modulate (`2`).
+    Modulate,
+    /// Modulate2 (`3`).
+    Modulate2,
+    /// Over (`4`).
+    Over,
+    /// Normal (`5`).
+    Normal,
+    /// Dissolve (`6`).
+    Dissolve,
+    /// Darken (`7`).
+    Darken,
+    /// Color burn (`8`).
+    ColorBurn,
+    /// Linear burn (`9`).
+    LinearBurn,
+    /// Darker color (`10`).
+    DarkerColor,
+    /// Lighten (`11`).
+    Lighten,
+    /// Screen (`12`).
+    Screen,
+    /// Color dodge (`13`).
+    ColorDodge,
+    /// Linear dodge (`14`).
+    LinearDodge,
+    /// Lighter color (`15`).
+    LighterColor,
+    /// Soft light (`16`).
+    SoftLight,
+    /// Hard light (`17`).
+    HardLight,
+    /// Vivid light (`18`).
+    VividLight,
+    /// Linear light (`19`).
+    LinearLight,
+    /// Pin light (`20`).
+    PinLight,
+    /// Hard mix (`21`).
+    HardMix,
+    /// Difference (`22`).
+    Difference,
+    /// Exclusion (`23`).
+    Exclusion,
+    /// Subtract (`24`).
+    Subtract,
+    /// Divide (`25`).
+    Divide,
+    /// Hue (`26`).
+    Hue,
+    /// Saturation (`27`).
+    Saturation,
+    /// Color (`28`).
+    Color,
+    /// Luminosity (`29`).
+    Luminosity,
+    /// Overlay (`30`).
+    Overlay,
+    /// Unknown value.
+    Other(i32),
+}
+
+impl BlendMode {
+    /// Converts the FBX enum value to `BlendMode`.
+    pub fn from_i32(value: i32) -> Self {
+        use self::BlendMode::*;
+        const MODES: [BlendMode; 31] = [
+            Translucent, Additive, Modulate, Modulate2, Over, Normal, Dissolve, Darken, ColorBurn,
+            LinearBurn, DarkerColor, Lighten, Screen, ColorDodge, LinearDodge, LighterColor,
+            SoftLight, HardLight, VividLight, LinearLight, PinLight, HardMix, Difference,
+            Exclusion, Subtract, Divide, Hue, Saturation, Color, Luminosity, Overlay,
+        ];
+        if value >= 0 && (value as usize) < MODES.len() {
+            MODES[value as usize]
+        } else {
+            Other(value)
+        }
+    }
+}
+
+
+/// A layer of a layered texture.
+#[derive(Debug)]
+pub struct TextureLayer<'a> {
+    /// Texture.
+    pub texture: Texture<'a>,
+    /// Blend mode.
+    pub blend_mode: BlendMode,
+    /// Alpha.
+    pub alpha: f64,
+}
+
+
+/// `LayeredTexture` object.
+#[derive(Debug)]
+pub struct LayeredTexture<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Layered texture name.
+    pub name: &'a str,
+    /// Layers in order of connection.
+    pub layers: Vec<TextureLayer<'a>>,
+    /// All properties.
+    pub properties: Properties70<'a>,
+}
+
+impl<'a> LayeredTexture<'a> {
+    /// Decodes the `LayeredTexture` object, and textures connected to it.
+    ///
+    /// `BlendModes` and `Alphas` values are assigned to textures in order of connection.
+    /// Missing blend modes are `BlendMode::Normal` and missing alphas are `1.0`.
+    pub fn from_object(object: &Object<'a>, graph: &ObjectGraph<'a>) -> Result<Self> {
+        check_class(object, "LayeredTexture")?;
+        let node = object.node;
+        let blend_modes: Vec<i64> = match child_property(node, "BlendModes") {
+            Some(prop) => match prop.as_i64() {
+                Some(v) => vec![v],
+                None => prop.into_vec_i64().unwrap_or_default(),
+            },
+            None => Vec::new(),
+        };
+        let alphas: Vec<f64> = match child_property(node, "Alphas") {
+            Some(prop) => match prop.as_f64() {
+                Some(v) => vec![v],
+                None => prop.into_vec_f64().unwrap_or_default(),
+            },
+            None => Vec::new(),
+        };
+        let mut layers = Vec::new();
+        for texture in graph.child_objects(object.id).filter(|child| child.class == "Texture") {
+            let i = layers.len();
+            layers.push(TextureLayer {
+                texture: Texture::from_object(texture)?,
+                blend_mode: blend_modes.get(i).map_or(BlendMode::Normal, |&v| BlendMode::from_i32(v as i32)),
+                alpha: alphas.get(i).cloned().unwrap_or(1.0),
+            });
+        }
+        Ok(LayeredTexture {
+            id: object.id,
+            name: object.name,
+            layers,
+            properties: Properties70::from_parent(node)?,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use property::Property;
+    use scene::object_graph::ObjectGraph;
+    use test_util::{connection, node, object, op, p, tree, TestNode};
+    use super::*;
+
+
+    fn texture(id: i64, name_class: &'static str, file_name: &'static str, properties: Vec<TestNode<'static>>) -> TestNode<'static> {
+        object("Texture", id, name_class, "", vec![
+            node("Properties70", vec![], properties),
+            node("FileName", vec![Property::String(Ok(file_name))], vec![]),
+        ])
+    }
+
+    fn nodes() -> Vec<TestNode<'static>> {
+        vec![
+            node("Objects", vec![], vec![
+                object("Material", 10, "Red\0\x01Material", "", vec![
+                    node("ShadingModel", vec![Property::String(Ok("Phong"))], vec![]),
+                    node("Properties70", vec![], vec![
+                        p("DiffuseColor", "Color", "", vec![Property::F64(1.0), Property::F64(0.0), Property::F64(0.0)]),
+                        p("ShininessExponent", "Number", "", vec![Property::F64(8.0)]),
+                        p("TransparentColor", "Color", "", vec![Property::F64(1.0), Property::F64(1.0), Property::F64(1.0)]),
+                        p("TransparencyFactor", "Number", "", vec![Property::F64(0.25)]),
+                    ]),
+                ]),
+                texture(20, "Wood\0\x01Texture", "C:/wood.png", vec![
+                    p("UVSet", "KString", "", vec![Property::String(Ok("map2"))]),
+                    p("WrapModeU", "enum", "", vec![Property::I32(1)]),
+                    p("Scaling", "Vector", "", vec![Property::F64(2.0), Property::F64(2.0), Property::F64(1.0)]),
+                ]),
+                texture(21, "Base\0\x01Texture", "C:/base.png", vec![]),
+                texture(22, "Dirt\0\x01Texture", "C:/dirt.png", vec![]),
+                texture(23, "Scratch\0\x01Texture", "C:/scratch.png", vec![]),
+                object("LayeredTexture", 30, "Stack\0\x01LayeredTexture", "", vec![
+                    node("BlendModes", vec![Property::VecI32(vec![0, 12])], vec![]),
+                    node("Alphas", vec![Property::VecF64(vec![1.0, 0.5])], vec![]),
+                ]),
+                object("Model", 40, "Cube\0\x01Model", "Mesh", vec![]),
+            ]),
+            node("Connections", vec![], vec![
+                connection(10, 40),
+                op(20, 10, "DiffuseColor"),
+                op(30, 10, "NormalMap"),
+                // Not connected to a property.
+                connection(22, 10),
+                connection(21, 30),
+                connection(22, 30),
+                connection(23, 30),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn material_properties() {
+        let tree = tree(7400, &nodes());
+        let graph = ObjectGraph::from_tree(&tree);
+        let material = Material::from_object(graph.object(10).unwrap()).unwrap();
+        assert_eq!(material.name, "Red");
+        assert_eq!(material.shading_model, "phong");
+        assert_eq!(material.diffuse_color, [1.0, 0.0, 0.0]);
+        assert_eq!(material.shininess, 8.0);
+        assert_eq!(material.opacity, 0.75);
+        // Defaults of FBX SDK.
+        assert_eq!(material.ambient_color, [0.2; 3]);
+        assert_eq!(material.specular_factor, 1.0);
+        assert!(Material::from_object(graph.object(20).unwrap()).is_err());
+    }
+
+    #[test]
+    fn textures_by_property() {
+        let tree = tree(7400, &nodes());
+        let graph = ObjectGraph::from_tree(&tree);
+        let material = Material::from_object(graph.object(10).unwrap()).unwrap();
+        let textures = material.textures(&graph).unwrap();
+        assert_eq!(textures.iter().map(|t| t.property).collect::<Vec<_>>(), ["DiffuseColor", "NormalMap"]);
+
+        let diffuse = match textures[0].texture {
+            TextureSource::Texture(ref texture) => texture,
+            ref source => panic!("Expected single texture, but got {:?}", source),
+        };
+        assert_eq!((diffuse.id, diffuse.name), (20, "Wood"));
+        assert_eq!(diffuse.file_name.as_ref().map(|s| &s[..]), Some("C:/wood.png"));
+        assert_eq!(diffuse.uv_set, "map2");
+        assert_eq!((diffuse.wrap_u, diffuse.wrap_v), (WrapMode::Clamp, WrapMode::Repeat));
+        assert_eq!(diffuse.scaling, [2.0, 2.0, 1.0]);
+
+        let layered = match textures[1].texture {
+            TextureSource::Layered(ref layered) => layered,
+            ref source => panic!("Expected layered texture, but got {:?}", source),
+        };
+        assert_eq!(layered.name, "Stack");
+        let layers = layered.layers.iter().map(|layer| (layer.texture.id, layer.blend_mode, layer.alpha)).collect::<Vec<_>>();
+        // The third layer has no blend mode nor alpha.
+        assert_eq!(layers, [
+            (21, BlendMode::Translucent, 1.0),
+            (22, BlendMode::Screen, 0.5),
+            (23, BlendMode::Normal, 1.0),
+        ]);
+    }
+
+    #[test]
+    fn blend_mode_values() {
+        assert_eq!(BlendMode::from_i32(0), BlendMode::Translucent);
+        assert_eq!(BlendMode::from_i32(30), BlendMode::Overlay);
+        assert_eq!(BlendMode::from_i32(31), BlendMode::Other(31));
+        assert_eq!(BlendMode::from_i32(-1), BlendMode::Other(-1));
+    }
+}
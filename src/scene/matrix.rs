@@ -0,0 +1,134 @@
+//! Contains 4x4 matrix helpers.
+//!
+//! Matrices are `[f64; 16]` in column-major order (the same as FBX data), and transform column
+//! vectors, i.e. `mul(a, b)` applies `b` first.
+
+/// 4x4 matrix in column-major order.
+pub type Matrix = [f64; 16];
+
+/// Identity matrix.
+pub const IDENTITY: Matrix = [
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 1.0, 0.0,
+    0.0, 0.0, 0.0, 1.0,
+];
+
+
+/// Returns `a * b`.
+pub fn mul(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            m[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    m
+}
+
+/// Returns the product of the matrices, applying the last one first.
+pub fn mul_all(matrices: &[Matrix]) -> Matrix {
+    matrices.iter().fold(IDENTITY, |acc, m| mul(&acc, m))
+}
+
+/// Returns a translation matrix.
+pub fn translation(v: [f64; 3]) -> Matrix {
+    let mut m = IDENTITY;
+    m[12] = v[0];
+    m[13] = v[1];
+    m[14] = v[2];
+    m
+}
+
+/// Returns a scaling matrix.
+pub fn scaling(v: [f64; 3]) -> Matrix {
+    let mut m = IDENTITY;
+    m[0] = v[0];
+    m[5] = v[1];
+    m[10] = v[2];
+    m
+}
+
+/// Returns a rotation matrix around the axis (0: X, 1: Y, 2: Z) by the angle in degrees.
+pub fn axis_rotation(axis: usize, degrees: f64) -> Matrix {
+    let (s, c) = degrees.to_radians().sin_cos();
+    let (i, j) = ((axis + 1) % 3, (axis + 2) % 3);
+    let mut m = IDENTITY;
+    m[i * 4 + i] = c;
+    m[i * 4 + j] = s;
+    m[j * 4 + i] = -s;
+    m[j * 4 + j] = c;
+    m
+}
+
+/// Returns a rotation matrix from Euler angles in degrees.
+///
+/// `order` is the axes in order of application, e.g. `[0, 1, 2]` rotates around X first.
+pub fn euler_rotation(degrees: [f64; 3], order: [usize; 3]) -> Matrix {
+    let r = order.map(|axis| axis_rotation(axis, degrees[axis]));
+    mul_all(&[r[2], r[1], r[0]])
+}
+
+/// Returns the translation part.
+pub fn get_translation(m: &Matrix) -> [f64; 3] {
+    [m[12], m[13], m[14]]
+}
+
+/// Returns the matrix with the translation part replaced.
+pub fn with_translation(m: &Matrix, t: [f64; 3]) -> Matrix {
+    let mut m = *m;
+    m[12] = t[0];
+    m[13] = t[1];
+    m[14] = t[2];
+    m
+}
+
+/// Transforms the point by the matrix.
+pub fn transform_point(m: &Matrix, p: [f64; 3]) -> [f64; 3] {
+    let mut r = [0.0; 3];
+    for (row, r) in r.iter_mut().enumerate() {
+        *r = m[row] * p[0] + m[4 + row] * p[1] + m[8 + row] * p[2] + m[12 + row];
+    }
+    r
+}
+
+/// Decomposes the affine matrix into translation, rotation matrix and scaling.
+///
+/// Shear is not representable and is folded into the rotation.
+/// A negative determinant is represented as a negative X scaling.
+pub fn decompose(m: &Matrix) -> ([f64; 3], Matrix, [f64; 3]) {
+    let column = |c: usize| [m[c * 4], m[c * 4 + 1], m[c * 4 + 2]];
+    let length = |v: [f64; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    let (x, y, z) = (column(0), column(1), column(2));
+    let det = x[0] * (y[1] * z[2] - y[2] * z[1]) - y[0] * (x[1] * z[2] - x[2] * z[1]) + z[0] * (x[1] * y[2] - x[2] * y[1]);
+    let sign = if det < 0.0 { -1.0 } else { 1.0 };
+    let scale = [length(x) * sign, length(y), length(z)];
+    let mut rotation = IDENTITY;
+    for (c, v) in [x, y, z].iter().enumerate() {
+        for row in 0..3 {
+            rotation[c * 4 + row] = if scale[c] != 0.0 { v[row] / scale[c] } else if c == row { 1.0 } else { 0.0 };
+        }
+    }
+    (get_translation(m), rotation, scale)
+}
+
+/// Converts the rotation matrix into a quaternion `[x, y, z, w]`.
+pub fn to_quaternion(r: &Matrix) -> [f64; 4] {
+    let a = |row: usize, col: usize| r[col * 4 + row];
+    let trace = a(0, 0) + a(1, 1) + a(2, 2);
+    let q = if trace > 0.0 {
+        let s = (trace + 1.0).sqrt() * 2.0;
+        [(a(2, 1) - a(1, 2)) / s, (a(0, 2) - a(2, 0)) / s, (a(1, 0) - a(0, 1)) / s, s / 4.0]
+    } else if a(0, 0) > a(1, 1) && a(0, 0) > a(2, 2) {
+        let s = (1.0 + a(0, 0) - a(1, 1) - a(2, 2)).sqrt() * 2.0;
+        [s / 4.0, (a(0, 1) + a(1, 0)) / s, (a(0, 2) + a(2, 0)) / s, (a(2, 1) - a(1, 2)) / s]
+    } else if a(1, 1) > a(2, 2) {
+        let s = (1.0 + a(1, 1) - a(0, 0) - a(2, 2)).sqrt() * 2.0;
+        [(a(0, 1) + a(1, 0)) / s, s / 4.0, (a(1, 2) + a(2, 1)) / s, (a(0, 2) - a(2, 0)) / s]
+    } else {
+        let s = (1.0 + a(2, 2) - a(0, 0) - a(1, 1)).sqrt() * 2.0;
+        [(a(0, 2) + a(2, 0)) / s, (a(1, 2) + a(2, 1)) / s, s / 4.0, (a(1, 0) - a(0, 1)) / s]
+    };
+    let len = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+    [q[0] / len, q[1] / len, q[2] / len, q[3] / len]
+}
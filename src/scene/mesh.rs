@@ -0,0 +1,367 @@
+//! Contains mesh data of `Geometry` objects.
+//!
+//! `PolygonVertexIndex` is an array of control point (vertex) indices, and the last index of
+//! each polygon is stored as bitwise negation (`-index - 1`).
+//! For example, `[0, 1, -3, 2, 3, 4, -6]` denotes a triangle `[0, 1, 2]` and a quad
+//! `[2, 3, 4, 5]`.
+//!
+//! "Polygon vertex" is an occurrence of a control point in a polygon, and it is identified by
+//! an index into `PolygonVertexIndex` array.
+//! Most of layer elements (normals, UVs and so on) are stored per polygon vertex.
+
+use std::ops::Range;
+use error::{Error, Result};
+use scene::{check_subclass, child_vec_f64, child_vec_i32, to_vec3};
+use scene::object_graph::Object;
+use tree::Node;
+
+
+/// Mesh geometry, i.e. `Geometry` object with `Mesh` subclass.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    /// Control point positions (`Vertices`).
+    pub vertices: Vec<[f64; 3]>,
+    /// Control point indices of all polygon vertices, decoded from `PolygonVertexIndex`.
+    pub polygon_vertices: Vec<u32>,
+    /// Start positions of polygons in `polygon_vertices`, followed by the length of
+    /// `polygon_vertices`.
+    ///
+    /// Polygon `i` consists of `polygon_vertices[polygon_starts[i]..polygon_starts[i + 1]]`.
+    pub polygon_starts: Vec<usize>,
+    /// Edges (`Edges`), each of which is the polygon vertex index of the edge start.
+    ///
+    /// The edge ends at the next polygon vertex in the same polygon.
+    pub edges: Vec<u32>,
+}
+
+impl Mesh {
+    /// Decodes the `Geometry` node.
+    pub fn from_node(node: &Node) -> Result<Self> {
+        let vertices = to_vec3(&child_vec_f64(node, "Vertices")?.unwrap_or_default(), node, "Vertices")?;
+        let raw_indices = child_vec_i32(node, "PolygonVertexIndex")?.unwrap_or_default();
+        let mut polygon_vertices = Vec::with_capacity(raw_indices.len());
+        let mut polygon_starts = vec![0];
+        for &raw in &raw_indices {
+            let (index, is_last) = if raw < 0 { (!raw, true) } else { (raw, false) };
+            if index as usize >= vertices.len() {
+                return Err(Error::DataError(format!("Polygon vertex index {} is out of range (number of vertices is {})", index, vertices.len())));
+            }
+            polygon_vertices.push(index as u32);
+            if is_last {
+                polygon_starts.push(polygon_vertices.len());
+            }
+        }
+        if *polygon_starts.last().unwrap() != polygon_vertices.len() {
+            warn!("The last polygon is not terminated by a negative index");
+            polygon_starts.push(polygon_vertices.len());
+        }
+        let edges = child_vec_i32(node, "Edges")?.unwrap_or_default();
+        if let Some(&edge) = edges.iter().find(|&&edge| edge < 0 || edge as usize >= polygon_vertices.len()) {
+            return Err(Error::DataError(format!("Edge {} is out of range (number of polygon vertices is {})", edge, polygon_vertices.len())));
+        }
+        Ok(Mesh {
+            vertices,
+            polygon_vertices,
+            polygon_starts,
+            edges: edges.into_iter().map(|edge| edge as u32).collect(),
+        })
+    }
+
+    /// Decodes the `Geometry` object.
+    pub fn from_object(object: &Object) -> Result<Self> {
+        check_subclass(object, "Geometry", "Mesh")?;
+        Mesh::from_node(object.node)
+    }
+
+    /// Returns the number of polygons.
+    pub fn num_polygons(&self) -> usize {
+        self.polygon_starts.len() - 1
+    }
+
+    /// Returns the range of polygon vertex indices of the polygon.
+    pub fn polygon_range(&self, polygon: usize) -> Range<usize> {
+        self.polygon_starts[polygon]..self.polygon_starts[polygon + 1]
+    }
+
+    /// Returns control point indices of the polygon.
+    pub fn polygon(&self, polygon: usize) -> &[u32] {
+        &self.polygon_vertices[self.polygon_range(polygon)]
+    }
+
+    /// Returns an iterator of polygons, each of which is a slice of control point indices.
+    pub fn polygons(&self) -> Polygons<'_> {
+        Polygons {
+            mesh: self,
+            next: 0,
+        }
+    }
+
+    /// Returns the polygon which the polygon vertex belongs to.
+    pub fn polygon_of_vertex(&self, polygon_vertex: usize) -> usize {
+        match self.polygon_starts.binary_search(&polygon_vertex) {
+            Ok(polygon) => polygon,
+            Err(next) => next - 1,
+        }
+    }
+
+    /// Returns control point indices of both ends of the edge.
+    pub fn edge_vertices(&self, edge: usize) -> [u32; 2] {
+        let start = self.edges[edge] as usize;
+        let range = self.polygon_range(self.polygon_of_vertex(start));
+        let end = if start + 1 == range.end { range.start } else { start + 1 };
+        [self.polygon_vertices[start], self.polygon_vertices[end]]
+    }
+
+    /// Triangulates all polygons.
+    ///
+    /// Degenerate polygons with less than three vertices are skipped.
+    pub fn triangulate(&self, method: TriangulationMethod) -> Vec<Triangle> {
+        let mut triangles = Vec::with_capacity(self.polygon_vertices.len());
+        for polygon in 0..self.num_polygons() {
+            let range = self.polygon_range(polygon);
+            let start = range.start;
+            let positions: Vec<[f64; 3]> = self.polygon(polygon).iter().map(|&v| self.vertices[v as usize]).collect();
+            for local in triangulate_polygon(&positions, method) {
+                triangles.push(Triangle {
+                    polygon,
+                    polygon_vertices: [start + local[0], start + local[1], start + local[2]],
+                });
+            }
+        }
+        triangles
+    }
+}
+
+
+/// An iterator of polygons of a mesh.
+#[derive(Debug, Clone)]
+pub struct Polygons<'a> {
+    mesh: &'a Mesh,
+    next: usize,
+}
+
+impl<'a> Iterator for Polygons<'a> {
+    type Item = &'a [u32];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.mesh.num_polygons() {
+            return None;
+        }
+        let polygon = self.mesh.polygon(self.next);
+        self.next += 1;
+        Some(polygon)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let rest = self.mesh.num_polygons() - self.next;
+        (rest, Some(rest))
+    }
+}
+
+
+/// Triangulation method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TriangulationMethod {
+    /// Fan triangulation from the first vertex.
+    ///
+    /// Fast, but correct only for convex polygons.
+    Fan,
+    /// Ear clipping.
+    ///
+    /// Handles concave (but not self-intersecting) polygons.
+    /// Falls back to fan triangulation if the polygon is degenerate.
+    EarClipping,
+}
+
+
+/// A triangle from a polygon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Triangle {
+    /// Index of the source polygon.
+    pub polygon: usize,
+    /// Polygon vertex indices (indices into `Mesh::polygon_vertices`) of the triangle.
+    pub polygon_vertices: [usize; 3],
+}
+
+
+/// Triangulates the polygon.
+///
+/// Returns triangles as indices into `positions`, with the same winding order as the polygon.
+pub fn triangulate_polygon(positions: &[[f64; 3]], method: TriangulationMethod) -> Vec<[usize; 3]> {
+    let n = positions.len();
+    if n < 3 {
+        return Vec::new();
+    }
+    if n == 3 || method == TriangulationMethod::Fan {
+        return fan(n);
+    }
+    ear_clipping(positions).unwrap_or_else(|| fan(n))
+}
+
+fn fan(n: usize) -> Vec<[usize; 3]> {
+    (1..n - 1).map(|i| [0, i, i + 1]).collect()
+}
+
+fn ear_clipping(positions: &[[f64; 3]]) -> Option<Vec<[usize; 3]>> {
+    // Project the polygon onto the plane perpendicular to the dominant axis of the normal
+    // (computed by Newell's method), keeping the orientation counter-clockwise.
+    let mut normal = [0.0; 3];
+    for (i, p) in positions.iter().enumerate() {
+        let q = positions[(i + 1) % positions.len()];
+        normal[0] += (p[1] - q[1]) * (p[2] + q[2]);
+        normal[1] += (p[2] - q[2]) * (p[0] + q[0]);
+        normal[2] += (p[0] - q[0]) * (p[1] + q[1]);
+    }
+    let abs = [normal[0].abs(), normal[1].abs(), normal[2].abs()];
+    let (u, v, sign) = if abs[0] >= abs[1] && abs[0] >= abs[2] {
+        (1, 2, normal[0])
+    } else if abs[1] >= abs[2] {
+        (2, 0, normal[1])
+    } else {
+        (0, 1, normal[2])
+    };
+    if sign == 0.0 || !sign.is_finite() {
+        return None;
+    }
+    let points: Vec<[f64; 2]> = positions.iter()
+        .map(|p| if sign > 0.0 { [p[u], p[v]] } else { [p[v], p[u]] })
+        .collect();
+
+    let cross = |a: [f64; 2], b: [f64; 2], c: [f64; 2]| (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(points.len() - 2);
+    while remaining.len() > 3 {
+        let len = remaining.len();
+        let ear = (0..len).find(|&i| {
+            let (prev, cur, next) = (remaining[(i + len - 1) % len], remaining[i], remaining[(i + 1) % len]);
+            let (a, b, c) = (points[prev], points[cur], points[next]);
+            if cross(a, b, c) <= 0.0 {
+                // Reflex or degenerate corner.
+                return false;
+            }
+            // No other vertices should be inside the triangle.
+            remaining.iter()
+                .filter(|&&other| other != prev && other != cur && other != next)
+                .all(|&other| {
+                    let p = points[other];
+                    cross(a, b, p) < 0.0 || cross(b, c, p) < 0.0 || cross(c, a, p) < 0.0
+                })
+        })?;
+        triangles.push([remaining[(ear + len - 1) % len], remaining[ear], remaining[(ear + 1) % len]]);
+        remaining.remove(ear);
+    }
+    triangles.push([remaining[0], remaining[1], remaining[2]]);
+    Some(triangles)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Mesh, TriangulationMethod, triangulate_polygon};
+    use error::Result;
+    use property::Property;
+    use test_util::{node, string, tree};
+
+    /// Returns twice the signed area of the triangle on the XY plane.
+    fn signed_area2(positions: &[[f64; 3]], t: [usize; 3]) -> f64 {
+        let (a, b, c) = (positions[t[0]], positions[t[1]], positions[t[2]]);
+        (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])
+    }
+
+    /// Counter-clockwise quad whose last vertex is reflex.
+    const CONCAVE_QUAD: [[f64; 3]; 4] = [[0.0, 0.0, 0.0], [2.0, 1.0, 0.0], [0.0, 2.0, 0.0], [0.5, 1.0, 0.0]];
+
+    #[test]
+    fn ear_clipping_concave_quad() {
+        let triangles = triangulate_polygon(&CONCAVE_QUAD, TriangulationMethod::EarClipping);
+        assert_eq!(triangles.len(), 2);
+        // All triangles keep the winding, and cover the polygon (area 1.5) without overlap.
+        assert!(triangles.iter().all(|&t| signed_area2(&CONCAVE_QUAD, t) > 0.0));
+        let area: f64 = triangles.iter().map(|&t| signed_area2(&CONCAVE_QUAD, t) / 2.0).sum();
+        assert!((area - 1.5).abs() < 1e-12);
+    }
+
+    #[test]
+    fn fan_concave_quad() {
+        // Fan triangulation is wrong for concave polygons: the second triangle is flipped.
+        let triangles = triangulate_polygon(&CONCAVE_QUAD, TriangulationMethod::Fan);
+        assert_eq!(triangles, [[0, 1, 2], [0, 2, 3]]);
+        assert!(signed_area2(&CONCAVE_QUAD, triangles[1]) < 0.0);
+    }
+
+    #[test]
+    fn ear_clipping_clockwise_concave_quad() {
+        let mut positions = CONCAVE_QUAD;
+        positions.reverse();
+        let triangles = triangulate_polygon(&positions, TriangulationMethod::EarClipping);
+        assert_eq!(triangles.len(), 2);
+        assert!(triangles.iter().all(|&t| signed_area2(&positions, t) < 0.0));
+    }
+
+    #[test]
+    fn degenerate_polygon_falls_back_to_fan() {
+        let collinear = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [2.0, 0.0, 0.0], [3.0, 0.0, 0.0]];
+        assert_eq!(triangulate_polygon(&collinear, TriangulationMethod::EarClipping), [[0, 1, 2], [0, 2, 3]]);
+        assert!(triangulate_polygon(&collinear[..2], TriangulationMethod::EarClipping).is_empty());
+    }
+
+    #[test]
+    fn mesh_triangulate_skips_degenerate_polygons() {
+        let mut vertices = CONCAVE_QUAD.to_vec();
+        vertices.push([5.0, 5.0, 0.0]);
+        let mesh = Mesh {
+            vertices,
+            // A line `[4, 0]` followed by the concave quad.
+            polygon_vertices: vec![4, 0, 0, 1, 2, 3],
+            polygon_starts: vec![0, 2, 6],
+            edges: Vec::new(),
+        };
+        let triangles = mesh.triangulate(TriangulationMethod::EarClipping);
+        assert_eq!(triangles.len(), 2);
+        for triangle in &triangles {
+            assert_eq!(triangle.polygon, 1);
+            assert!(triangle.polygon_vertices.iter().all(|&v| (2..6).contains(&v)));
+        }
+    }
+
+    fn decode(num_vertices: usize, indices: Vec<i32>, edges: Vec<i32>) -> Result<Mesh> {
+        let tree = tree(7400, &[node("Geometry", vec![Property::I64(1), string("\0\x01Geometry"), string("Mesh")], vec![
+            node("Vertices", vec![Property::VecF64((0..num_vertices * 3).map(|v| v as f64).collect())], vec![]),
+            node("PolygonVertexIndex", vec![Property::VecI32(indices)], vec![]),
+            node("Edges", vec![Property::VecI32(edges)], vec![]),
+        ])]);
+        Mesh::from_node(&tree.children[0])
+    }
+
+    #[test]
+    fn from_node() {
+        // A triangle `[0, 1, 2]` and a quad `[2, 1, 3, 4]`.
+        let mesh = decode(5, vec![0, 1, -3, 2, 1, 3, -5], vec![0, 2, 6]).unwrap();
+        assert_eq!(mesh.vertices[1], [3.0, 4.0, 5.0]);
+        assert_eq!(mesh.polygon_vertices, [0, 1, 2, 2, 1, 3, 4]);
+        assert_eq!(mesh.polygon_starts, [0, 3, 7]);
+        assert_eq!(mesh.polygons().collect::<Vec<_>>(), [&[0, 1, 2][..], &[2, 1, 3, 4][..]]);
+        assert_eq!((0..7).map(|v| mesh.polygon_of_vertex(v)).collect::<Vec<_>>(), [0, 0, 0, 1, 1, 1, 1]);
+        // The edge from the last polygon vertex wraps around to the first one.
+        assert_eq!(mesh.edges, [0, 2, 6]);
+        assert_eq!((0..3).map(|e| mesh.edge_vertices(e)).collect::<Vec<_>>(), [[0, 1], [2, 0], [4, 2]]);
+    }
+
+    #[test]
+    fn unterminated_last_polygon() {
+        let mesh = decode(5, vec![0, 1, -3, 3, 4], vec![]).unwrap();
+        assert_eq!(mesh.polygon_vertices, [0, 1, 2, 3, 4]);
+        assert_eq!(mesh.polygon_starts, [0, 3, 5]);
+        assert_eq!(mesh.polygon(1), [3, 4]);
+        assert_eq!(mesh.polygon_of_vertex(4), 1);
+    }
+
+    #[test]
+    fn out_of_range_indices() {
+        assert!(decode(5, vec![0, 1, -6], vec![]).is_err());
+        assert!(decode(5, vec![0, 5, -3], vec![]).is_err());
+        assert!(decode(5, vec![0, 1, -3], vec![3]).is_err());
+        assert!(decode(5, vec![0, 1, -3], vec![-1]).is_err());
+        assert!(decode(5, vec![0, 1, -3], vec![2]).is_ok());
+    }
+}
@@ -0,0 +1,185 @@
+//! Contains typed views of FBX 7 data.
+//!
+//! Types in this module interpret generic nodes (see [`tree`](../tree/index.html)) as
+//! FBX contents, such as file metadata and scene settings.
+
+use error::{Error, Result};
+use property::Property;
+use tree::Node;
+
+pub use self::animation::{AnimationChannel, AnimationCurve, AnimationCurveNode, AnimationLayer, AnimationStack, AnimationTarget, Interpolation, Keyframe, LayerBlendMode, TangentMode, animation_stacks};
+pub use self::bake::{BakeOptions, BakedTrack, bake_stack, sample_times};
+pub use self::blend_shape::{BlendShape, BlendShapeChannel, Shape, geometry_blend_shapes};
+pub use self::definitions::{Definitions, EffectiveProperties, ObjectType, PropertyTemplate};
+pub use self::global_settings::{Axis, AxisSystem, GlobalSettings, SignedAxis};
+pub use self::header_extension::{HeaderExtension, MetaData, SceneInfo, TimeStamp, read_header_extension};
+pub use self::hierarchy::{DepthFirst, Hierarchy, NodeType, SceneNode};
+pub use self::layer_element::{LayerElement, LayerElementKind, MappingMode, MaterialLayer, ReferenceMode};
+pub use self::material::{BlendMode, LayeredTexture, Material, MaterialTexture, Texture, TextureLayer, TextureSource, WrapMode};
+pub use self::matrix::Matrix;
+pub use self::mesh::{Mesh, Triangle, TriangulationMethod};
+pub use self::node_attribute::{ApertureMode, Camera, DecayType, Light, LightType, ProjectionType, model_camera, model_light};
+pub use self::object_graph::{Connection, ConnectionType, Object, ObjectGraph, ROOT_ID, parent_model};
+pub use self::pose::{Pose, PoseMatrix, PoseMismatch, PoseNode, PoseType, poses, skin_bind_pose};
+pub use self::properties70::{Properties70, PropertyFlags, PropertyRecord, PropertyValue};
+pub use self::skin::{Cluster, Influence, InfluenceOptions, LinkMode, Skin, SkinningType, geometry_skins};
+pub use self::submesh::Submesh;
+pub use self::time::{FbxTime, TimeMode};
+pub use self::transform::{Decomposed, InheritType, ModelTransform, RotationOrder, TransformProperties, model_transforms};
+pub use self::vertex_buffer::{IndexBuffer, VertexAttributes, VertexBuffers, Welder};
+pub use self::video::{EmbeddedMedia, ImageFormat, MediaInfo, embedded_media, stream_embedded_media};
+
+pub mod animation;
+pub mod bake;
+pub mod blend_shape;
+pub mod definitions;
+pub mod global_settings;
+pub mod header_extension;
+pub mod hierarchy;
+pub mod layer_element;
+pub mod material;
+pub mod mesh;
+pub mod node_attribute;
+pub mod object_graph;
+pub mod pose;
+pub mod properties70;
+pub mod skin;
+pub mod submesh;
+pub mod time;
+pub mod transform;
+pub mod vertex_buffer;
+pub mod video;
+
+mod matrix;
+
+
+/// Returns the first property of the first child with the given name.
+fn child_property<'a>(node: &'a Node, name: &str) -> Option<Property<'a>> {
+    node.first_child_by_name(name).and_then(|child| child.properties.iter().next())
+}
+
+/// Returns the first property of the first child with the given name as `i32`.
+fn child_i32(node: &Node, name: &str) -> Result<Option<i32>> {
+    match child_property(node, name) {
+        Some(prop) => prop.as_i32().map(Some).ok_or_else(|| unexpected_type(node, name, "integer", &prop)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the first property of the first child with the given name as `String`.
+fn child_string(node: &Node, name: &str) -> Result<Option<String>> {
+    match child_property(node, name) {
+        Some(prop) => prop.get_string().map(|s| Some(s.to_owned())).ok_or_else(|| unexpected_type(node, name, "string", &prop)),
+        None => Ok(None),
+    }
+}
+
+fn unexpected_type(node: &Node, name: &str, expected: &str, actual: &Property) -> Error {
+    Error::UnexpectedValue(format!("Expected {} value for `{}/{}`, but got {:?}", expected, node.name, name, actual))
+}
+
+/// Returns the first property of the first child with the given name as `Vec<f64>`.
+fn child_vec_f64(node: &Node, name: &str) -> Result<Option<Vec<f64>>> {
+    match child_property(node, name) {
+        Some(prop) => prop.into_vec_f64().map(Some).map_err(|prop| unexpected_type(node, name, "floating-point array", &prop)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the first property of the first child with the given name as `Vec<f32>`.
+fn child_vec_f32(node: &Node, name: &str) -> Result<Option<Vec<f32>>> {
+    match child_property(node, name) {
+        Some(prop) => prop.into_vec_f32().map(Some).map_err(|prop| unexpected_type(node, name, "floating-point array", &prop)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the first property of the first child with the given name as `Vec<i64>`.
+fn child_vec_i64(node: &Node, name: &str) -> Result<Option<Vec<i64>>> {
+    match child_property(node, name) {
+        Some(prop) => prop.into_vec_i64().map(Some).map_err(|prop| unexpected_type(node, name, "integer array", &prop)),
+        None => Ok(None),
+    }
+}
+
+/// Returns the first property of the first child with the given name as `Vec<i32>`.
+fn child_vec_i32(node: &Node, name: &str) -> Result<Option<Vec<i32>>> {
+    match child_property(node, name) {
+        Some(prop) => prop.extract_vec_i32().map(Some).map_err(|prop| unexpected_type(node, name, "32-bit integer array", &prop)),
+        None => Ok(None),
+    }
+}
+
+/// Converts a flat array into an array of 3D vectors.
+fn to_vec3(values: &[f64], node: &Node, name: &str) -> Result<Vec<[f64; 3]>> {
+    let vectors = values.chunks_exact(3);
+    if !vectors.remainder().is_empty() {
+        return Err(Error::DataError(format!("Length of `{}/{}` is not a multiple of 3: {}", node.name, name, values.len())));
+    }
+    Ok(vectors.map(|v| [v[0], v[1], v[2]]).collect())
+}
+
+/// Returns the first property of the first child with the given name as a 4x4 matrix.
+///
+/// Matrices are stored in column-major order, i.e. elements 12, 13 and 14 are the translation.
+fn child_matrix(node: &Node, name: &str) -> Result<Option<[f64; 16]>> {
+    let values = match child_vec_f64(node, name)? {
+        Some(values) => values,
+        None => return Ok(None),
+    };
+    if values.len() != 16 {
+        return Err(Error::DataError(format!("Expected 16 elements for `{}/{}`, but got {}", node.name, name, values.len())));
+    }
+    let mut matrix = [0.0; 16];
+    matrix.copy_from_slice(&values);
+    Ok(Some(matrix))
+}
+
+/// Checks whether the object is a deformer of the given type.
+///
+/// Sub-deformers (such as clusters) usually have `SubDeformer` class rather than `Deformer`.
+fn is_deformer(object: &Object, subclass: &str) -> bool {
+    (object.class == "Deformer" || object.class == "SubDeformer") && object.subclass == subclass
+}
+
+/// Checks whether the object is a deformer of the given type.
+fn check_deformer(object: &Object, subclass: &str) -> Result<()> {
+    if is_deformer(object, subclass) {
+        Ok(())
+    } else {
+        Err(Error::UnexpectedValue(format!("Expected `Deformer` object of `{}`, but got `{}` object of `{}`", subclass, object.class, object.subclass)))
+    }
+}
+
+/// Checks whether the object has the given class.
+fn check_class(object: &Object, class: &str) -> Result<()> {
+    if object.class == class {
+        Ok(())
+    } else {
+        Err(Error::UnexpectedValue(format!("Expected `{}` object, but got `{}` object", class, object.class)))
+    }
+}
+
+/// Checks whether the object has the given class and subclass.
+fn check_subclass(object: &Object, class: &str, subclass: &str) -> Result<()> {
+    if object.class == class && object.subclass == subclass {
+        Ok(())
+    } else {
+        Err(Error::UnexpectedValue(format!("Expected `{}` object of `{}`, but got `{}` object of `{}`", class, subclass, object.class, object.subclass)))
+    }
+}
+
+/// Returns the value of the property record as `f64`, or the default if missing.
+fn number(properties: &Properties70, name: &str, default: f64) -> f64 {
+    properties.value(name).and_then(PropertyValue::as_f64).unwrap_or(default)
+}
+
+/// Returns the value of the property record as `i64`, or the default if missing.
+fn integer(properties: &Properties70, name: &str, default: i64) -> i64 {
+    properties.value(name).and_then(PropertyValue::as_i64).unwrap_or(default)
+}
+
+/// Returns the value of the property record as a 3D vector, or the default if missing.
+fn vector3(properties: &Properties70, name: &str, default: [f64; 3]) -> [f64; 3] {
+    properties.value(name).and_then(PropertyValue::as_vector3).unwrap_or(default)
+}
@@ -0,0 +1,461 @@
+//! Contains camera and light node attributes.
+//!
+//! A `NodeAttribute` object is connected to a `Model` by an `OO` connection, and holds the
+//! type-specific data of the model such as camera lenses and light colors.
+
+use std::f64::consts::PI;
+use error::{Error, Result};
+use scene::{check_subclass, integer, number, vector3};
+use scene::definitions::Definitions;
+use scene::object_graph::{Object, ObjectGraph};
+use scene::properties70::{Properties70, PropertyValue};
+
+
+/// Millimeters per inch, used to convert film sizes.
+const MM_PER_INCH: f64 = 25.4;
+
+
+/// Camera projection type (`CameraProjectionType`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProjectionType {
+    /// Perspective projection (`0`).
+    Perspective,
+    /// Orthographic projection (`1`).
+    Orthographic,
+}
+
+/// How the camera field of view is specified (`ApertureMode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ApertureMode {
+    /// Horizontal and vertical angles, `FieldOfViewX` and `FieldOfViewY` (`0`).
+    HorizontalAndVertical,
+    /// Horizontal angle, `FieldOfView` (`1`).
+    Horizontal,
+    /// Vertical angle, `FieldOfView` (`2`).
+    Vertical,
+    /// Focal length and film size (`3`).
+    FocalLength,
+}
+
+
+/// `NodeAttribute` object of `Camera`.
+///
+/// Properties missing in the file have default values of FBX SDK.
+#[derive(Debug)]
+pub struct Camera<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Attribute name.
+    pub name: &'a str,
+    /// Projection type.
+    pub projection: ProjectionType,
+    /// Aperture mode.
+    pub aperture_mode: ApertureMode,
+    /// `FieldOfView` in degrees.
+    pub field_of_view: f64,
+    /// `FieldOfViewX` in degrees.
+    pub field_of_view_x: f64,
+    /// `FieldOfViewY` in degrees.
+    pub field_of_view_y: f64,
+    /// `FocalLength` in millimeters.
+    pub focal_length: f64,
+    /// `FilmWidth` in inches.
+    pub film_width: f64,
+    /// `FilmHeight` in inches.
+    pub film_height: f64,
+    /// `FilmSqueezeRatio`.
+    pub film_squeeze_ratio: f64,
+    /// `NearPlane`.
+    pub near_plane: f64,
+    /// `FarPlane`.
+    pub far_plane: f64,
+    /// `AspectWidth`, resolution width.
+    pub aspect_width: f64,
+    /// `AspectHeight`, resolution height.
+    pub aspect_height: f64,
+    /// `OrthoZoom`.
+    pub ortho_zoom: f64,
+    /// All properties.
+    pub properties: Properties70<'a>,
+}
+
+impl<'a> Camera<'a> {
+    /// Decodes the `NodeAttribute` object of `Camera`.
+    ///
+    /// Property templates are not applied (see [`definitions`](../definitions/index.html)).
+    pub fn from_object(object: &Object<'a>) -> Result<Self> {
+        check_subclass(object, "NodeAttribute", "Camera")?;
+        Camera::from_properties(object, Properties70::from_parent(object.node)?)
+    }
+
+    /// Decodes the `NodeAttribute` object of `Camera`, with default values from the property
+    /// template in `definitions`.
+    ///
+    /// `properties` has template records not overridden by the object, too.
+    pub fn from_object_with(object: &Object<'a>, definitions: &Definitions<'a>) -> Result<Self> {
+        check_subclass(object, "NodeAttribute", "Camera")?;
+        Camera::from_properties(object, definitions.properties_of(object)?.to_properties())
+    }
+
+    fn from_properties(object: &Object<'a>, properties: Properties70<'a>) -> Result<Self> {
+        let projection = match integer(&properties, "CameraProjectionType", 0) {
+            0 => ProjectionType::Perspective,
+            1 => ProjectionType::Orthographic,
+            v => return Err(Error::UnexpectedValue(format!("Unknown camera projection type: {}", v))),
+        };
+        let aperture_mode = match integer(&properties, "ApertureMode", 2) {
+            0 => ApertureMode::HorizontalAndVertical,
+            1 => ApertureMode::Horizontal,
+            2 => ApertureMode::Vertical,
+            3 => ApertureMode::FocalLength,
+            v => return Err(Error::UnexpectedValue(format!("Unknown camera aperture mode: {}", v))),
+        };
+        Ok(Camera {
+            id: object.id,
+            name: object.name,
+            projection,
+            aperture_mode,
+            field_of_view: number(&properties, "FieldOfView", 25.114_999_771_118_164),
+            field_of_view_x: number(&properties, "FieldOfViewX", 40.0),
+            field_of_view_y: number(&properties, "FieldOfViewY", 40.0),
+            focal_length: number(&properties, "FocalLength", 34.893_013_7),
+            film_width: number(&properties, "FilmWidth", 0.816),
+            film_height: number(&properties, "FilmHeight", 0.612),
+            film_squeeze_ratio: number(&properties, "FilmSqueezeRatio", 1.0),
+            near_plane: number(&properties, "NearPlane", 10.0),
+            far_plane: number(&properties, "FarPlane", 4000.0),
+            aspect_width: number(&properties, "AspectWidth", 320.0),
+            aspect_height: number(&properties, "AspectHeight", 200.0),
+            ortho_zoom: number(&properties, "OrthoZoom", 1.0),
+            properties,
+        })
+    }
+
+    /// Returns the aspect ratio (width / height) of the rendered image.
+    ///
+    /// `AspectWidth / AspectHeight` is used if valid, otherwise the film aspect ratio.
+    pub fn aspect_ratio(&self) -> f64 {
+        if self.aspect_width > 0.0 && self.aspect_height > 0.0 {
+            self.aspect_width / self.aspect_height
+        } else if self.film_height > 0.0 {
+            self.film_width * self.film_squeeze_ratio / self.film_height
+        } else {
+            1.0
+        }
+    }
+
+    /// Returns the vertical field of view in radians.
+    ///
+    /// With `ApertureMode::FocalLength`, returns π if `FocalLength` is not positive.
+    pub fn vertical_fov_radians(&self) -> f64 {
+        match self.aperture_mode {
+            ApertureMode::Vertical => self.field_of_view.to_radians(),
+            ApertureMode::HorizontalAndVertical => self.field_of_view_y.to_radians(),
+            ApertureMode::Horizontal => {
+                let half = self.field_of_view.to_radians() / 2.0;
+                2.0 * (half.tan() / self.aspect_ratio()).atan()
+            },
+            ApertureMode::FocalLength => focal_length_to_fov(self.focal_length, self.film_height * MM_PER_INCH),
+        }
+    }
+
+    /// Returns the horizontal field of view in radians.
+    ///
+    /// With `ApertureMode::FocalLength`, returns π if `FocalLength` is not positive.
+    pub fn horizontal_fov_radians(&self) -> f64 {
+        match self.aperture_mode {
+            ApertureMode::Horizontal => self.field_of_view.to_radians(),
+            ApertureMode::HorizontalAndVertical => self.field_of_view_x.to_radians(),
+            ApertureMode::Vertical => {
+                let half = self.field_of_view.to_radians() / 2.0;
+                2.0 * (half.tan() * self.aspect_ratio()).atan()
+            },
+            ApertureMode::FocalLength => {
+                focal_length_to_fov(self.focal_length, self.film_width * self.film_squeeze_ratio * MM_PER_INCH)
+            },
+        }
+    }
+}
+
+/// Computes the field of view in radians from the focal length and the film size in
+/// millimeters.
+///
+/// Returns π (the limit as the focal length approaches zero) if the focal length is not positive.
+fn focal_length_to_fov(focal_length: f64, film_size: f64) -> f64 {
+    if focal_length <= 0.0 {
+        return PI;
+    }
+    2.0 * (film_size / 2.0 / focal_length).atan()
+}
+
+
+/// Light type (`LightType`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LightType {
+    /// Point light (`0`).
+    Point,
+    /// Directional light (`1`).
+    Directional,
+    /// Spot light (`2`).
+    Spot,
+    /// Area light (`3`).
+    Area,
+    /// Volume light (`4`).
+    Volume,
+}
+
+/// Light decay (`DecayType`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecayType {
+    /// No decay (`0`).
+    None,
+    /// Linear decay (`1`).
+    Linear,
+    /// Quadratic decay (`2`).
+    Quadratic,
+    /// Cubic decay (`3`).
+    Cubic,
+}
+
+
+/// `NodeAttribute` object of `Light`.
+///
+/// Properties missing in the file have default values of FBX SDK.
+#[derive(Debug)]
+pub struct Light<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Attribute name.
+    pub name: &'a str,
+    /// Light type.
+    pub light_type: LightType,
+    /// `Color`.
+    pub color: [f64; 3],
+    /// `Intensity`, in percent.
+    pub intensity: f64,
+    /// Decay type.
+    pub decay_type: DecayType,
+    /// `DecayStart`.
+    pub decay_start: f64,
+    /// `InnerAngle` of spot lights, in degrees.
+    pub inner_angle: f64,
+    /// `OuterAngle` of spot lights, in degrees.
+    pub outer_angle: f64,
+    /// `CastShadows`.
+    pub cast_shadows: bool,
+    /// `CastLightOnObject`.
+    pub cast_light: bool,
+    /// All properties.
+    pub properties: Properties70<'a>,
+}
+
+impl<'a> Light<'a> {
+    /// Decodes the `NodeAttribute` object of `Light`.
+    ///
+    /// Property templates are not applied (see [`definitions`](../definitions/index.html)).
+    pub fn from_object(object: &Object<'a>) -> Result<Self> {
+        check_subclass(object, "NodeAttribute", "Light")?;
+        Light::from_properties(object, Properties70::from_parent(object.node)?)
+    }
+
+    /// Decodes the `NodeAttribute` object of `Light`, with default values from the property
+    /// template in `definitions`.
+    ///
+    /// `properties` has template records not overridden by the object, too.
+    pub fn from_object_with(object: &Object<'a>, definitions: &Definitions<'a>) -> Result<Self> {
+        check_subclass(object, "NodeAttribute", "Light")?;
+        Light::from_properties(object, definitions.properties_of(object)?.to_properties())
+    }
+
+    fn from_properties(object: &Object<'a>, properties: Properties70<'a>) -> Result<Self> {
+        let light_type = match integer(&properties, "LightType", 0) {
+            0 => LightType::Point,
+            1 => LightType::Directional,
+            2 => LightType::Spot,
+            3 => LightType::Area,
+            4 => LightType::Volume,
+            v => return Err(Error::UnexpectedValue(format!("Unknown light type: {}", v))),
+        };
+        let decay_type = match integer(&properties, "DecayType", 0) {
+            0 => DecayType::None,
+            1 => DecayType::Linear,
+            2 => DecayType::Quadratic,
+            3 => DecayType::Cubic,
+            v => return Err(Error::UnexpectedValue(format!("Unknown light decay type: {}", v))),
+        };
+        let boolean = |name, default| properties.value(name).and_then(PropertyValue::as_bool).unwrap_or(default);
+        Ok(Light {
+            id: object.id,
+            name: object.name,
+            light_type,
+            color: vector3(&properties, "Color", [1.0; 3]),
+            intensity: number(&properties, "Intensity", 100.0),
+            decay_type,
+            decay_start: number(&properties, "DecayStart", 0.0),
+            // Old files have `HotSpot` and `Cone angle` instead.
+            inner_angle: properties.value("InnerAngle").or_else(|| properties.value("HotSpot"))
+                .and_then(PropertyValue::as_f64)
+                .unwrap_or(0.0),
+            outer_angle: properties.value("OuterAngle").or_else(|| properties.value("Cone angle"))
+                .and_then(PropertyValue::as_f64)
+                .unwrap_or(45.0),
+            cast_shadows: boolean("CastShadows", true),
+            cast_light: boolean("CastLightOnObject", true),
+            properties,
+        })
+    }
+}
+
+
+/// Returns the camera attribute of the model, if exists.
+///
+/// Default values come from the property template in `definitions`.
+pub fn model_camera<'a>(graph: &ObjectGraph<'a>, definitions: &Definitions<'a>, model: i64) -> Result<Option<Camera<'a>>> {
+    match model_attribute(graph, model, "Camera") {
+        Some(object) => Camera::from_object_with(object, definitions).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Returns the light attribute of the model, if exists.
+///
+/// Default values come from the property template in `definitions`.
+pub fn model_light<'a>(graph: &ObjectGraph<'a>, definitions: &Definitions<'a>, model: i64) -> Result<Option<Light<'a>>> {
+    match model_attribute(graph, model, "Light") {
+        Some(object) => Light::from_object_with(object, definitions).map(Some),
+        None => Ok(None),
+    }
+}
+
+fn model_attribute<'g, 'a>(graph: &'g ObjectGraph<'a>, model: i64, subclass: &str) -> Option<&'g Object<'a>> {
+    graph.child_objects(model).find(|child| child.class == "NodeAttribute" && child.subclass == subclass)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use property::Property;
+    use test_util::{node, object, p, tree, TestNode};
+
+    /// Returns vertical and horizontal FOVs in degrees of the camera with the properties.
+    fn fovs(properties: Vec<TestNode<'static>>) -> (f64, f64) {
+        let tree = tree(7400, &[
+            node("Objects", vec![], vec![
+                object("NodeAttribute", 1, "\0\x01NodeAttribute", "Camera", vec![node("Properties70", vec![], properties)]),
+            ]),
+        ]);
+        let graph = ObjectGraph::from_tree(&tree);
+        let camera = Camera::from_object(graph.object(1).unwrap()).unwrap();
+        (camera.vertical_fov_radians().to_degrees(), camera.horizontal_fov_radians().to_degrees())
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-3, "{} != {}", actual, expected);
+    }
+
+    // Default aspect ratio is 320 / 200.
+    const ASPECT: f64 = 1.6;
+
+    #[test]
+    fn fov_vertical() {
+        let (v, h) = fovs(vec![
+            p("ApertureMode", "enum", "", vec![Property::I32(2)]),
+            p("FieldOfView", "FieldOfView", "", vec![Property::F64(40.0)]),
+        ]);
+        assert_close(v, 40.0);
+        assert_close(h, 2.0 * (20.0_f64.to_radians().tan() * ASPECT).atan().to_degrees());
+    }
+
+    #[test]
+    fn fov_horizontal() {
+        let (v, h) = fovs(vec![
+            p("ApertureMode", "enum", "", vec![Property::I32(1)]),
+            p("FieldOfView", "FieldOfView", "", vec![Property::F64(60.0)]),
+        ]);
+        assert_close(v, 2.0 * (30.0_f64.to_radians().tan() / ASPECT).atan().to_degrees());
+        assert_close(h, 60.0);
+    }
+
+    #[test]
+    fn fov_horizontal_and_vertical() {
+        let (v, h) = fovs(vec![
+            p("ApertureMode", "enum", "", vec![Property::I32(0)]),
+            p("FieldOfView", "FieldOfView", "", vec![Property::F64(60.0)]),
+            p("FieldOfViewX", "FieldOfViewX", "", vec![Property::F64(50.0)]),
+            p("FieldOfViewY", "FieldOfViewY", "", vec![Property::F64(30.0)]),
+        ]);
+        assert_close(v, 30.0);
+        assert_close(h, 50.0);
+    }
+
+    #[test]
+    fn fov_focal_length() {
+        // Default focal length (34.893 mm) and film size (0.816 x 0.612 inches) of FBX SDK.
+        let (v, h) = fovs(vec![p("ApertureMode", "enum", "", vec![Property::I32(3)])]);
+        assert_close(v, 25.115);
+        assert_close(h, 2.0 * (0.816 * MM_PER_INCH / 2.0 / 34.893_013_7).atan().to_degrees());
+
+        let (v, h) = fovs(vec![
+            p("ApertureMode", "enum", "", vec![Property::I32(3)]),
+            p("FocalLength", "Number", "", vec![Property::F64(0.0)]),
+        ]);
+        assert_close(v, 180.0);
+        assert_close(h, 180.0);
+    }
+
+    /// Returns the type, decay, inner and outer angles, and `CastShadows` of the light with the
+    /// properties.
+    fn light(properties: Vec<TestNode<'static>>) -> Result<(LightType, DecayType, f64, f64, bool)> {
+        let tree = tree(7400, &[
+            node("Objects", vec![], vec![
+                object("NodeAttribute", 1, "\0\x01NodeAttribute", "Light", vec![node("Properties70", vec![], properties)]),
+            ]),
+        ]);
+        let graph = ObjectGraph::from_tree(&tree);
+        Light::from_object(graph.object(1).unwrap())
+            .map(|light| (light.light_type, light.decay_type, light.inner_angle, light.outer_angle, light.cast_shadows))
+    }
+
+    fn enum_value(name: &'static str, value: i32) -> TestNode<'static> {
+        p(name, "enum", "", vec![Property::I32(value)])
+    }
+
+    fn angle(name: &'static str, value: f64) -> TestNode<'static> {
+        p(name, "Number", "A", vec![Property::F64(value)])
+    }
+
+    #[test]
+    fn light_types() {
+        let types = [LightType::Point, LightType::Directional, LightType::Spot, LightType::Area, LightType::Volume];
+        for (i, &light_type) in types.iter().enumerate() {
+            assert_eq!(light(vec![enum_value("LightType", i as i32)]).unwrap().0, light_type);
+        }
+        let decays = [DecayType::None, DecayType::Linear, DecayType::Quadratic, DecayType::Cubic];
+        for (i, &decay_type) in decays.iter().enumerate() {
+            assert_eq!(light(vec![enum_value("DecayType", i as i32)]).unwrap().1, decay_type);
+        }
+        assert!(light(vec![enum_value("LightType", 5)]).is_err());
+        assert!(light(vec![enum_value("DecayType", -1)]).is_err());
+    }
+
+    #[test]
+    fn light_defaults() {
+        assert_eq!(light(vec![]).unwrap(), (LightType::Point, DecayType::None, 0.0, 45.0, true));
+        let cast_shadows = |value| light(vec![p("CastShadows", "bool", "", vec![Property::I32(value)])]).unwrap().4;
+        assert!(!cast_shadows(0));
+        assert!(cast_shadows(1));
+    }
+
+    #[test]
+    fn light_angles() {
+        let spot = light(vec![enum_value("LightType", 2), angle("InnerAngle", 20.0), angle("OuterAngle", 30.0)]).unwrap();
+        assert_eq!((spot.2, spot.3), (20.0, 30.0));
+        // Old files have `HotSpot` and `Cone angle`.
+        let old = light(vec![enum_value("LightType", 2), angle("HotSpot", 10.0), angle("Cone angle", 40.0)]).unwrap();
+        assert_eq!((old.2, old.3), (10.0, 40.0));
+        // New names take precedence.
+        let both = light(vec![angle("HotSpot", 10.0), angle("InnerAngle", 20.0), angle("Cone angle", 40.0), angle("OuterAngle", 30.0)]).unwrap();
+        assert_eq!((both.2, both.3), (20.0, 30.0));
+    }
+}
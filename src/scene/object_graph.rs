@@ -0,0 +1,393 @@
+//! Contains object graph built from `Objects` and `Connections`.
+//!
+//! Every child of `Objects` node is an object, whose first three properties are ID, name and
+//! subclass:
+//!
+//! ```text
+//! Model: 123456, "Cube\x00\x01Model", "Mesh" { ... }
+//! ```
+//!
+//! Relations between objects are defined by `C` children of `Connections` node.
+//! The first property is connection type, and the rest are IDs and property names:
+//!
+//! | Type | Properties                                            |
+//! |:-----|:------------------------------------------------------|
+//! | `OO` | child ID, parent ID                                   |
+//! | `OP` | child ID, parent ID, parent property name             |
+//! | `PO` | child ID, child property name, parent ID              |
+//! | `PP` | child ID, child property name, parent ID, parent property name |
+//!
+//! ID `0` is the implicit root (scene root node).
+
+use std::collections::HashMap;
+use std::slice;
+use error::{Error, Result};
+use property::Property;
+use tree::{Node, Tree};
+
+
+/// ID of the implicit root object.
+pub const ROOT_ID: i64 = 0;
+
+
+/// An object, i.e. a child of `Objects` node.
+#[derive(Debug, Clone, Copy)]
+pub struct Object<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Object name.
+    pub name: &'a str,
+    /// Class name, such as `Model` and `Geometry`.
+    ///
+    /// If the name property has no class part, this is the node name.
+    pub class: &'a str,
+    /// Subclass name, such as `Mesh` and `LimbNode`.
+    ///
+    /// Empty if the object has no subclass property.
+    pub subclass: &'a str,
+    /// The object node.
+    pub node: &'a Node,
+}
+
+impl<'a> Object<'a> {
+    /// Constructs `Object` from the child node of `Objects`.
+    pub fn from_node(node: &'a Node) -> Result<Self> {
+        let mut props = node.properties.iter();
+        let id = match props.next().as_ref().and_then(Property::as_i64) {
+            Some(id) => id,
+            None => return Err(Error::UnexpectedValue(format!("Object `{}` has no ID", node.name))),
+        };
+        let (name, class) = props.next().as_ref().and_then(Property::get_name_class).unwrap_or(("", None));
+        let subclass = props.next().as_ref().and_then(Property::get_string).unwrap_or("");
+        Ok(Object {
+            id,
+            name,
+            class: class.unwrap_or(&node.name),
+            subclass,
+            node,
+        })
+    }
+}
+
+
+/// Type of a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConnectionType {
+    /// Object to object (`OO`).
+    ObjectObject,
+    /// Object to property (`OP`).
+    ObjectProperty,
+    /// Property to object (`PO`).
+    PropertyObject,
+    /// Property to property (`PP`).
+    PropertyProperty,
+}
+
+
+/// A connection, i.e. a `C` child of `Connections` node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Connection<'a> {
+    /// Connection type.
+    pub type_: ConnectionType,
+    /// Child (source) object ID.
+    pub child: i64,
+    /// Child property name, for `PO` and `PP` connections.
+    pub child_property: Option<&'a str>,
+    /// Parent (destination) object ID.
+    pub parent: i64,
+    /// Parent property name, for `OP` and `PP` connections.
+    pub parent_property: Option<&'a str>,
+}
+
+impl<'a> Connection<'a> {
+    /// Constructs `Connection` from the `C` node.
+    pub fn from_node(node: &'a Node) -> Result<Self> {
+        let props: Vec<_> = node.properties.iter().collect();
+        let invalid = || Error::UnexpectedValue(format!("Invalid connection: {:?}", props));
+        let id = |i: usize| props.get(i).and_then(Property::as_i64).ok_or_else(invalid);
+        let name = |i: usize| props.get(i).and_then(Property::get_string).ok_or_else(invalid);
+        let connection = match name(0)? {
+            "OO" => Connection {
+                type_: ConnectionType::ObjectObject,
+                child: id(1)?,
+                child_property: None,
+                parent: id(2)?,
+                parent_property: None,
+            },
+            "OP" => Connection {
+                type_: ConnectionType::ObjectProperty,
+                child: id(1)?,
+                child_property: None,
+                parent: id(2)?,
+                parent_property: Some(name(3)?),
+            },
+            "PO" => Connection {
+                type_: ConnectionType::PropertyObject,
+                child: id(1)?,
+                child_property: Some(name(2)?),
+                parent: id(3)?,
+                parent_property: None,
+            },
+            "PP" => Connection {
+                type_: ConnectionType::PropertyProperty,
+                child: id(1)?,
+                child_property: Some(name(2)?),
+                parent: id(3)?,
+                parent_property: Some(name(4)?),
+            },
+            _ => return Err(invalid()),
+        };
+        Ok(connection)
+    }
+}
+
+
+/// Objects and connections between them.
+#[derive(Debug, Clone, Default)]
+pub struct ObjectGraph<'a> {
+    /// Objects in order of appearance.
+    objects: Vec<Object<'a>>,
+    /// Indices of objects by ID.
+    object_indices: HashMap<i64, usize>,
+    /// Connections in order of appearance.
+    connections: Vec<Connection<'a>>,
+    /// Indices of connections by child ID.
+    connections_by_child: HashMap<i64, Vec<usize>>,
+    /// Indices of connections by parent ID.
+    connections_by_parent: HashMap<i64, Vec<usize>>,
+}
+
+impl<'a> ObjectGraph<'a> {
+    /// Builds the object graph from `Objects` and `Connections` top-level nodes of the tree.
+    ///
+    /// Missing `Objects` or `Connections` node is treated as empty.
+    pub fn from_tree(tree: &'a Tree) -> Self {
+        ObjectGraph::from_nodes(tree.first_child_by_name("Objects"), tree.first_child_by_name("Connections"))
+    }
+
+    /// Builds the object graph from `Objects` and `Connections` nodes.
+    ///
+    /// Objects without IDs, connections of unknown types, and connections with invalid
+    /// properties are logged and skipped.
+    pub fn from_nodes(objects: Option<&'a Node>, connections: Option<&'a Node>) -> Self {
+        let mut graph = ObjectGraph::default();
+        for node in objects.into_iter().flat_map(|objects| objects.children.iter()) {
+            let object = match Object::from_node(node) {
+                Ok(object) => object,
+                Err(err) => {
+                    warn!("Ignoring object: {}", err);
+                    continue;
+                },
+            };
+            if graph.object_indices.contains_key(&object.id) {
+                warn!("Duplicate object ID {}, ignoring `{}` object {:?}", object.id, object.class, object.name);
+                continue;
+            }
+            graph.object_indices.insert(object.id, graph.objects.len());
+            graph.objects.push(object);
+        }
+        for node in connections.into_iter().flat_map(|connections| connections.children_by_name("C")) {
+            let connection = match Connection::from_node(node) {
+                Ok(connection) => connection,
+                Err(err) => {
+                    warn!("Ignoring connection: {}", err);
+                    continue;
+                },
+            };
+            let index = graph.connections.len();
+            graph.connections_by_child.entry(connection.child).or_default().push(index);
+            graph.connections_by_parent.entry(connection.parent).or_default().push(index);
+            graph.connections.push(connection);
+        }
+        graph
+    }
+
+    /// Returns the object with the given ID.
+    pub fn object(&self, id: i64) -> Option<&Object<'a>> {
+        self.object_indices.get(&id).map(|&index| &self.objects[index])
+    }
+
+    /// Returns an iterator of all objects in order of appearance.
+    pub fn objects(&self) -> slice::Iter<'_, Object<'a>> {
+        self.objects.iter()
+    }
+
+    /// Returns an iterator of all connections in order of appearance.
+    pub fn connections(&self) -> slice::Iter<'_, Connection<'a>> {
+        self.connections.iter()
+    }
+
+    /// Returns an iterator of connections whose parent is the given object.
+    pub fn children_of(&self, id: i64) -> Connections<'_, 'a> {
+        Connections {
+            connections: &self.connections,
+            indices: self.connections_by_parent.get(&id).map_or(&[][..], |v| &v[..]).iter(),
+        }
+    }
+
+    /// Returns an iterator of connections whose child is the given object.
+    pub fn parents_of(&self, id: i64) -> Connections<'_, 'a> {
+        Connections {
+            connections: &self.connections,
+            indices: self.connections_by_child.get(&id).map_or(&[][..], |v| &v[..]).iter(),
+        }
+    }
+
+    /// Returns an iterator of connections to the given property of the given object.
+    pub fn connected_via<'g>(&'g self, id: i64, property: &'g str) -> impl Iterator<Item = &'g Connection<'a>> + 'g {
+        self.children_of(id).filter(move |c| c.parent_property == Some(property))
+    }
+
+    /// Returns an iterator of objects connected to the given object as children.
+    ///
+    /// Both object-object and object-property connections are included, and connections to
+    /// unknown objects are skipped.
+    pub fn child_objects<'g>(&'g self, id: i64) -> impl Iterator<Item = &'g Object<'a>> + 'g {
+        self.children_of(id).filter_map(move |c| self.object(c.child))
+    }
+
+    /// Returns an iterator of objects connected to the given object as parents.
+    ///
+    /// Connections to unknown objects (including the root) are skipped.
+    pub fn parent_objects<'g>(&'g self, id: i64) -> impl Iterator<Item = &'g Object<'a>> + 'g {
+        self.parents_of(id).filter_map(move |c| self.object(c.parent))
+    }
+}
+
+
+/// Returns the parent model of the model, if exists.
+pub fn parent_model(graph: &ObjectGraph, model: i64) -> Option<i64> {
+    graph.parents_of(model)
+        .filter(|connection| connection.parent_property.is_none())
+        .filter_map(|connection| graph.object(connection.parent))
+        .find(|parent| parent.class == "Model")
+        .map(|parent| parent.id)
+}
+
+
+/// An iterator of connections.
+#[derive(Debug, Clone)]
+pub struct Connections<'g, 'a: 'g> {
+    connections: &'g [Connection<'a>],
+    indices: slice::Iter<'g, usize>,
+}
+
+impl<'g, 'a> Iterator for Connections<'g, 'a> {
+    type Item = &'g Connection<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.indices.next().map(|&index| &self.connections[index])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use property::Property;
+    use test_util::{connection, node, object, op, string, tree, TestNode};
+    use super::{ConnectionType, ObjectGraph, ROOT_ID};
+
+    fn c(properties: Vec<Property<'static>>) -> TestNode<'static> {
+        node("C", properties, vec![])
+    }
+
+
+    fn objects() -> TestNode<'static> {
+        node("Objects", vec![], vec![
+            object("Model", 10, "Cube\0\x01Model", "Mesh", vec![]),
+            object("Geometry", 20, "\0\x01Geometry", "Mesh", vec![]),
+            object("Material", 30, "Red\0\x01Material", "", vec![]),
+            object("Texture", 40, "Wood\0\x01Texture", "", vec![]),
+            object("AnimationCurveNode", 50, "T\0\x01AnimCurveNode", "", vec![]),
+        ])
+    }
+
+    #[test]
+    fn objects_and_connection_types() {
+        let tree = tree(7400, &[objects(), node("Connections", vec![], vec![
+            connection(10, ROOT_ID),
+            connection(20, 10),
+            connection(30, 10),
+            op(40, 30, "DiffuseColor"),
+            c(vec![string("PO"), Property::I64(50), string("d|X"), Property::I64(10)]),
+            c(vec![string("PP"), Property::I64(50), string("d|Y"), Property::I64(10), string("Lcl Translation")]),
+        ])]);
+        let graph = ObjectGraph::from_tree(&tree);
+        let model = graph.object(10).unwrap();
+        assert_eq!((model.name, model.class, model.subclass), ("Cube", "Model", "Mesh"));
+        assert_eq!(graph.object(20).unwrap().name, "");
+        assert!(graph.object(ROOT_ID).is_none());
+        assert_eq!(graph.objects().map(|o| o.id).collect::<Vec<_>>(), [10, 20, 30, 40, 50]);
+
+        let types = graph.connections().map(|c| c.type_).collect::<Vec<_>>();
+        assert_eq!(types, [
+            ConnectionType::ObjectObject,
+            ConnectionType::ObjectObject,
+            ConnectionType::ObjectObject,
+            ConnectionType::ObjectProperty,
+            ConnectionType::PropertyObject,
+            ConnectionType::PropertyProperty,
+        ]);
+        let op = graph.connections().nth(3).unwrap();
+        assert_eq!((op.child, op.child_property, op.parent, op.parent_property), (40, None, 30, Some("DiffuseColor")));
+        let po = graph.connections().nth(4).unwrap();
+        assert_eq!((po.child, po.child_property, po.parent, po.parent_property), (50, Some("d|X"), 10, None));
+        let pp = graph.connections().nth(5).unwrap();
+        assert_eq!((pp.child, pp.child_property, pp.parent, pp.parent_property), (50, Some("d|Y"), 10, Some("Lcl Translation")));
+
+        assert_eq!(graph.children_of(10).map(|c| c.child).collect::<Vec<_>>(), [20, 30, 50, 50]);
+        assert_eq!(graph.parents_of(50).map(|c| c.parent).collect::<Vec<_>>(), [10, 10]);
+        assert_eq!(graph.child_objects(ROOT_ID).map(|o| o.id).collect::<Vec<_>>(), [10]);
+        // The root is not an object.
+        assert_eq!(graph.parent_objects(10).count(), 0);
+        assert_eq!(graph.parent_objects(40).map(|o| o.id).collect::<Vec<_>>(), [30]);
+    }
+
+    #[test]
+    fn connected_via() {
+        let tree = tree(7400, &[objects(), node("Connections", vec![], vec![
+            connection(40, 30),
+            op(40, 30, "DiffuseColor"),
+            op(20, 30, "NormalMap"),
+            c(vec![string("PP"), Property::I64(50), string("d|X"), Property::I64(30), string("DiffuseColor")]),
+        ])]);
+        let graph = ObjectGraph::from_tree(&tree);
+        assert_eq!(graph.connected_via(30, "DiffuseColor").map(|c| c.child).collect::<Vec<_>>(), [40, 50]);
+        assert_eq!(graph.connected_via(30, "NormalMap").map(|c| c.child).collect::<Vec<_>>(), [20]);
+        assert_eq!(graph.connected_via(30, "SpecularColor").count(), 0);
+        assert_eq!(graph.connected_via(40, "DiffuseColor").count(), 0);
+    }
+
+    #[test]
+    fn skip_invalid_entries() {
+        let tree = tree(7400, &[
+            node("Objects", vec![], vec![
+                object("Model", 10, "Cube\0\x01Model", "Mesh", vec![]),
+                // No ID.
+                node("Model", vec![string("NoId\0\x01Model"), string("Null")], vec![]),
+                // Duplicate ID.
+                object("Model", 10, "Duplicate\0\x01Model", "Null", vec![]),
+                object("Geometry", 20, "\0\x01Geometry", "Mesh", vec![]),
+            ]),
+            node("Connections", vec![], vec![
+                // Vendor-specific connection type.
+                c(vec![string("XX"), Property::I64(20), Property::I64(10)]),
+                // Missing parent ID.
+                c(vec![string("OO"), Property::I64(20)]),
+                // Missing parent property name.
+                c(vec![string("OP"), Property::I64(20), Property::I64(10)]),
+                c(vec![]),
+                connection(20, 10),
+            ]),
+        ]);
+        let graph = ObjectGraph::from_tree(&tree);
+        assert_eq!(graph.objects().map(|o| o.id).collect::<Vec<_>>(), [10, 20]);
+        assert_eq!(graph.object(10).unwrap().name, "Cube");
+        assert_eq!(graph.connections().count(), 1);
+        assert_eq!(graph.child_objects(10).map(|o| o.id).collect::<Vec<_>>(), [20]);
+    }
+}
@@ -0,0 +1,472 @@
+//! Contains bind poses and rest poses.
+//!
+//! `Pose` objects are not connected by `Connections`. Each `PoseNode` child refers to a model
+//! by its object ID, and holds the global matrix of the model in the pose:
+//!
+//! ```text
+//! Pose: 123456, "BindPose\x00\x01Pose", "BindPose" {
+//!     Type: "BindPose"
+//!     Version: 100
+//!     NbPoseNodes: 2
+//!     PoseNode:  {
+//!         Node: 234567
+//!         Matrix: *16 { a: ... }
+//!     }
+//!     ...
+//! }
+//! ```
+//!
+//! For a skinned mesh, matrices of the bind pose should be the same as `TransformLink` (for
+//! bones) and `Transform` (for the mesh) of the clusters.
+
+use error::{Error, Result};
+use scene::{check_class, child_matrix, child_property, child_string};
+use scene::object_graph::{Object, ObjectGraph};
+use scene::skin::{Skin, geometry_skins};
+
+
+/// Type of a pose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoseType {
+    /// Pose at the skin binding time (`BindPose`).
+    BindPose,
+    /// Rest pose (`RestPose`).
+    RestPose,
+}
+
+impl PoseType {
+    /// Parses the pose type string.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "BindPose" => Some(PoseType::BindPose),
+            "RestPose" => Some(PoseType::RestPose),
+            _ => None,
+        }
+    }
+}
+
+
+/// A `PoseNode` of a pose.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoseNode {
+    /// ID of the model (`Node`).
+    pub node: i64,
+    /// Global transform of the model in the pose (`Matrix`).
+    pub matrix: [f64; 16],
+}
+
+
+/// `Pose` object.
+#[derive(Debug, Clone)]
+pub struct Pose<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Pose name.
+    pub name: &'a str,
+    /// Pose type.
+    pub pose_type: PoseType,
+    /// Pose nodes in order of appearance.
+    pub nodes: Vec<PoseNode>,
+}
+
+impl<'a> Pose<'a> {
+    /// Decodes the `Pose` object.
+    pub fn from_object(object: &Object<'a>) -> Result<Self> {
+        check_class(object, "Pose")?;
+        let ty = pose_type_name(object)?;
+        let pose_type = PoseType::parse(&ty).ok_or_else(|| Error::UnexpectedValue(format!("Unknown pose type: {:?}", ty)))?;
+        Pose::from_object_of_type(object, pose_type)
+    }
+
+    /// Decodes the `Pose` object whose type is already parsed.
+    fn from_object_of_type(object: &Object<'a>, pose_type: PoseType) -> Result<Self> {
+        let node = object.node;
+        let mut nodes = Vec::new();
+        for pose_node in node.children_by_name("PoseNode") {
+            let id = child_property(pose_node, "Node").and_then(|prop| prop.as_i64())
+                .ok_or_else(|| Error::DataError(format!("Pose {} has a pose node without node ID", object.id)))?;
+            let matrix = child_matrix(pose_node, "Matrix")?
+                .ok_or_else(|| Error::DataError(format!("Pose {} has no matrix for node {}", object.id, id)))?;
+            nodes.push(PoseNode { node: id, matrix });
+        }
+        if let Some(count) = child_property(node, "NbPoseNodes").and_then(|prop| prop.as_i64()) {
+            if count != nodes.len() as i64 {
+                warn!("Pose {} declares {} pose nodes (`NbPoseNodes`), but has {}", object.id, count, nodes.len());
+            }
+        }
+        Ok(Pose {
+            id: object.id,
+            name: object.name,
+            pose_type,
+            nodes,
+        })
+    }
+
+    /// Returns the matrix of the model in the pose.
+    pub fn matrix(&self, node: i64) -> Option<&[f64; 16]> {
+        self.nodes.iter().find(|pose_node| pose_node.node == node).map(|pose_node| &pose_node.matrix)
+    }
+
+    /// Returns whether the pose has the model.
+    pub fn contains(&self, node: i64) -> bool {
+        self.matrix(node).is_some()
+    }
+
+    /// Returns IDs of skeleton models (`LimbNode` and `Root`) in the pose.
+    pub fn skeleton_nodes(&self, graph: &ObjectGraph<'a>) -> Vec<i64> {
+        self.nodes.iter()
+            .filter(|pose_node| matches!(graph.object(pose_node.node), Some(object) if {
+                object.class == "Model" && matches!(object.subclass, "LimbNode" | "Root")
+            }))
+            .map(|pose_node| pose_node.node)
+            .collect()
+    }
+
+    /// Returns mesh models in the pose with skins of their geometries.
+    pub fn skinned_meshes(&self, graph: &ObjectGraph<'a>) -> Result<Vec<(i64, Vec<Skin<'a>>)>> {
+        let mut meshes = Vec::new();
+        for pose_node in &self.nodes {
+            if !matches!(graph.object(pose_node.node), Some(object) if object.class == "Model") {
+                continue;
+            }
+            let mut skins = Vec::new();
+            for geometry in graph.child_objects(pose_node.node).filter(|child| child.class == "Geometry") {
+                skins.extend(geometry_skins(graph, geometry.id)?);
+            }
+            if !skins.is_empty() {
+                meshes.push((pose_node.node, skins));
+            }
+        }
+        Ok(meshes)
+    }
+
+    /// Compares the pose with cluster matrices of the skin.
+    ///
+    /// `TransformLink` of each cluster is compared with the matrix of its bone.
+    /// `Transform` is usually the same for all clusters, so the matrix of the mesh model is
+    /// compared once for each distinct `Transform` (reported with the first cluster having it),
+    /// and a mesh missing in the pose is reported once.
+    /// Returns mismatches whose element-wise difference exceeds the tolerance, and models
+    /// missing in the pose.
+    pub fn verify_skin(&self, graph: &ObjectGraph<'a>, skin: &Skin<'a>, tolerance: f64) -> Vec<PoseMismatch> {
+        let mesh = skin.geometry.and_then(|geometry| {
+            graph.parent_objects(geometry).find(|parent| parent.class == "Model").map(|model| model.id)
+        });
+        let mut mismatches = Vec::new();
+        for cluster in &skin.clusters {
+            if let Some(bone) = cluster.bone {
+                mismatches.extend(self.compare(cluster.id, bone, &cluster.transform_link, PoseMatrix::TransformLink, tolerance));
+            }
+        }
+        if let Some(mesh) = mesh {
+            let mut transforms: Vec<&[f64; 16]> = Vec::new();
+            for cluster in &skin.clusters {
+                if transforms.contains(&&cluster.transform) {
+                    continue;
+                }
+                transforms.push(&cluster.transform);
+                let mismatch = self.compare(cluster.id, mesh, &cluster.transform, PoseMatrix::Transform, tolerance);
+                let missing = matches!(mismatch, Some(PoseMismatch { error: None, .. }));
+                mismatches.extend(mismatch);
+                if missing {
+                    break;
+                }
+            }
+        }
+        mismatches
+    }
+
+    /// Compares the matrix of the model with the cluster matrix.
+    fn compare(&self, cluster: i64, node: i64, expected: &[f64; 16], matrix: PoseMatrix, tolerance: f64) -> Option<PoseMismatch> {
+        let error = self.matrix(node).map(|actual| max_difference(actual, expected));
+        let mismatched = match error {
+            Some(error) => error > tolerance,
+            None => true,
+        };
+        if mismatched {
+            Some(PoseMismatch { cluster, node, matrix, error })
+        } else {
+            None
+        }
+    }
+}
+
+
+/// Cluster matrix compared with a pose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PoseMatrix {
+    /// `Transform`, compared with the mesh model.
+    Transform,
+    /// `TransformLink`, compared with the bone.
+    TransformLink,
+}
+
+/// A difference between a pose and a cluster.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoseMismatch {
+    /// ID of the cluster.
+    pub cluster: i64,
+    /// ID of the model.
+    pub node: i64,
+    /// The compared cluster matrix.
+    pub matrix: PoseMatrix,
+    /// Maximum element-wise difference, or `None` if the model is missing in the pose.
+    pub error: Option<f64>,
+}
+
+
+/// Returns all poses in the object graph.
+///
+/// Poses of types other than `BindPose` and `RestPose` are skipped with a warning.
+pub fn poses<'a>(graph: &ObjectGraph<'a>) -> Result<Vec<Pose<'a>>> {
+    let mut poses = Vec::new();
+    for object in graph.objects().filter(|object| object.class == "Pose") {
+        let ty = pose_type_name(object)?;
+        match PoseType::parse(&ty) {
+            Some(pose_type) => poses.push(Pose::from_object_of_type(object, pose_type)?),
+            None => warn!("Skipping pose {} of unknown type {:?}", object.id, ty),
+        }
+    }
+    Ok(poses)
+}
+
+/// Returns the bind pose with the most bones of the skin.
+///
+/// Returns `None` if no bind pose has any bone of the skin.
+pub fn skin_bind_pose<'p, 'a>(poses: &'p [Pose<'a>], skin: &Skin<'a>) -> Option<&'p Pose<'a>> {
+    poses.iter()
+        .filter(|pose| pose.pose_type == PoseType::BindPose)
+        .map(|pose| (pose, skin.clusters.iter().filter(|cluster| matches!(cluster.bone, Some(bone) if pose.contains(bone))).count()))
+        .filter(|&(_, count)| count > 0)
+        // `max_by_key` returns the last maximum, so reverse to prefer the first one.
+        .rev()
+        .max_by_key(|&(_, count)| count)
+        .map(|(pose, _)| pose)
+}
+
+
+/// Returns the `Type` of the pose, or its subclass if missing.
+fn pose_type_name(object: &Object) -> Result<String> {
+    Ok(child_string(object.node, "Type")?.unwrap_or_else(|| object.subclass.to_owned()))
+}
+
+fn max_difference(a: &[f64; 16], b: &[f64; 16]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b).abs()).fold(0.0, f64::max)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scene::matrix;
+    use property::Property;
+    use scene::skin::{Cluster, LinkMode, SkinningType};
+    use test_util::{connection, node, object, string, tree, TestNode};
+
+    fn cluster(id: i64, bone: Option<i64>, transform: [f64; 16], transform_link: [f64; 16]) -> Cluster<'static> {
+        Cluster {
+            id,
+            name: "",
+            indices: vec![],
+            weights: vec![],
+            transform,
+            transform_link,
+            transform_associate_model: None,
+            link_mode: LinkMode::Normalize,
+            bone,
+        }
+    }
+
+    fn skin(clusters: Vec<Cluster<'static>>) -> Skin<'static> {
+        Skin {
+            id: 30,
+            name: "",
+            skinning_type: SkinningType::Linear,
+            deform_accuracy: 50.0,
+            clusters,
+            geometry: Some(20),
+        }
+    }
+
+    fn pose(id: i64, pose_type: PoseType, nodes: &[(i64, [f64; 16])]) -> Pose<'static> {
+        Pose {
+            id,
+            name: "",
+            pose_type,
+            nodes: nodes.iter().map(|&(node, matrix)| PoseNode { node, matrix }).collect(),
+        }
+    }
+
+    #[test]
+    fn verify_skin() {
+        // Mesh model 10 with geometry 20.
+        let tree = tree(7400, &[
+            node("Objects", vec![], vec![
+                object("Model", 10, "Mesh\0\x01Model", "Mesh", vec![]),
+                object("Geometry", 20, "\0\x01Geometry", "Mesh", vec![]),
+            ]),
+            node("Connections", vec![], vec![connection(10, 0), connection(20, 10)]),
+        ]);
+        let graph = ObjectGraph::from_tree(&tree);
+        let mesh = matrix::translation([0.0, 1.0, 0.0]);
+        let bone = matrix::translation([2.0, 0.0, 0.0]);
+        let moved = matrix::translation([3.0, 0.0, 0.0]);
+
+        let skin = skin(vec![
+            cluster(40, Some(1), mesh, bone),
+            cluster(41, Some(2), mesh, moved),
+            cluster(42, Some(3), mesh, bone),
+            cluster(43, None, mesh, bone),
+        ]);
+        let bind_pose = pose(50, PoseType::BindPose, &[(10, mesh), (1, bone), (2, bone)]);
+        let mismatches = bind_pose.verify_skin(&graph, &skin, 1e-6);
+        assert_eq!(mismatches, [
+            PoseMismatch { cluster: 41, node: 2, matrix: PoseMatrix::TransformLink, error: Some(1.0) },
+            PoseMismatch { cluster: 42, node: 3, matrix: PoseMatrix::TransformLink, error: None },
+        ]);
+        assert_eq!(bind_pose.verify_skin(&graph, &skin, 1.0).len(), 1);
+
+        // The mesh is compared once, not for each cluster.
+        let moved_mesh = pose(51, PoseType::BindPose, &[(10, moved), (1, bone), (2, moved), (3, bone)]);
+        assert_eq!(moved_mesh.verify_skin(&graph, &skin, 1e-6), [
+            PoseMismatch { cluster: 40, node: 10, matrix: PoseMatrix::Transform, error: Some(3.0) },
+        ]);
+        let no_mesh = pose(52, PoseType::BindPose, &[(1, bone), (2, moved), (3, bone)]);
+        assert_eq!(no_mesh.verify_skin(&graph, &skin, 1e-6), [
+            PoseMismatch { cluster: 40, node: 10, matrix: PoseMatrix::Transform, error: None },
+        ]);
+
+        // Distinct `Transform`s are compared separately.
+        let skin = self::skin(vec![
+            cluster(40, None, mesh, bone),
+            cluster(41, None, moved, bone),
+            cluster(42, None, mesh, bone),
+        ]);
+        assert_eq!(bind_pose.verify_skin(&graph, &skin, 1e-6), [
+            PoseMismatch { cluster: 41, node: 10, matrix: PoseMatrix::Transform, error: Some(3.0) },
+        ]);
+    }
+
+    #[test]
+    fn skin_bind_pose_prefers_first() {
+        let m = matrix::IDENTITY;
+        let skin = skin(vec![cluster(40, Some(1), m, m), cluster(41, Some(2), m, m), cluster(42, Some(3), m, m)]);
+        let poses = [
+            pose(50, PoseType::BindPose, &[(1, m)]),
+            pose(51, PoseType::RestPose, &[(1, m), (2, m), (3, m)]),
+            pose(52, PoseType::BindPose, &[(1, m), (2, m)]),
+            pose(53, PoseType::BindPose, &[(2, m), (3, m), (4, m)]),
+            pose(54, PoseType::BindPose, &[(1, m), (3, m)]),
+        ];
+        assert_eq!(skin_bind_pose(&poses, &skin).map(|pose| pose.id), Some(52));
+        assert_eq!(skin_bind_pose(&poses[..2], &skin).map(|pose| pose.id), Some(50));
+        assert_eq!(skin_bind_pose(&poses[1..2], &skin).map(|pose| pose.id), None);
+    }
+
+    fn pose_node(id: i64, translation: Option<[f64; 3]>) -> TestNode<'static> {
+        let mut children = vec![node("Node", vec![Property::I64(id)], vec![])];
+        if let Some(translation) = translation {
+            let matrix = matrix::translation(translation);
+            children.push(node("Matrix", vec![Property::VecF64(matrix.to_vec())], vec![]));
+        }
+        node("PoseNode", vec![], children)
+    }
+
+    fn pose_object(id: i64, subclass: &'static str, pose_type: Option<&'static str>, count: i32, nodes: Vec<TestNode<'static>>) -> TestNode<'static> {
+        let mut children = Vec::new();
+        if let Some(pose_type) = pose_type {
+            children.push(node("Type", vec![string(pose_type)], vec![]));
+        }
+        children.push(node("Version", vec![Property::I32(100)], vec![]));
+        children.push(node("NbPoseNodes", vec![Property::I32(count)], vec![]));
+        children.extend(nodes);
+        object("Pose", id, "\0\x01Pose", subclass, children)
+    }
+
+    /// Returns a skinned mesh model (10), a mesh model without skins (11), a `LimbNode` (50), a
+    /// `Root` (51) and a `Null` (52), with the given poses.
+    fn posed(poses: Vec<TestNode<'static>>) -> Vec<TestNode<'static>> {
+        let mut objects = vec![
+            object("Model", 10, "Body\0\x01Model", "Mesh", vec![]),
+            object("Geometry", 20, "\0\x01Geometry", "Mesh", vec![]),
+            object("Deformer", 30, "\0\x01Deformer", "Skin", vec![]),
+            object("Deformer", 40, "\0\x01SubDeformer", "Cluster", vec![]),
+            object("Model", 11, "Prop\0\x01Model", "Mesh", vec![]),
+            object("Geometry", 21, "\0\x01Geometry", "Mesh", vec![]),
+            object("Model", 50, "Hips\0\x01Model", "LimbNode", vec![]),
+            object("Model", 51, "Root\0\x01Model", "Root", vec![]),
+            object("Model", 52, "Locator\0\x01Model", "Null", vec![]),
+        ];
+        objects.extend(poses);
+        vec![
+            node("Objects", vec![], objects),
+            node("Connections", vec![], vec![
+                connection(10, 0),
+                connection(20, 10),
+                connection(30, 20),
+                connection(40, 30),
+                connection(50, 40),
+                connection(11, 0),
+                connection(21, 11),
+                connection(51, 0),
+                connection(50, 51),
+                connection(52, 0),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn poses_from_tree() {
+        let tree = tree(7400, &posed(vec![
+            pose_object(60, "BindPose", Some("BindPose"), 6, vec![
+                pose_node(10, Some([0.0, 1.0, 0.0])),
+                pose_node(11, Some([0.0; 3])),
+                pose_node(50, Some([2.0, 0.0, 0.0])),
+                pose_node(51, Some([0.0; 3])),
+                pose_node(52, Some([0.0; 3])),
+                // A model missing in the scene.
+                pose_node(99, Some([0.0; 3])),
+            ]),
+            // `Type` falls back to the subclass, and a wrong `NbPoseNodes` is only warned.
+            pose_object(61, "RestPose", None, 3, vec![pose_node(50, Some([1.0, 0.0, 0.0]))]),
+            pose_object(62, "Unknown", Some("Unknown"), 0, vec![]),
+        ]));
+        let graph = ObjectGraph::from_tree(&tree);
+        let poses = poses(&graph).unwrap();
+        assert_eq!(poses.iter().map(|pose| (pose.id, pose.pose_type)).collect::<Vec<_>>(), [
+            (60, PoseType::BindPose),
+            (61, PoseType::RestPose),
+        ]);
+        assert!(Pose::from_object(graph.object(62).unwrap()).is_err());
+
+        let bind_pose = &poses[0];
+        assert_eq!(bind_pose.nodes.iter().map(|pose_node| pose_node.node).collect::<Vec<_>>(), [10, 11, 50, 51, 52, 99]);
+        assert_eq!(bind_pose.matrix(50), Some(&matrix::translation([2.0, 0.0, 0.0])));
+        assert_eq!(bind_pose.matrix(30), None);
+        assert_eq!(poses[1].nodes, [PoseNode { node: 50, matrix: matrix::translation([1.0, 0.0, 0.0]) }]);
+
+        assert_eq!(bind_pose.skeleton_nodes(&graph), [50, 51]);
+        let meshes = bind_pose.skinned_meshes(&graph).unwrap();
+        assert_eq!(meshes.len(), 1);
+        let (mesh, ref skins) = meshes[0];
+        assert_eq!(mesh, 10);
+        assert_eq!(skins.iter().map(|skin| skin.id).collect::<Vec<_>>(), [30]);
+        assert_eq!(skins[0].clusters[0].bone, Some(50));
+        assert!(poses[1].skinned_meshes(&graph).unwrap().is_empty());
+    }
+
+    #[test]
+    fn pose_node_without_matrix() {
+        let file = tree(7400, &posed(vec![
+            pose_object(60, "BindPose", Some("BindPose"), 2, vec![pose_node(10, Some([0.0; 3])), pose_node(50, None)]),
+        ]));
+        let graph = ObjectGraph::from_tree(&file);
+        assert!(Pose::from_object(graph.object(60).unwrap()).is_err());
+        assert!(poses(&graph).is_err());
+
+        let no_node = pose_object(60, "BindPose", Some("BindPose"), 1, vec![node("PoseNode", vec![], vec![])]);
+        let file = tree(7400, &posed(vec![no_node]));
+        let graph = ObjectGraph::from_tree(&file);
+        assert!(poses(&graph).is_err());
+    }
+}
@@ -0,0 +1,428 @@
+//! Contains typed `Properties70` records.
+//!
+//! Each child of `Properties70` node is a `P` node with the properties below:
+//!
+//! 1. name,
+//! 2. type name (such as `KString`, `Lcl Translation` and `ColorRGB`),
+//! 3. label (usually empty, or the same as the type name),
+//! 4. flags (such as `A`, `A+` and `AU`), and
+//! 5. a variable number of values.
+
+use std::iter::FromIterator;
+use std::slice;
+use error::{Error, Result};
+use property::Property;
+use scene::time::FbxTime;
+use tree::Node;
+
+
+/// Flags of a property record.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PropertyFlags {
+    /// The property is animatable (`A`).
+    pub animatable: bool,
+    /// The property is animated (`+`, usually follows `A`).
+    pub animated: bool,
+    /// The property is user-defined (`U`).
+    pub user_defined: bool,
+    /// The property is hidden (`H`).
+    pub hidden: bool,
+    /// The property is locked (`L`, optionally followed by a bitmask of locked members).
+    pub locked: bool,
+    /// The property is muted (`M`, optionally followed by a bitmask of muted members).
+    pub muted: bool,
+}
+
+impl PropertyFlags {
+    /// Parses the flag string.
+    ///
+    /// Unknown characters are ignored.
+    pub fn parse(flags: &str) -> Self {
+        let mut result = PropertyFlags::default();
+        for c in flags.chars() {
+            match c {
+                'A' => result.animatable = true,
+                '+' => result.animated = true,
+                'U' => result.user_defined = true,
+                'H' => result.hidden = true,
+                'L' => result.locked = true,
+                'M' => result.muted = true,
+                // Digits of lock or mute bitmask.
+                '0'..='9' => {},
+                _ => warn!("Unknown property flag: {:?}", c),
+            }
+        }
+        result
+    }
+}
+
+
+/// Value of a property record.
+#[derive(Debug, Clone)]
+pub enum PropertyValue<'a> {
+    /// No value.
+    ///
+    /// Property of `Compound` type, or an unknown type without values.
+    None,
+    /// Boolean (`bool`, `Bool` and `Visibility Inheritance`).
+    Bool(bool),
+    /// Integer (`int`, `Integer`, `enum`, `ULongLong` and so on).
+    Integer(i64),
+    /// Scalar number (`double`, `Number`, `float`, `FieldOfView` and so on).
+    Number(f64),
+    /// 3D vector (`Vector3D`, `Vector`, `Lcl Translation`, `Lcl Rotation` and `Lcl Scaling`).
+    Vector3([f64; 3]),
+    /// RGB color (`ColorRGB` and `Color`).
+    Color([f64; 3]),
+    /// RGBA color (`ColorAndAlpha`).
+    ColorAndAlpha([f64; 4]),
+    /// String (`KString`, `DateTime`, `Url`, `XRefUrl` and so on).
+    String(&'a str),
+    /// Time (`KTime`).
+    Time(FbxTime),
+    /// Reference to objects (`object`).
+    ///
+    /// The property has no value, and referenced objects are connected to the property by `OP`
+    /// connections.
+    ObjectReference,
+    /// Values of unknown type.
+    Other(Vec<Property<'a>>),
+}
+
+impl<'a> PropertyValue<'a> {
+    /// Decodes the values of the given type.
+    ///
+    /// If the values do not fit the type (e.g. `ColorRGB` with four values), the type is
+    /// guessed from the values as for unknown types, and a warning is logged.
+    pub fn decode(type_name: &str, values: Vec<Property<'a>>) -> Self {
+        PropertyValue::decode_named(type_name, values, None)
+    }
+
+    /// Decodes the values of the given type, with the property name for the warning.
+    fn decode_named(type_name: &str, values: Vec<Property<'a>>, name: Option<&str>) -> Self {
+        match PropertyValue::decode_typed(type_name, &values) {
+            Some(Some(value)) => value,
+            Some(None) => {
+                match name {
+                    Some(name) => warn!("Unexpected values for property type `{}`: {:?} (property `{}`)", type_name, values, name),
+                    None => warn!("Unexpected values for property type `{}`: {:?}", type_name, values),
+                }
+                PropertyValue::guess(values)
+            },
+            None => PropertyValue::guess(values),
+        }
+    }
+
+    /// Decodes the values of the known type.
+    ///
+    /// Returns `None` for unknown types, and `Some(None)` if the values do not fit the type.
+    fn decode_typed(type_name: &str, values: &[Property<'a>]) -> Option<Option<Self>> {
+        fn numbers(values: &[Property], len: usize) -> Option<[f64; 4]> {
+            if values.len() != len {
+                return None;
+            }
+            let mut result = [0.0; 4];
+            for (dest, value) in result.iter_mut().zip(values) {
+                *dest = as_number(value)?;
+            }
+            Some(result)
+        }
+        // Returns the only value.
+        let single = || if values.len() == 1 { values.first() } else { None };
+        Some(match type_name {
+            "Compound" => Some(PropertyValue::None),
+            "object" => Some(PropertyValue::ObjectReference),
+            "bool" | "Bool" | "Visibility Inheritance" => single().and_then(as_integer).map(|v| PropertyValue::Bool(v != 0)),
+            "int" | "Integer" | "enum" | "ULongLong" => single().and_then(as_integer).map(PropertyValue::Integer),
+            "double" | "Number" | "float" | "Float" | "Real" | "FieldOfView" | "FieldOfViewX" | "FieldOfViewY" | "Visibility" => {
+                single().and_then(as_number).map(PropertyValue::Number)
+            },
+            "KTime" => single().and_then(Property::as_i64).map(|v| PropertyValue::Time(FbxTime(v))),
+            "Vector3D" | "Vector" | "Lcl Translation" | "Lcl Rotation" | "Lcl Scaling" => {
+                numbers(values, 3).map(|v| PropertyValue::Vector3([v[0], v[1], v[2]]))
+            },
+            "ColorRGB" | "Color" => numbers(values, 3).map(|v| PropertyValue::Color([v[0], v[1], v[2]])),
+            "ColorAndAlpha" => numbers(values, 4).map(PropertyValue::ColorAndAlpha),
+            "KString" | "DateTime" | "Url" | "XRefUrl" | "charptr" => single().and_then(Property::get_string).map(PropertyValue::String),
+            _ => return None,
+        })
+    }
+
+    /// Guesses the value type from the values.
+    fn guess(mut values: Vec<Property<'a>>) -> Self {
+        match values.len() {
+            0 => PropertyValue::None,
+            1 => match values[0] {
+                Property::Bool(v) => PropertyValue::Bool(v),
+                Property::I16(_) | Property::I32(_) | Property::I64(_) => PropertyValue::Integer(values[0].as_i64().unwrap()),
+                Property::F32(_) | Property::F64(_) => PropertyValue::Number(values[0].as_f64().unwrap()),
+                Property::String(Ok(v)) => PropertyValue::String(v),
+                _ => PropertyValue::Other(values.pop().into_iter().collect()),
+            },
+            3 if values.iter().all(|v| as_number(v).is_some()) => {
+                let v: Vec<f64> = values.iter().filter_map(as_number).collect();
+                PropertyValue::Vector3([v[0], v[1], v[2]])
+            },
+            _ => PropertyValue::Other(values),
+        }
+    }
+
+    /// Returns the value as a boolean.
+    ///
+    /// Integers are converted (non-zero is `true`).
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            PropertyValue::Bool(v) => Some(v),
+            PropertyValue::Integer(v) => Some(v != 0),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as an integer.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            PropertyValue::Integer(v) => Some(v),
+            PropertyValue::Bool(v) => Some(v as i64),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a number.
+    ///
+    /// Integers are converted.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            PropertyValue::Number(v) => Some(v),
+            PropertyValue::Integer(v) => Some(v as f64),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a 3D vector.
+    ///
+    /// RGB colors are also returned.
+    pub fn as_vector3(&self) -> Option<[f64; 3]> {
+        match *self {
+            PropertyValue::Vector3(v) | PropertyValue::Color(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a string.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match *self {
+            PropertyValue::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a time.
+    ///
+    /// Integers are converted as ticks.
+    pub fn as_time(&self) -> Option<FbxTime> {
+        match *self {
+            PropertyValue::Time(v) => Some(v),
+            PropertyValue::Integer(v) => Some(FbxTime(v)),
+            _ => None,
+        }
+    }
+}
+
+fn as_integer(value: &Property) -> Option<i64> {
+    match *value {
+        Property::Bool(v) => Some(v as i64),
+        ref v => v.as_i64(),
+    }
+}
+
+fn as_number(value: &Property) -> Option<f64> {
+    value.as_f64().or_else(|| value.as_i64().map(|v| v as f64))
+}
+
+
+/// A `P` record in `Properties70`.
+#[derive(Debug, Clone)]
+pub struct PropertyRecord<'a> {
+    /// Property name.
+    pub name: &'a str,
+    /// Type name.
+    pub type_name: &'a str,
+    /// Label.
+    pub label: &'a str,
+    /// Flags.
+    pub flags: PropertyFlags,
+    /// Value.
+    pub value: PropertyValue<'a>,
+}
+
+impl<'a> PropertyRecord<'a> {
+    /// Decodes the `P` node.
+    pub fn from_node(node: &'a Node) -> Result<Self> {
+        let mut props = node.properties.iter();
+        let mut header = [""; 4];
+        for (i, field) in header.iter_mut().enumerate() {
+            *field = match props.next() {
+                Some(Property::String(Ok(v))) => v,
+                prop => return Err(Error::UnexpectedValue(format!("Expected string as property record field #{}, but got {:?}", i, prop))),
+            };
+        }
+        let [name, type_name, label, flags] = header;
+        Ok(PropertyRecord {
+            name,
+            type_name,
+            label,
+            flags: PropertyFlags::parse(flags),
+            value: PropertyValue::decode_named(type_name, props.collect(), Some(name)),
+        })
+    }
+}
+
+
+/// Property records in a `Properties70` node.
+#[derive(Debug, Default)]
+pub struct Properties70<'a> {
+    records: Vec<PropertyRecord<'a>>,
+}
+
+impl<'a> Properties70<'a> {
+    /// Decodes the `Properties70` node.
+    ///
+    /// Children other than `P` are ignored, and `P` records with invalid header fields (such as
+    /// non-UTF-8 names) are logged and skipped.
+    pub fn from_node(node: &'a Node) -> Result<Self> {
+        let records = node.children_by_name("P").filter_map(|p| match PropertyRecord::from_node(p) {
+            Ok(record) => Some(record),
+            Err(err) => {
+                warn!("Skipping invalid property record in `{}`: {}", node.name, err);
+                None
+            },
+        }).collect();
+        Ok(Properties70 { records })
+    }
+
+    /// Decodes the `Properties70` child of the given node.
+    ///
+    /// Returns empty records if the node has no `Properties70` child.
+    pub fn from_parent(node: &'a Node) -> Result<Self> {
+        match node.first_child_by_name("Properties70") {
+            Some(properties70) => Properties70::from_node(properties70),
+            None => Ok(Properties70::default()),
+        }
+    }
+
+    /// Returns the record with the given name.
+    pub fn get(&self, name: &str) -> Option<&PropertyRecord<'a>> {
+        self.records.iter().find(|record| record.name == name)
+    }
+
+    /// Returns the value of the record with the given name.
+    pub fn value(&self, name: &str) -> Option<&PropertyValue<'a>> {
+        self.get(name).map(|record| &record.value)
+    }
+
+    /// Returns an iterator of the records.
+    pub fn iter(&self) -> slice::Iter<'_, PropertyRecord<'a>> {
+        self.records.iter()
+    }
+
+    /// Returns the number of the records.
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns whether there are no records.
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl<'a> FromIterator<PropertyRecord<'a>> for Properties70<'a> {
+    fn from_iter<I: IntoIterator<Item = PropertyRecord<'a>>>(iter: I) -> Self {
+        Properties70 { records: iter.into_iter().collect() }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use property::Property;
+    use scene::time::FbxTime;
+    use test_util::{node, p, tree};
+    use super::{Properties70, PropertyFlags, PropertyValue};
+
+
+    #[test]
+    fn decode_typed() {
+        let typed = |type_name: &str, values: &[Property<'static>]| format!("{:?}", PropertyValue::decode_typed(type_name, values));
+        assert_eq!(typed("Compound", &[]), "Some(Some(None))");
+        assert_eq!(typed("object", &[]), "Some(Some(ObjectReference))");
+        assert_eq!(typed("bool", &[Property::I32(1)]), "Some(Some(Bool(true)))");
+        assert_eq!(typed("Visibility Inheritance", &[Property::Bool(false)]), "Some(Some(Bool(false)))");
+        assert_eq!(typed("enum", &[Property::I16(2)]), "Some(Some(Integer(2)))");
+        assert_eq!(typed("ULongLong", &[Property::I64(1 << 40)]), "Some(Some(Integer(1099511627776)))");
+        assert_eq!(typed("double", &[Property::F64(0.5)]), "Some(Some(Number(0.5)))");
+        // Integers are accepted as numbers.
+        assert_eq!(typed("FieldOfView", &[Property::I32(45)]), "Some(Some(Number(45.0)))");
+        assert_eq!(typed("KTime", &[Property::I64(46_186_158_000)]), format!("Some(Some({:?}))", PropertyValue::Time(FbxTime(46_186_158_000))));
+        assert_eq!(typed("Lcl Translation", &[Property::F64(1.0), Property::F32(2.0), Property::I32(3)]), "Some(Some(Vector3([1.0, 2.0, 3.0])))");
+        assert_eq!(typed("ColorRGB", &[Property::F64(1.0), Property::F64(0.5), Property::F64(0.0)]), "Some(Some(Color([1.0, 0.5, 0.0])))");
+        assert_eq!(typed("ColorAndAlpha", &[Property::F64(1.0), Property::F64(0.5), Property::F64(0.0), Property::F64(0.25)]), "Some(Some(ColorAndAlpha([1.0, 0.5, 0.0, 0.25])))");
+        assert_eq!(typed("KString", &[Property::String(Ok("Y"))]), "Some(Some(String(\"Y\")))");
+        // Values which do not fit the type.
+        assert_eq!(typed("ColorRGB", &[Property::F64(1.0), Property::F64(0.5), Property::F64(0.0), Property::F64(1.0)]), "Some(None)");
+        assert_eq!(typed("int", &[Property::String(Ok("1"))]), "Some(None)");
+        assert_eq!(typed("double", &[]), "Some(None)");
+        // Unknown type.
+        assert_eq!(typed("MyType", &[Property::I32(1)]), "None");
+    }
+
+    #[test]
+    fn decode_falls_back_to_guess() {
+        // Four values do not fit `ColorRGB`, and are kept as they are.
+        let values = vec![Property::F64(1.0), Property::F64(0.5), Property::F64(0.0), Property::F64(1.0)];
+        match PropertyValue::decode("ColorRGB", values) {
+            PropertyValue::Other(values) => assert_eq!(values.len(), 4),
+            value => panic!("Expected `Other`, but got {:?}", value),
+        }
+        // `double` with a string value is guessed as a string.
+        assert_eq!(PropertyValue::decode("double", vec![Property::String(Ok("0.5"))]).as_str(), Some("0.5"));
+        // Unknown types are guessed.
+        assert_eq!(PropertyValue::decode("MyVector", vec![Property::F64(1.0), Property::I32(2), Property::F32(3.0)]).as_vector3(), Some([1.0, 2.0, 3.0]));
+        assert_eq!(PropertyValue::decode("MyInt", vec![Property::I16(7)]).as_i64(), Some(7));
+        assert_eq!(PropertyValue::decode("MyBool", vec![Property::Bool(true)]).as_bool(), Some(true));
+        assert!(matches!(PropertyValue::decode("MyNone", vec![]), PropertyValue::None));
+    }
+
+    #[test]
+    fn parse_flags() {
+        assert_eq!(PropertyFlags::parse(""), PropertyFlags::default());
+        let flags = PropertyFlags::parse("A+U");
+        assert!(flags.animatable && flags.animated && flags.user_defined);
+        assert!(!flags.hidden && !flags.locked && !flags.muted);
+        // Bitmasks of locked and muted members are ignored.
+        let flags = PropertyFlags::parse("AHL7M3");
+        assert!(flags.animatable && flags.hidden && flags.locked && flags.muted);
+        assert!(!flags.animated && !flags.user_defined);
+        // Unknown characters are ignored.
+        assert_eq!(PropertyFlags::parse("AX"), PropertyFlags::parse("A"));
+    }
+
+    #[test]
+    fn skip_invalid_records() {
+        let tree = tree(7400, &[node("Properties70", vec![], vec![
+            p("UpAxis", "int", "", vec![Property::I32(1)]),
+            // Non-UTF-8 name.
+            node("P", vec![Property::String(Err(b"\xff")), Property::String(Ok("int")), Property::String(Ok("")), Property::String(Ok("")), Property::I32(2)], vec![]),
+            // Non-string type name.
+            node("P", vec![Property::String(Ok("Broken")), Property::I32(0)], vec![]),
+            p("Lcl Translation", "Lcl Translation", "A+", vec![Property::F64(1.0), Property::F64(2.0), Property::F64(3.0)]),
+        ])]);
+        let properties = Properties70::from_node(&tree.children[0]).unwrap();
+        assert_eq!(properties.iter().map(|record| record.name).collect::<Vec<_>>(), ["UpAxis", "Lcl Translation"]);
+        assert_eq!(properties.value("UpAxis").and_then(PropertyValue::as_i64), Some(1));
+        let record = properties.get("Lcl Translation").unwrap();
+        assert!(record.flags.animated);
+        assert_eq!(record.value.as_vector3(), Some([1.0, 2.0, 3.0]));
+        assert!(properties.get("Broken").is_none());
+    }
+}
@@ -0,0 +1,454 @@
+//! Contains skin deformers and bone weights.
+//!
+//! A skinned mesh has the following connections:
+//!
+//! ```text
+//! Geometry <- Deformer (Skin) <- Deformer (Cluster) <- Model (LimbNode)
+//! ```
+//!
+//! Each `Cluster` has sparse weights of one bone: `Indexes` (control point indices) and
+//! `Weights`.
+
+use error::{Error, Result};
+use scene::{check_deformer, child_matrix, child_property, child_string, child_vec_f64, child_vec_i32, is_deformer};
+use scene::object_graph::{Object, ObjectGraph};
+
+
+/// 4x4 identity matrix in column-major order.
+const IDENTITY: [f64; 16] = [
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 1.0, 0.0,
+    0.0, 0.0, 0.0, 1.0,
+];
+
+
+/// Skinning type of a skin deformer (`SkinningType`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SkinningType {
+    /// Linear blend skinning.
+    Linear,
+    /// Dual quaternion skinning.
+    DualQuaternion,
+    /// Blend of linear and dual quaternion skinning.
+    Blend,
+    /// Rigid skinning.
+    Rigid,
+}
+
+impl SkinningType {
+    /// Parses the skinning type.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "Linear" => Some(SkinningType::Linear),
+            "DualQuaternion" => Some(SkinningType::DualQuaternion),
+            "Blend" => Some(SkinningType::Blend),
+            "Rigid" => Some(SkinningType::Rigid),
+            _ => None,
+        }
+    }
+}
+
+
+/// Link mode of a cluster (`Mode`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkMode {
+    /// Weights are normalized (`"Normalize"`).
+    Normalize,
+    /// Weights are added to the original position (`"Additive"`).
+    Additive,
+    /// Weights sum up to one (`"Total1"`).
+    TotalOne,
+}
+
+impl LinkMode {
+    /// Parses the link mode.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "Normalize" => Some(LinkMode::Normalize),
+            "Additive" => Some(LinkMode::Additive),
+            "Total1" | "TotalOne" => Some(LinkMode::TotalOne),
+            _ => None,
+        }
+    }
+}
+
+
+/// `Cluster` sub-deformer, which binds control points to a bone.
+#[derive(Debug, Clone)]
+pub struct Cluster<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Cluster name.
+    pub name: &'a str,
+    /// Indices of control points influenced by the bone (`Indexes`).
+    pub indices: Vec<i32>,
+    /// Weights for the control points (`Weights`).
+    pub weights: Vec<f64>,
+    /// Global transform of the mesh at the binding time (`Transform`).
+    pub transform: [f64; 16],
+    /// Global transform of the bone at the binding time (`TransformLink`).
+    pub transform_link: [f64; 16],
+    /// `TransformAssociateModel`, used with the additive link mode.
+    pub transform_associate_model: Option<[f64; 16]>,
+    /// Link mode.
+    pub link_mode: LinkMode,
+    /// ID of the bone (`LimbNode` or other `Model`) connected to the cluster.
+    pub bone: Option<i64>,
+}
+
+impl<'a> Cluster<'a> {
+    /// Decodes the `Cluster` deformer and finds the bone connected to it.
+    pub fn from_object(object: &Object<'a>, graph: &ObjectGraph<'a>) -> Result<Self> {
+        check_deformer(object, "Cluster")?;
+        let node = object.node;
+        let indices = child_vec_i32(node, "Indexes")?.unwrap_or_default();
+        let weights = child_vec_f64(node, "Weights")?.unwrap_or_default();
+        if indices.len() != weights.len() {
+            return Err(Error::DataError(format!("Cluster {} has {} indices but {} weights", object.id, indices.len(), weights.len())));
+        }
+        if let Some(&index) = indices.iter().find(|&&index| index < 0) {
+            return Err(Error::DataError(format!("Cluster {} has a negative control point index {}", object.id, index)));
+        }
+        let link_mode = match child_string(node, "Mode")? {
+            Some(mode) => LinkMode::parse(&mode)
+                .ok_or_else(|| Error::UnexpectedValue(format!("Unknown cluster link mode: {:?}", mode)))?,
+            None => LinkMode::Normalize,
+        };
+        let bone = graph.child_objects(object.id)
+            .find(|child| child.class == "Model")
+            .map(|bone| bone.id);
+        Ok(Cluster {
+            id: object.id,
+            name: object.name,
+            indices,
+            weights,
+            transform: child_matrix(node, "Transform")?.unwrap_or(IDENTITY),
+            transform_link: child_matrix(node, "TransformLink")?.unwrap_or(IDENTITY),
+            transform_associate_model: child_matrix(node, "TransformAssociateModel")?,
+            link_mode,
+            bone,
+        })
+    }
+}
+
+
+/// `Skin` deformer.
+#[derive(Debug, Clone)]
+pub struct Skin<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Skin name.
+    pub name: &'a str,
+    /// Skinning type.
+    pub skinning_type: SkinningType,
+    /// `Link_DeformAcuracy` (sic).
+    pub deform_accuracy: f64,
+    /// Clusters in order of connection.
+    pub clusters: Vec<Cluster<'a>>,
+    /// ID of the geometry deformed by the skin.
+    pub geometry: Option<i64>,
+}
+
+impl<'a> Skin<'a> {
+    /// Decodes the `Skin` deformer with its clusters.
+    pub fn from_object(object: &Object<'a>, graph: &ObjectGraph<'a>) -> Result<Self> {
+        check_deformer(object, "Skin")?;
+        let node = object.node;
+        let skinning_type = match child_string(node, "SkinningType")? {
+            Some(ty) => SkinningType::parse(&ty)
+                .ok_or_else(|| Error::UnexpectedValue(format!("Unknown skinning type: {:?}", ty)))?,
+            None => SkinningType::Linear,
+        };
+        let deform_accuracy = child_property(node, "Link_DeformAcuracy").and_then(|prop| prop.as_f64()).unwrap_or(50.0);
+        let mut clusters = Vec::new();
+        for child in graph.child_objects(object.id) {
+            if is_deformer(child, "Cluster") {
+                clusters.push(Cluster::from_object(child, graph)?);
+            }
+        }
+        let geometry = graph.parent_objects(object.id)
+            .find(|parent| parent.class == "Geometry")
+            .map(|geometry| geometry.id);
+        Ok(Skin {
+            id: object.id,
+            name: object.name,
+            skinning_type,
+            deform_accuracy,
+            clusters,
+            geometry,
+        })
+    }
+
+    /// Returns influences for each control point.
+    ///
+    /// `num_control_points` is the number of control points of the geometry.
+    /// Influences with zero weight are dropped, and each list is sorted in descending order of
+    /// weights.
+    pub fn influences(&self, num_control_points: usize, options: &InfluenceOptions) -> Result<Vec<Vec<Influence>>> {
+        let mut influences = vec![Vec::new(); num_control_points];
+        for (cluster_index, cluster) in self.clusters.iter().enumerate() {
+            for (&index, &weight) in cluster.indices.iter().zip(&cluster.weights) {
+                if weight == 0.0 {
+                    continue;
+                }
+                let list = influences.get_mut(index as usize).ok_or_else(|| {
+                    Error::DataError(format!("Cluster {} refers to control point {}, but the geometry has {}", cluster.id, index, num_control_points))
+                })?;
+                list.push(Influence {
+                    cluster: cluster_index,
+                    bone: cluster.bone,
+                    weight,
+                });
+            }
+        }
+        for list in &mut influences {
+            list.sort_by(|a, b| b.weight.partial_cmp(&a.weight).unwrap_or(::std::cmp::Ordering::Equal));
+            if let Some(max) = options.max_influences {
+                list.truncate(max);
+            }
+            if options.normalize {
+                let sum: f64 = list.iter().map(|influence| influence.weight).sum();
+                if sum != 0.0 {
+                    for influence in list.iter_mut() {
+                        influence.weight /= sum;
+                    }
+                }
+            }
+        }
+        Ok(influences)
+    }
+}
+
+/// Returns skin deformers connected to the geometry.
+pub fn geometry_skins<'a>(graph: &ObjectGraph<'a>, geometry: i64) -> Result<Vec<Skin<'a>>> {
+    let mut skins = Vec::new();
+    for child in graph.child_objects(geometry) {
+        if is_deformer(child, "Skin") {
+            skins.push(Skin::from_object(child, graph)?);
+        }
+    }
+    Ok(skins)
+}
+
+
+/// Influence of a bone on a control point.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Influence {
+    /// Index of the cluster in [`Skin::clusters`](struct.Skin.html#structfield.clusters).
+    pub cluster: usize,
+    /// ID of the bone.
+    pub bone: Option<i64>,
+    /// Weight.
+    pub weight: f64,
+}
+
+
+/// Options for [`Skin::influences()`](struct.Skin.html#method.influences).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfluenceOptions {
+    /// Whether weights of each control point are scaled to sum up to one.
+    ///
+    /// Normalization is done after capping by `max_influences`.
+    /// Default is `false`.
+    pub normalize: bool,
+    /// Maximum number of influences per control point.
+    ///
+    /// Influences with the largest weights are kept.
+    /// Default is `None` (no limit).
+    pub max_influences: Option<usize>,
+}
+
+impl InfluenceOptions {
+    /// Creates a new options with default values.
+    pub fn new() -> Self {
+        InfluenceOptions {
+            normalize: false,
+            max_influences: None,
+        }
+    }
+
+    /// Sets the value of `normalize` and returns the options.
+    pub fn normalize(mut self, value: bool) -> Self {
+        self.normalize = value;
+        self
+    }
+
+    /// Sets the value of `max_influences` and returns the options.
+    pub fn max_influences(mut self, value: Option<usize>) -> Self {
+        self.max_influences = value;
+        self
+    }
+}
+
+impl Default for InfluenceOptions {
+    fn default() -> Self {
+        InfluenceOptions::new()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use property::Property;
+    use scene::object_graph::ObjectGraph;
+    use test_util::{connection, node, object, tree, TestNode};
+
+    fn cluster(id: i64, indices: Vec<i32>, weights: Vec<f64>) -> Cluster<'static> {
+        Cluster {
+            id,
+            name: "",
+            indices,
+            weights,
+            transform: IDENTITY,
+            transform_link: IDENTITY,
+            transform_associate_model: None,
+            link_mode: LinkMode::Normalize,
+            bone: Some(id + 100),
+        }
+    }
+
+    /// Returns a skin of four clusters, influencing control point 0 (all clusters) and 1.
+    fn skin() -> Skin<'static> {
+        Skin {
+            id: 1,
+            name: "",
+            skinning_type: SkinningType::Linear,
+            deform_accuracy: 50.0,
+            clusters: vec![
+                cluster(10, vec![0, 1], vec![0.1, 0.5]),
+                cluster(11, vec![0], vec![0.4]),
+                cluster(12, vec![0, 1], vec![0.2, 0.0]),
+                cluster(13, vec![0], vec![0.3]),
+            ],
+            geometry: None,
+        }
+    }
+
+    /// Returns cluster indices and weights of the influences.
+    fn summary(influences: &[Influence]) -> Vec<(usize, f64)> {
+        influences.iter().map(|influence| (influence.cluster, influence.weight)).collect()
+    }
+
+    #[test]
+    fn sorted_by_weight() {
+        let influences = skin().influences(3, &InfluenceOptions::new()).unwrap();
+        assert_eq!(summary(&influences[0]), [(1, 0.4), (3, 0.3), (2, 0.2), (0, 0.1)]);
+        assert_eq!(influences[0][0].bone, Some(111));
+        // Zero weights are dropped.
+        assert_eq!(summary(&influences[1]), [(0, 0.5)]);
+        assert!(influences[2].is_empty());
+    }
+
+    #[test]
+    fn capped_and_normalized() {
+        let options = InfluenceOptions::new().max_influences(Some(2)).normalize(true);
+        let influences = skin().influences(3, &options).unwrap();
+        // Weights are normalized after capping: 0.4 / 0.7 and 0.3 / 0.7.
+        let weights = summary(&influences[0]);
+        assert_eq!(weights.iter().map(|w| w.0).collect::<Vec<_>>(), [1, 3]);
+        assert!((weights[0].1 - 4.0 / 7.0).abs() < 1e-12);
+        assert!((weights[1].1 - 3.0 / 7.0).abs() < 1e-12);
+        assert_eq!(summary(&influences[1]), [(0, 1.0)]);
+
+        // Capping without normalization keeps the weights.
+        let options = InfluenceOptions::new().max_influences(Some(3));
+        let influences = skin().influences(3, &options).unwrap();
+        assert_eq!(summary(&influences[0]), [(1, 0.4), (3, 0.3), (2, 0.2)]);
+    }
+
+    #[test]
+    fn normalized_without_cap() {
+        let influences = skin().influences(3, &InfluenceOptions::new().normalize(true)).unwrap();
+        let sum: f64 = influences[0].iter().map(|influence| influence.weight).sum();
+        assert!((sum - 1.0).abs() < 1e-12);
+        assert!((influences[0][0].weight - 0.4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn control_point_out_of_range() {
+        assert!(skin().influences(1, &InfluenceOptions::new()).is_err());
+    }
+
+    fn matrix(translation: [f64; 3]) -> Vec<f64> {
+        let mut m = IDENTITY.to_vec();
+        m[12..15].copy_from_slice(&translation);
+        m
+    }
+
+    fn cluster_node(id: i64, indices: Vec<i32>, weights: Vec<f64>) -> TestNode<'static> {
+        object("Deformer", id, "\0\x01SubDeformer", "Cluster", vec![
+            node("Version", vec![Property::I32(100)], vec![]),
+            node("Indexes", vec![Property::VecI32(indices)], vec![]),
+            node("Weights", vec![Property::VecF64(weights)], vec![]),
+            node("Transform", vec![Property::VecF64(matrix([0.0, -1.0, 0.0]))], vec![]),
+            node("TransformLink", vec![Property::VecF64(matrix([0.0, 1.0, 0.0]))], vec![]),
+        ])
+    }
+
+    /// Returns `Geometry (20) <- Skin (30) <- Cluster (40, 41) <- LimbNode (50, 51)`.
+    fn skinned(cluster_41: TestNode<'static>) -> Vec<TestNode<'static>> {
+        vec![
+            node("Objects", vec![], vec![
+                object("Model", 10, "Body\0\x01Model", "Mesh", vec![]),
+                object("Geometry", 20, "\0\x01Geometry", "Mesh", vec![]),
+                object("Deformer", 30, "\0\x01Deformer", "Skin", vec![
+                    node("Version", vec![Property::I32(101)], vec![]),
+                    node("Link_DeformAcuracy", vec![Property::F64(25.0)], vec![]),
+                    node("SkinningType", vec![Property::String(Ok("DualQuaternion"))], vec![]),
+                ]),
+                cluster_node(40, vec![0, 2], vec![1.0, 0.25]),
+                cluster_41,
+                object("Model", 50, "Hips\0\x01Model", "LimbNode", vec![]),
+                object("Model", 51, "Spine\0\x01Model", "LimbNode", vec![]),
+            ]),
+            node("Connections", vec![], vec![
+                connection(10, 0),
+                connection(50, 0),
+                connection(51, 50),
+                connection(20, 10),
+                connection(30, 20),
+                connection(40, 30),
+                connection(41, 30),
+                connection(50, 40),
+                connection(51, 41),
+            ]),
+        ]
+    }
+
+    #[test]
+    fn skin_from_tree() {
+        let tree = tree(7400, &skinned(cluster_node(41, vec![1, 2], vec![1.0, 0.75])));
+        let graph = ObjectGraph::from_tree(&tree);
+        let skins = geometry_skins(&graph, 20).unwrap();
+        assert_eq!(skins.len(), 1);
+        let skin = &skins[0];
+        assert_eq!((skin.id, skin.geometry), (30, Some(20)));
+        assert_eq!(skin.skinning_type, SkinningType::DualQuaternion);
+        assert_eq!(skin.deform_accuracy, 25.0);
+        assert_eq!(skin.clusters.iter().map(|c| (c.id, c.bone)).collect::<Vec<_>>(), [(40, Some(50)), (41, Some(51))]);
+        let cluster = &skin.clusters[0];
+        assert_eq!(cluster.indices, [0, 2]);
+        assert_eq!(cluster.weights, [1.0, 0.25]);
+        assert_eq!(cluster.link_mode, LinkMode::Normalize);
+        assert_eq!(&cluster.transform[12..15], [0.0, -1.0, 0.0]);
+        assert_eq!(&cluster.transform_link[12..15], [0.0, 1.0, 0.0]);
+        assert_eq!(cluster.transform_associate_model, None);
+
+        let influences = skin.influences(3, &InfluenceOptions::new()).unwrap();
+        assert_eq!(influences[2].iter().map(|i| (i.bone, i.weight)).collect::<Vec<_>>(), [(Some(51), 0.75), (Some(50), 0.25)]);
+        // Other objects have no skins.
+        assert!(geometry_skins(&graph, 10).unwrap().is_empty());
+        assert!(Skin::from_object(graph.object(40).unwrap(), &graph).is_err());
+    }
+
+    #[test]
+    fn mismatched_weights() {
+        let tree = tree(7400, &skinned(cluster_node(41, vec![1, 2], vec![1.0])));
+        let graph = ObjectGraph::from_tree(&tree);
+        assert!(geometry_skins(&graph, 20).is_err());
+        assert!(Cluster::from_object(graph.object(41).unwrap(), &graph).is_err());
+        assert!(Cluster::from_object(graph.object(40).unwrap(), &graph).is_ok());
+    }
+}
@@ -0,0 +1,212 @@
+//! Contains per-material splitting of meshes.
+
+use error::{Error, Result};
+use scene::layer_element::material_layer;
+use scene::mesh::{Mesh, Triangle, TriangulationMethod};
+use scene::object_graph::{Object, ObjectGraph};
+use tree::Node;
+
+
+/// A part of a mesh with the same material.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Submesh {
+    /// Material index (slot) of the submesh.
+    pub material_index: usize,
+    /// ID of the material object, if the material index refers to a connected material.
+    pub material: Option<i64>,
+    /// Indices of polygons in the submesh.
+    pub polygons: Vec<usize>,
+    /// Triangles of the polygons.
+    pub triangles: Vec<Triangle>,
+    /// Polygon vertex indices of the triangles, flattened.
+    ///
+    /// These are indices into polygon vertices of the whole mesh, not into a vertex buffer of
+    /// the submesh.
+    /// Pass them to [`Welder`](../vertex_buffer/struct.Welder.html) to get vertex buffers and an
+    /// index buffer, using a welder per submesh or sharing a welder among submeshes.
+    pub polygon_vertices: Vec<u32>,
+}
+
+
+/// Returns material objects connected to the model, in order of material indices.
+pub fn model_materials<'g, 'a>(graph: &'g ObjectGraph<'a>, model: i64) -> Vec<&'g Object<'a>> {
+    graph.children_of(model)
+        .filter(|c| c.parent_property.is_none())
+        .filter_map(|c| graph.object(c.child))
+        .filter(|object| object.class == "Material")
+        .collect()
+}
+
+/// Returns the material index for each polygon of the mesh in the `Geometry` node.
+///
+/// If the geometry has no material layer, all polygons have material index `0`.
+pub fn polygon_materials(geometry: &Node, mesh: &Mesh) -> Result<Vec<usize>> {
+    match material_layer(geometry)? {
+        Some(layer) => layer.polygon_materials(mesh),
+        None => Ok(vec![0; mesh.num_polygons()]),
+    }
+}
+
+/// Splits the mesh into submeshes by material.
+///
+/// `polygon_materials` is the material index of each polygon, and `materials` is the material
+/// object IDs in order of material indices.
+/// Submeshes are sorted by material index, and empty submeshes are not created.
+pub fn split_by_material(mesh: &Mesh, polygon_materials: &[usize], materials: &[i64], method: TriangulationMethod) -> Vec<Submesh> {
+    let mut submeshes: Vec<Submesh> = Vec::new();
+    for triangle in mesh.triangulate(method) {
+        let material_index = polygon_materials.get(triangle.polygon).cloned().unwrap_or(0);
+        let pos = match submeshes.binary_search_by_key(&material_index, |submesh| submesh.material_index) {
+            Ok(pos) => pos,
+            Err(pos) => {
+                submeshes.insert(pos, Submesh {
+                    material_index,
+                    material: materials.get(material_index).cloned(),
+                    polygons: Vec::new(),
+                    triangles: Vec::new(),
+                    polygon_vertices: Vec::new(),
+                });
+                pos
+            },
+        };
+        let submesh = &mut submeshes[pos];
+        if submesh.polygons.last() != Some(&triangle.polygon) {
+            submesh.polygons.push(triangle.polygon);
+        }
+        submesh.polygon_vertices.extend(triangle.polygon_vertices.iter().map(|&v| v as u32));
+        submesh.triangles.push(triangle);
+    }
+    submeshes
+}
+
+/// Splits the mesh of the `Geometry` object by materials connected to the model.
+///
+/// The geometry should be connected to the model.
+/// Material indices out of range are logged, and submeshes of them have no material.
+/// Models without materials are expected to have material index `0` for all polygons, so
+/// only other indices are logged for them.
+pub fn split_model_mesh(graph: &ObjectGraph, model: i64, geometry: &Object, mesh: &Mesh, method: TriangulationMethod) -> Result<Vec<Submesh>> {
+    if !graph.children_of(model).any(|c| c.child == geometry.id) {
+        return Err(Error::DataError(format!("Geometry {} is not connected to model {}", geometry.id, model)));
+    }
+    let materials: Vec<i64> = model_materials(graph, model).iter().map(|material| material.id).collect();
+    let polygon_materials = polygon_materials(geometry.node, mesh)?;
+    if let Some((count, max)) = out_of_range_materials(&polygon_materials, materials.len()) {
+        warn!("{} polygons have material indices out of range (up to {}, model {} has {} materials)", count, max, model, materials.len());
+    }
+    Ok(split_by_material(mesh, &polygon_materials, &materials, method))
+}
+
+/// Returns the number of polygons with material indices out of range, and the maximum index.
+///
+/// Index `0` is valid even if there are no materials.
+fn out_of_range_materials(polygon_materials: &[usize], num_materials: usize) -> Option<(usize, usize)> {
+    let num_valid = ::std::cmp::max(num_materials, 1);
+    let (count, max) = polygon_materials.iter()
+        .filter(|&&index| index >= num_valid)
+        .fold((0, 0), |(count, max), &index| (count + 1, ::std::cmp::max(max, index)));
+    if count > 0 { Some((count, max)) } else { None }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use property::Property;
+    use scene::mesh::{Mesh, TriangulationMethod};
+    use scene::object_graph::ObjectGraph;
+    use test_util::{connection, node, object, tree, TestNode};
+    use super::{out_of_range_materials, split_by_material, split_model_mesh};
+
+    /// Returns a `Geometry` node with a triangle, a quad and a triangle.
+    fn geometry(materials: Option<Vec<i32>>) -> TestNode<'static> {
+        let mut children = vec![
+            node("Vertices", vec![Property::VecF64(vec![0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0])], vec![]),
+            node("PolygonVertexIndex", vec![Property::VecI32(vec![0, 1, -3, 0, 1, 2, -4, 1, 2, -4])], vec![]),
+        ];
+        if let Some(materials) = materials {
+            children.push(node("LayerElementMaterial", vec![Property::I32(0)], vec![
+                node("MappingInformationType", vec![Property::String(Ok("ByPolygon"))], vec![]),
+                node("Materials", vec![Property::VecI32(materials)], vec![]),
+            ]));
+        }
+        object("Geometry", 20, "\0\x01Geometry", "Mesh", children)
+    }
+
+    fn mesh() -> Mesh {
+        Mesh {
+            vertices: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]],
+            polygon_vertices: vec![0, 1, 2, 0, 1, 2, 3, 1, 2, 3],
+            polygon_starts: vec![0, 3, 7, 10],
+            edges: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn sorted_by_material_index() {
+        let submeshes = split_by_material(&mesh(), &[2, 0, 2], &[100, 101, 102], TriangulationMethod::Fan);
+        // Material 1 is not used, and has no submesh.
+        assert_eq!(submeshes.iter().map(|s| (s.material_index, s.material)).collect::<Vec<_>>(), [(0, Some(100)), (2, Some(102))]);
+        assert_eq!(submeshes[0].polygons, [1]);
+        assert_eq!(submeshes[0].polygon_vertices, [3, 4, 5, 3, 5, 6]);
+        assert_eq!(submeshes[1].polygons, [0, 2]);
+        assert_eq!(submeshes[1].polygon_vertices, [0, 1, 2, 7, 8, 9]);
+        assert_eq!(submeshes[1].triangles.len(), 2);
+    }
+
+    #[test]
+    fn material_index_out_of_range() {
+        let submeshes = split_by_material(&mesh(), &[0, 3, 0], &[100], TriangulationMethod::Fan);
+        assert_eq!(submeshes.iter().map(|s| (s.material_index, s.material)).collect::<Vec<_>>(), [(0, Some(100)), (3, None)]);
+
+        assert_eq!(out_of_range_materials(&[0, 3, 0, 1], 1), Some((2, 3)));
+        assert_eq!(out_of_range_materials(&[0, 1], 2), None);
+        // Index `0` is valid for models without materials.
+        assert_eq!(out_of_range_materials(&[0, 0], 0), None);
+        assert_eq!(out_of_range_materials(&[0, 1], 0), Some((1, 1)));
+    }
+
+    #[test]
+    fn model_mesh() {
+        let tree = tree(7400, &[
+            node("Objects", vec![], vec![
+                object("Model", 10, "Cube\0\x01Model", "Mesh", vec![]),
+                geometry(Some(vec![1, 0, 1])),
+                object("Material", 30, "Red\0\x01Material", "", vec![]),
+                object("Material", 31, "Blue\0\x01Material", "", vec![]),
+            ]),
+            node("Connections", vec![], vec![
+                connection(10, 0),
+                connection(20, 10),
+                connection(30, 10),
+                connection(31, 10),
+            ]),
+        ]);
+        let graph = ObjectGraph::from_tree(&tree);
+        let geometry = graph.object(20).unwrap();
+        let mesh = Mesh::from_object(geometry).unwrap();
+        let submeshes = split_model_mesh(&graph, 10, geometry, &mesh, TriangulationMethod::Fan).unwrap();
+        assert_eq!(submeshes.iter().map(|s| (s.material_index, s.material)).collect::<Vec<_>>(), [(0, Some(30)), (1, Some(31))]);
+        assert_eq!(submeshes[1].polygons, [0, 2]);
+    }
+
+    #[test]
+    fn model_mesh_without_materials() {
+        let tree = tree(7400, &[
+            node("Objects", vec![], vec![
+                object("Model", 10, "Cube\0\x01Model", "Mesh", vec![]),
+                object("Model", 11, "Other\0\x01Model", "Mesh", vec![]),
+                geometry(None),
+            ]),
+            node("Connections", vec![], vec![connection(20, 10)]),
+        ]);
+        let graph = ObjectGraph::from_tree(&tree);
+        let geometry = graph.object(20).unwrap();
+        let mesh = Mesh::from_object(geometry).unwrap();
+        let submeshes = split_model_mesh(&graph, 10, geometry, &mesh, TriangulationMethod::Fan).unwrap();
+        assert_eq!(submeshes.len(), 1);
+        assert_eq!(submeshes[0].material, None);
+        assert_eq!(submeshes[0].polygons, [0, 1, 2]);
+        // The geometry is not connected to model 11.
+        assert!(split_model_mesh(&graph, 11, geometry, &mesh, TriangulationMethod::Fan).is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! Contains FBX time types.
+
+/// Number of FBX time ticks in a second.
+pub const TICKS_PER_SECOND: i64 = 46_186_158_000;
+
+
+/// Time in FBX ticks (1/46186158000 second).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FbxTime(pub i64);
+
+impl FbxTime {
+    /// Creates a time from seconds.
+    pub fn from_seconds(seconds: f64) -> Self {
+        FbxTime((seconds * TICKS_PER_SECOND as f64).round() as i64)
+    }
+
+    /// Returns the time in seconds.
+    pub fn seconds(self) -> f64 {
+        self.0 as f64 / TICKS_PER_SECOND as f64
+    }
+
+    /// Returns the time in ticks.
+    pub fn ticks(self) -> i64 {
+        self.0
+    }
+}
+
+
+/// Time mode, which determines frame rate.
+///
+/// Values correspond to `FbxTime::EMode` in FBX SDK.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TimeMode {
+    /// Default frame rate of the application.
+    Default,
+    /// 120 frames/s.
+    Frames120,
+    /// 100 frames/s.
+    Frames100,
+    /// 60 frames/s.
+    Frames60,
+    /// 50 frames/s.
+    Frames50,
+    /// 48 frames/s.
+    Frames48,
+    /// 30 frames/s.
+    Frames30,
+    /// 30 frames/s with drop frames.
+    Frames30Drop,
+    /// NTSC drop frame, 29.97 frames/s.
+    NtscDropFrame,
+    /// NTSC full frame, 29.97 frames/s.
+    NtscFullFrame,
+    /// PAL, 25 frames/s.
+    Pal,
+    /// 24 frames/s.
+    Frames24,
+    /// 1000 frames/s.
+    Frames1000,
+    /// Film full frame, 23.976 frames/s.
+    FilmFullFrame,
+    /// Custom frame rate, specified by `CustomFrameRate` global setting.
+    Custom,
+    /// 96 frames/s.
+    Frames96,
+    /// 72 frames/s.
+    Frames72,
+    /// 59.94 frames/s.
+    Frames59_94,
+    /// 119.88 frames/s.
+    Frames119_88,
+}
+
+impl TimeMode {
+    /// Converts the FBX enum value to `TimeMode`.
+    pub fn from_i32(value: i32) -> Option<Self> {
+        use self::TimeMode::*;
+        Some(match value {
+            0 => Default,
+            1 => Frames120,
+            2 => Frames100,
+            3 => Frames60,
+            4 => Frames50,
+            5 => Frames48,
+            6 => Frames30,
+            7 => Frames30Drop,
+            8 => NtscDropFrame,
+            9 => NtscFullFrame,
+            10 => Pal,
+            11 => Frames24,
+            12 => Frames1000,
+            13 => FilmFullFrame,
+            14 => Custom,
+            15 => Frames96,
+            16 => Frames72,
+            17 => Frames59_94,
+            18 => Frames119_88,
+            _ => return None,
+        })
+    }
+
+    /// Returns the frame rate in frames per second.
+    ///
+    /// Returns `None` for `Default` and `Custom`, whose frame rates are not determined by the
+    /// mode itself.
+    pub fn frame_rate(self) -> Option<f64> {
+        use self::TimeMode::*;
+        Some(match self {
+            Default | Custom => return None,
+            Frames120 => 120.0,
+            Frames100 => 100.0,
+            Frames60 => 60.0,
+            Frames50 => 50.0,
+            Frames48 => 48.0,
+            Frames30 | Frames30Drop => 30.0,
+            NtscDropFrame | NtscFullFrame => 30000.0 / 1001.0,
+            Pal => 25.0,
+            Frames24 => 24.0,
+            Frames1000 => 1000.0,
+            FilmFullFrame => 24000.0 / 1001.0,
+            Frames96 => 96.0,
+            Frames72 => 72.0,
+            Frames59_94 => 60000.0 / 1001.0,
+            Frames119_88 => 120000.0 / 1001.0,
+        })
+    }
+}
@@ -0,0 +1,680 @@
+//! Contains evaluation of model transforms.
+//!
+//! The local transform of a model is computed as FBX SDK does:
+//!
+//! ```text
+//! T * Roff * Rp * Rpre * R * Rpost^-1 * Rp^-1 * Soff * Sp * S * Sp^-1
+//! ```
+//!
+//! where `T` is `Lcl Translation`, `Roff` and `Rp` are `RotationOffset` and `RotationPivot`,
+//! `Rpre` and `Rpost` are `PreRotation` and `PostRotation`, `R` is `Lcl Rotation` in
+//! `RotationOrder`, `Soff` and `Sp` are `ScalingOffset` and `ScalingPivot`, and `S` is
+//! `Lcl Scaling`.
+//!
+//! The geometric transform (`GeometricTranslation`, `GeometricRotation` and
+//! `GeometricScaling`) applies only to the geometry attached to the model, and is not inherited
+//! by children.
+//!
+//! Matrices are [`Matrix`](../type.Matrix.html) values in column-major order, as stored in FBX
+//! files.
+
+use std::collections::HashMap;
+use error::{Error, Result};
+use scene::{check_class, vector3};
+use scene::animation::{AnimationStack, AnimationTarget};
+use scene::definitions::Definitions;
+use scene::matrix::{self, Matrix};
+use scene::object_graph::{Object, ObjectGraph, parent_model};
+use scene::properties70::{Properties70, PropertyValue};
+use scene::time::FbxTime;
+
+
+/// Euler rotation order (`RotationOrder`).
+///
+/// The name lists axes in order of application, e.g. `Xyz` rotates around X first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RotationOrder {
+    /// XYZ (`0`).
+    Xyz,
+    /// XZY (`1`).
+    Xzy,
+    /// YZX (`2`).
+    Yzx,
+    /// YXZ (`3`).
+    Yxz,
+    /// ZXY (`4`).
+    Zxy,
+    /// ZYX (`5`).
+    Zyx,
+    /// Spheric XYZ (`6`), evaluated as XYZ.
+    SphericXyz,
+}
+
+impl RotationOrder {
+    /// Creates a rotation order from the `RotationOrder` value.
+    pub fn from_i32(v: i32) -> Option<Self> {
+        match v {
+            0 => Some(RotationOrder::Xyz),
+            1 => Some(RotationOrder::Xzy),
+            2 => Some(RotationOrder::Yzx),
+            3 => Some(RotationOrder::Yxz),
+            4 => Some(RotationOrder::Zxy),
+            5 => Some(RotationOrder::Zyx),
+            6 => Some(RotationOrder::SphericXyz),
+            _ => None,
+        }
+    }
+
+    /// Returns axis indices in order of application.
+    fn axes(self) -> [usize; 3] {
+        match self {
+            RotationOrder::Xyz | RotationOrder::SphericXyz => [0, 1, 2],
+            RotationOrder::Xzy => [0, 2, 1],
+            RotationOrder::Yzx => [1, 2, 0],
+            RotationOrder::Yxz => [1, 0, 2],
+            RotationOrder::Zxy => [2, 0, 1],
+            RotationOrder::Zyx => [2, 1, 0],
+        }
+    }
+
+    /// Returns the rotation matrix of Euler angles in degrees.
+    pub fn to_matrix(self, degrees: [f64; 3]) -> Matrix {
+        matrix::euler_rotation(degrees, self.axes())
+    }
+}
+
+
+/// How a model inherits the transform of its parent (`InheritType`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InheritType {
+    /// Parent rotation, then child rotation, parent scaling and child scaling (`0`).
+    ///
+    /// Parent scaling is applied in the child's rotated space, so no shear is introduced.
+    RrSs,
+    /// Parent rotation and scaling, then child rotation and scaling (`1`).
+    ///
+    /// This is plain matrix multiplication.
+    RSrs,
+    /// Like `RrSs`, but the local scaling of the parent is not inherited (`2`).
+    Rrs,
+}
+
+impl InheritType {
+    /// Creates an inherit type from the `InheritType` value.
+    pub fn from_i32(v: i32) -> Option<Self> {
+        match v {
+            0 => Some(InheritType::RrSs),
+            1 => Some(InheritType::RSrs),
+            2 => Some(InheritType::Rrs),
+            _ => None,
+        }
+    }
+}
+
+
+/// Transform properties of a `Model`.
+///
+/// Properties missing in the file have default values of FBX SDK.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransformProperties {
+    /// `Lcl Translation`.
+    pub translation: [f64; 3],
+    /// `Lcl Rotation`, Euler angles in degrees.
+    pub rotation: [f64; 3],
+    /// `Lcl Scaling`.
+    pub scaling: [f64; 3],
+    /// `RotationOrder`.
+    pub rotation_order: RotationOrder,
+    /// `PreRotation`, Euler angles in degrees (always in XYZ order).
+    pub pre_rotation: [f64; 3],
+    /// `PostRotation`, Euler angles in degrees (always in XYZ order).
+    pub post_rotation: [f64; 3],
+    /// `RotationOffset`.
+    pub rotation_offset: [f64; 3],
+    /// `RotationPivot`.
+    pub rotation_pivot: [f64; 3],
+    /// `ScalingOffset`.
+    pub scaling_offset: [f64; 3],
+    /// `ScalingPivot`.
+    pub scaling_pivot: [f64; 3],
+    /// `InheritType`.
+    pub inherit_type: InheritType,
+    /// `GeometricTranslation`.
+    pub geometric_translation: [f64; 3],
+    /// `GeometricRotation`, Euler angles in degrees (always in XYZ order).
+    pub geometric_rotation: [f64; 3],
+    /// `GeometricScaling`.
+    pub geometric_scaling: [f64; 3],
+}
+
+impl TransformProperties {
+    /// Reads transform properties of the `Model` object.
+    ///
+    /// Property templates are not applied (see [`definitions`](../definitions/index.html)).
+    pub fn from_object(object: &Object) -> Result<Self> {
+        check_class(object, "Model")?;
+        TransformProperties::from_properties(&Properties70::from_parent(object.node)?)
+    }
+
+    /// Reads transform properties of the `Model` object, with default values from the
+    /// `FbxNode` property template in `definitions`.
+    pub fn from_object_with<'a>(object: &Object<'a>, definitions: &Definitions<'a>) -> Result<Self> {
+        check_class(object, "Model")?;
+        TransformProperties::from_properties(&definitions.properties_of(object)?.to_properties())
+    }
+
+    fn from_properties(properties: &Properties70) -> Result<Self> {
+        let rotation_order = match properties.value("RotationOrder").and_then(PropertyValue::as_i64) {
+            Some(v) => RotationOrder::from_i32(v as i32).ok_or_else(|| Error::UnexpectedValue(format!("Unknown rotation order: {}", v)))?,
+            None => RotationOrder::Xyz,
+        };
+        let inherit_type = match properties.value("InheritType").and_then(PropertyValue::as_i64) {
+            Some(v) => InheritType::from_i32(v as i32).ok_or_else(|| Error::UnexpectedValue(format!("Unknown inherit type: {}", v)))?,
+            None => InheritType::RrSs,
+        };
+        Ok(TransformProperties {
+            translation: vector3(properties, "Lcl Translation", [0.0; 3]),
+            rotation: vector3(properties, "Lcl Rotation", [0.0; 3]),
+            scaling: vector3(properties, "Lcl Scaling", [1.0; 3]),
+            rotation_order,
+            pre_rotation: vector3(properties, "PreRotation", [0.0; 3]),
+            post_rotation: vector3(properties, "PostRotation", [0.0; 3]),
+            rotation_offset: vector3(properties, "RotationOffset", [0.0; 3]),
+            rotation_pivot: vector3(properties, "RotationPivot", [0.0; 3]),
+            scaling_offset: vector3(properties, "ScalingOffset", [0.0; 3]),
+            scaling_pivot: vector3(properties, "ScalingPivot", [0.0; 3]),
+            inherit_type,
+            geometric_translation: vector3(properties, "GeometricTranslation", [0.0; 3]),
+            geometric_rotation: vector3(properties, "GeometricRotation", [0.0; 3]),
+            geometric_scaling: vector3(properties, "GeometricScaling", [1.0; 3]),
+        })
+    }
+
+    /// Replaces `Lcl Translation`, `Lcl Rotation` and `Lcl Scaling` with values animated by the
+    /// stack at the given time.
+    pub fn apply_animation(&mut self, stack: &AnimationStack, object: i64, time: FbxTime) {
+        let fields = [
+            ("Lcl Translation", &mut self.translation),
+            ("Lcl Rotation", &mut self.rotation),
+            ("Lcl Scaling", &mut self.scaling),
+        ];
+        for (property, field) in fields {
+            let target = AnimationTarget { object, property };
+            let values = match stack.evaluate(target, time) {
+                Some(values) => values,
+                None => continue,
+            };
+            for (name, value) in stack.channel_names(target).into_iter().zip(values) {
+                match name {
+                    "X" => field[0] = value,
+                    "Y" => field[1] = value,
+                    "Z" => field[2] = value,
+                    _ => {},
+                }
+            }
+        }
+    }
+
+    /// Returns the rotation part of the local transform (`Rpre * R * Rpost^-1`).
+    pub fn rotation_matrix(&self) -> Matrix {
+        let pre = RotationOrder::Xyz.to_matrix(self.pre_rotation);
+        let rotation = self.rotation_order.to_matrix(self.rotation);
+        let post = RotationOrder::Xyz.to_matrix(self.post_rotation);
+        // Inverse of a rotation matrix is its transpose.
+        matrix::mul_all(&[pre, rotation, transpose3(&post)])
+    }
+
+    /// Returns the local transform matrix.
+    pub fn local_matrix(&self) -> Matrix {
+        let neg = |v: [f64; 3]| [-v[0], -v[1], -v[2]];
+        matrix::mul_all(&[
+            matrix::translation(self.translation),
+            matrix::translation(self.rotation_offset),
+            matrix::translation(self.rotation_pivot),
+            self.rotation_matrix(),
+            matrix::translation(neg(self.rotation_pivot)),
+            matrix::translation(self.scaling_offset),
+            matrix::translation(self.scaling_pivot),
+            matrix::scaling(self.scaling),
+            matrix::translation(neg(self.scaling_pivot)),
+        ])
+    }
+
+    /// Returns the geometric transform matrix.
+    pub fn geometric_matrix(&self) -> Matrix {
+        matrix::mul_all(&[
+            matrix::translation(self.geometric_translation),
+            RotationOrder::Xyz.to_matrix(self.geometric_rotation),
+            matrix::scaling(self.geometric_scaling),
+        ])
+    }
+
+    /// Returns the world transform matrix from the world transform of the parent.
+    ///
+    /// `parent` is `None` for root models.
+    pub fn world_matrix(&self, parent: Option<(&Matrix, &TransformProperties)>) -> Matrix {
+        let local = self.local_matrix();
+        let (parent_world, parent_properties) = match parent {
+            Some(parent) => parent,
+            None => return local,
+        };
+        if self.inherit_type == InheritType::RSrs {
+            return matrix::mul(parent_world, &local);
+        }
+        // Separate the parent world transform into rotation and scaling (with shear).
+        let (_, parent_rotation, _) = matrix::decompose(parent_world);
+        let parent_linear = matrix::with_translation(parent_world, [0.0; 3]);
+        let mut parent_scaling = matrix::mul(&transpose3(&parent_rotation), &parent_linear);
+        if self.inherit_type == InheritType::Rrs {
+            let s = parent_properties.scaling;
+            let inverse = |v: f64| if v != 0.0 { 1.0 / v } else { 0.0 };
+            parent_scaling = matrix::mul(&parent_scaling, &matrix::scaling([inverse(s[0]), inverse(s[1]), inverse(s[2])]));
+        }
+        let linear = matrix::mul_all(&[parent_rotation, self.rotation_matrix(), parent_scaling, matrix::scaling(self.scaling)]);
+        let translation = matrix::transform_point(parent_world, matrix::get_translation(&local));
+        matrix::with_translation(&linear, translation)
+    }
+}
+
+
+/// Translation, rotation and scaling decomposed from a matrix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decomposed {
+    /// Translation.
+    pub translation: [f64; 3],
+    /// Rotation as a unit quaternion `[x, y, z, w]`.
+    pub rotation: [f64; 4],
+    /// Scaling.
+    ///
+    /// Negative determinant is represented as negative X scaling.
+    pub scaling: [f64; 3],
+}
+
+impl Decomposed {
+    /// Decomposes the affine matrix.
+    ///
+    /// Shear cannot be represented, and the result is approximate for sheared matrices.
+    pub fn from_matrix(m: &Matrix) -> Self {
+        let (translation, rotation, scaling) = matrix::decompose(m);
+        Decomposed {
+            translation,
+            rotation: matrix::to_quaternion(&rotation),
+            scaling,
+        }
+    }
+}
+
+
+/// Evaluated transform of a model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelTransform {
+    /// Object ID of the model.
+    pub id: i64,
+    /// ID of the parent model, if the model is not a root.
+    pub parent: Option<i64>,
+    /// Transform properties.
+    pub properties: TransformProperties,
+    /// Local transform matrix.
+    pub local: Matrix,
+    /// World transform matrix.
+    pub world: Matrix,
+    /// Geometric transform matrix.
+    pub geometric: Matrix,
+}
+
+impl ModelTransform {
+    /// Returns the matrix transforming the attached geometry into the world space
+    /// (`world * geometric`).
+    pub fn geometry_to_world(&self) -> Matrix {
+        matrix::mul(&self.world, &self.geometric)
+    }
+
+    /// Returns the decomposed local transform.
+    pub fn local_decomposed(&self) -> Decomposed {
+        Decomposed::from_matrix(&self.local)
+    }
+
+    /// Returns the decomposed world transform.
+    pub fn world_decomposed(&self) -> Decomposed {
+        Decomposed::from_matrix(&self.world)
+    }
+}
+
+
+/// Evaluates transforms of all models, in order of objects.
+///
+/// Default values come from the `FbxNode` property template in `definitions`.
+/// If `animation` is given, local transforms are animated by the stack at the time.
+pub fn model_transforms<'a>(graph: &ObjectGraph<'a>, definitions: &Definitions<'a>, animation: Option<(&AnimationStack, FbxTime)>) -> Result<Vec<ModelTransform>> {
+    let mut transforms = Vec::new();
+    let mut indices = HashMap::new();
+    for object in graph.objects().filter(|object| object.class == "Model") {
+        let mut properties = TransformProperties::from_object_with(object, definitions)?;
+        if let Some((stack, time)) = animation {
+            properties.apply_animation(stack, object.id, time);
+        }
+        indices.insert(object.id, transforms.len());
+        transforms.push(ModelTransform {
+            id: object.id,
+            parent: parent_model(graph, object.id),
+            local: properties.local_matrix(),
+            world: matrix::IDENTITY,
+            geometric: properties.geometric_matrix(),
+            properties,
+        });
+    }
+
+    // Compute world transforms, parents first.
+    let mut done = vec![false; transforms.len()];
+    for start in 0..transforms.len() {
+        // Collect ancestors not yet computed.
+        let mut chain = vec![start];
+        let mut current = start;
+        while let Some(parent) = transforms[current].parent.and_then(|id| indices.get(&id).cloned()) {
+            if done[parent] {
+                break;
+            }
+            if chain.contains(&parent) {
+                return Err(Error::DataError(format!("Model hierarchy has a cycle at model {}", transforms[parent].id)));
+            }
+            chain.push(parent);
+            current = parent;
+        }
+        for &index in chain.iter().rev() {
+            if done[index] {
+                continue;
+            }
+            let world = {
+                let transform = &transforms[index];
+                let parent = transform.parent.and_then(|id| indices.get(&id)).map(|&parent| &transforms[parent]);
+                transform.properties.world_matrix(parent.map(|parent| (&parent.world, &parent.properties)))
+            };
+            transforms[index].world = world;
+            done[index] = true;
+        }
+    }
+    Ok(transforms)
+}
+
+
+/// Transposes the upper-left 3x3 part, dropping the translation.
+fn transpose3(m: &Matrix) -> Matrix {
+    let mut r = matrix::IDENTITY;
+    for col in 0..3 {
+        for row in 0..3 {
+            r[col * 4 + row] = m[row * 4 + col];
+        }
+    }
+    r
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{Decomposed, InheritType, RotationOrder, TransformProperties, model_transforms};
+    use property::Property;
+    use scene::animation::animation_stacks;
+    use scene::definitions::Definitions;
+    use scene::matrix::{self, Matrix};
+    use scene::object_graph::ObjectGraph;
+    use scene::time::{FbxTime, TICKS_PER_SECOND};
+    use test_util::{connection, node, object, op, p, tree, TestNode};
+
+    fn properties(translation: [f64; 3], rotation: [f64; 3], scaling: [f64; 3], inherit_type: InheritType) -> TransformProperties {
+        TransformProperties {
+            translation,
+            rotation,
+            scaling,
+            rotation_order: RotationOrder::Xyz,
+            pre_rotation: [0.0; 3],
+            post_rotation: [0.0; 3],
+            rotation_offset: [0.0; 3],
+            rotation_pivot: [0.0; 3],
+            scaling_offset: [0.0; 3],
+            scaling_pivot: [0.0; 3],
+            inherit_type,
+            geometric_translation: [0.0; 3],
+            geometric_rotation: [0.0; 3],
+            geometric_scaling: [1.0; 3],
+        }
+    }
+
+    fn assert_matrix_eq(actual: &Matrix, expected: &Matrix) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-9, "expected {:?}, got {:?}", expected, actual);
+        }
+    }
+
+    /// Returns the world matrix of a child at `[1, 0, 0]` rotated 90 degrees around Z, under a
+    /// root parent at `[1, 2, 3]` rotated 90 degrees around Z and scaled by 2 along its X axis.
+    fn child_world(inherit_type: InheritType) -> Matrix {
+        let parent = properties([1.0, 2.0, 3.0], [0.0, 0.0, 90.0], [2.0, 1.0, 1.0], InheritType::RrSs);
+        let parent_world = parent.world_matrix(None);
+        assert_matrix_eq(&parent_world, &parent.local_matrix());
+        let child = properties([1.0, 0.0, 0.0], [0.0, 0.0, 90.0], [1.0; 3], inherit_type);
+        child.world_matrix(Some((&parent_world, &parent)))
+    }
+
+    #[test]
+    fn world_matrix_rsrs() {
+        let world = child_world(InheritType::RSrs);
+        let parent = properties([1.0, 2.0, 3.0], [0.0, 0.0, 90.0], [2.0, 1.0, 1.0], InheritType::RrSs);
+        let child = properties([1.0, 0.0, 0.0], [0.0, 0.0, 90.0], [1.0; 3], InheritType::RSrs);
+        assert_matrix_eq(&world, &matrix::mul(&parent.local_matrix(), &child.local_matrix()));
+        // The child X axis is the parent Y axis, which is not scaled.
+        assert_matrix_eq(&world, &matrix::mul_all(&[
+            matrix::translation([1.0, 4.0, 3.0]),
+            matrix::axis_rotation(2, 180.0),
+            matrix::scaling([1.0, 2.0, 1.0]),
+        ]));
+    }
+
+    #[test]
+    fn world_matrix_rrss() {
+        let world = child_world(InheritType::RrSs);
+        // The parent scaling is applied along the child's own X axis.
+        assert_matrix_eq(&world, &matrix::mul_all(&[
+            matrix::translation([1.0, 4.0, 3.0]),
+            matrix::axis_rotation(2, 180.0),
+            matrix::scaling([2.0, 1.0, 1.0]),
+        ]));
+    }
+
+    #[test]
+    fn world_matrix_rrs() {
+        let world = child_world(InheritType::Rrs);
+        // The parent local scaling is not inherited, but the translation is still in the parent
+        // space.
+        assert_matrix_eq(&world, &matrix::mul(&matrix::translation([1.0, 4.0, 3.0]), &matrix::axis_rotation(2, 180.0)));
+    }
+
+    #[test]
+    fn world_matrix_root() {
+        let model = properties([1.0, 2.0, 3.0], [10.0, 20.0, 30.0], [1.0, 2.0, 3.0], InheritType::RSrs);
+        assert_matrix_eq(&model.world_matrix(None), &model.local_matrix());
+    }
+
+    #[test]
+    fn decompose_round_trip() {
+        for &scaling in &[[2.0, 3.0, 4.0], [-2.0, 3.0, 4.0]] {
+            let rotation = RotationOrder::Zxy.to_matrix([30.0, 45.0, 60.0]);
+            let m = matrix::mul_all(&[matrix::translation([1.0, -2.0, 3.0]), rotation, matrix::scaling(scaling)]);
+            let (t, r, s) = matrix::decompose(&m);
+            assert_matrix_eq(&r, &rotation);
+            assert_matrix_eq(&matrix::mul_all(&[matrix::translation(t), r, matrix::scaling(s)]), &m);
+
+            let decomposed = Decomposed::from_matrix(&m);
+            assert_eq!(decomposed.translation, [1.0, -2.0, 3.0]);
+            for (a, e) in decomposed.scaling.iter().zip(&scaling) {
+                assert!((a - e).abs() < 1e-9);
+            }
+            let q = decomposed.rotation;
+            assert!((q.iter().map(|v| v * v).sum::<f64>() - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn decompose_quaternion() {
+        let decomposed = Decomposed::from_matrix(&matrix::axis_rotation(2, 90.0));
+        let half = ::std::f64::consts::FRAC_1_SQRT_2;
+        for (a, e) in decomposed.rotation.iter().zip(&[0.0, 0.0, half, half]) {
+            assert!((a - e).abs() < 1e-9, "{:?}", decomposed.rotation);
+        }
+        assert_eq!(decomposed.translation, [0.0; 3]);
+    }
+
+    #[test]
+    fn model_transforms_with_template() {
+        let tree = tree(7400, &[
+            node("Definitions", vec![], vec![
+                node("ObjectType", vec![Property::String(Ok("Model"))], vec![
+                    node("PropertyTemplate", vec![Property::String(Ok("FbxNode"))], vec![
+                        node("Properties70", vec![], vec![p("Lcl Scaling", "Lcl Scaling", "A", vec![Property::F64(2.0); 3])]),
+                    ]),
+                ]),
+            ]),
+            node("Objects", vec![], vec![
+                object("Model", 1, "Parent\0\x01Model", "Null", vec![]),
+                object("Model", 2, "Child\0\x01Model", "Null", vec![
+                    node("Properties70", vec![], vec![p("Lcl Scaling", "Lcl Scaling", "A", vec![Property::F64(3.0); 3])]),
+                ]),
+            ]),
+            node("Connections", vec![], vec![connection(1, 0), connection(2, 1)]),
+        ]);
+        let definitions = Definitions::from_tree(&tree).unwrap();
+        let graph = ObjectGraph::from_tree(&tree);
+        let transforms = model_transforms(&graph, &definitions, None).unwrap();
+        assert_matrix_eq(&transforms[0].world, &matrix::scaling([2.0; 3]));
+        assert_eq!(transforms[1].parent, Some(1));
+        assert_matrix_eq(&transforms[1].local, &matrix::scaling([3.0; 3]));
+        assert_matrix_eq(&transforms[1].world, &matrix::scaling([6.0; 3]));
+    }
+
+    #[test]
+    fn local_matrix_with_pivots() {
+        let mut model = properties([1.0, 2.0, 3.0], [10.0, 20.0, 30.0], [2.0, 3.0, 4.0], InheritType::RrSs);
+        model.rotation_order = RotationOrder::Zyx;
+        model.pre_rotation = [-90.0, 0.0, 45.0];
+        model.post_rotation = [15.0, -30.0, 60.0];
+        model.rotation_offset = [0.5, 0.0, -0.5];
+        model.rotation_pivot = [1.0, -1.0, 2.0];
+        model.scaling_offset = [0.0, 0.25, 0.0];
+        model.scaling_pivot = [-2.0, 1.0, 0.5];
+
+        let (x, y, z) = (|a| matrix::axis_rotation(0, a), |a| matrix::axis_rotation(1, a), |a| matrix::axis_rotation(2, a));
+        // `PreRotation` is in XYZ order, i.e. X is applied first.
+        let pre = matrix::mul_all(&[z(45.0), y(0.0), x(-90.0)]);
+        // `Lcl Rotation` is in ZYX order.
+        let rotation = matrix::mul_all(&[x(10.0), y(20.0), z(30.0)]);
+        // Inverse of `PostRotation` in XYZ order.
+        let post_inverse = matrix::mul_all(&[x(-15.0), y(30.0), z(-60.0)]);
+        let expected = matrix::mul_all(&[
+            matrix::translation([1.0, 2.0, 3.0]),
+            matrix::translation([0.5, 0.0, -0.5]),
+            matrix::translation([1.0, -1.0, 2.0]),
+            pre,
+            rotation,
+            post_inverse,
+            matrix::translation([-1.0, 1.0, -2.0]),
+            matrix::translation([0.0, 0.25, 0.0]),
+            matrix::translation([-2.0, 1.0, 0.5]),
+            matrix::scaling([2.0, 3.0, 4.0]),
+            matrix::translation([2.0, -1.0, -0.5]),
+        ]);
+        assert_matrix_eq(&model.local_matrix(), &expected);
+        assert_matrix_eq(&model.world_matrix(None), &expected);
+
+        // Rotation around the pivot `[1, 0, 0]` moves `[2, 0, 0]` to `[1, 1, 0]`.
+        let mut model = properties([0.0; 3], [0.0, 0.0, 90.0], [1.0; 3], InheritType::RrSs);
+        model.rotation_pivot = [1.0, 0.0, 0.0];
+        let point = matrix::transform_point(&model.local_matrix(), [2.0, 0.0, 0.0]);
+        assert_matrix_eq(&matrix::translation(point), &matrix::translation([1.0, 1.0, 0.0]));
+    }
+
+    fn vector(name: &'static str, v: [f64; 3]) -> TestNode<'static> {
+        let type_name = if name.starts_with("Lcl ") { name } else { "Vector3D" };
+        p(name, type_name, "A", v.iter().map(|&v| Property::F64(v)).collect())
+    }
+
+    #[test]
+    fn geometric_transform() {
+        let tree = tree(7400, &[
+            node("Objects", vec![], vec![
+                object("Model", 1, "Parent\0\x01Model", "Mesh", vec![
+                    node("Properties70", vec![], vec![
+                        vector("Lcl Translation", [0.0, 0.0, 5.0]),
+                        vector("GeometricTranslation", [1.0, 0.0, 0.0]),
+                        vector("GeometricRotation", [0.0, 0.0, 90.0]),
+                        vector("GeometricScaling", [2.0; 3]),
+                    ]),
+                ]),
+                object("Model", 2, "Child\0\x01Model", "Null", vec![
+                    node("Properties70", vec![], vec![vector("Lcl Translation", [0.0, 1.0, 0.0])]),
+                ]),
+            ]),
+            node("Connections", vec![], vec![connection(1, 0), connection(2, 1)]),
+        ]);
+        let definitions = Definitions::from_tree(&tree).unwrap();
+        let graph = ObjectGraph::from_tree(&tree);
+        let transforms = model_transforms(&graph, &definitions, None).unwrap();
+        let geometric = matrix::mul_all(&[matrix::translation([1.0, 0.0, 0.0]), matrix::axis_rotation(2, 90.0), matrix::scaling([2.0; 3])]);
+        assert_matrix_eq(&transforms[0].geometric, &geometric);
+        assert_matrix_eq(&transforms[0].properties.geometric_matrix(), &geometric);
+        assert_matrix_eq(&transforms[0].world, &matrix::translation([0.0, 0.0, 5.0]));
+        assert_matrix_eq(&transforms[0].geometry_to_world(), &matrix::mul(&matrix::translation([0.0, 0.0, 5.0]), &geometric));
+        // The geometric transform is not inherited.
+        assert_matrix_eq(&transforms[1].world, &matrix::translation([0.0, 1.0, 5.0]));
+        assert_matrix_eq(&transforms[1].geometry_to_world(), &transforms[1].world);
+    }
+
+    #[test]
+    fn animated_transform() {
+        let number = |name, value| p(name, "Number", "A", vec![Property::F64(value)]);
+        let tree = tree(7400, &[
+            node("Objects", vec![], vec![
+                object("Model", 1, "Cube\0\x01Model", "Mesh", vec![
+                    node("Properties70", vec![], vec![
+                        vector("Lcl Translation", [7.0, 8.0, 9.0]),
+                        vector("Lcl Rotation", [0.0, 0.0, 90.0]),
+                    ]),
+                ]),
+                object("AnimationStack", 100, "Take\0\x01AnimStack", "", vec![]),
+                object("AnimationLayer", 200, "Base\0\x01AnimLayer", "", vec![]),
+                object("AnimationCurveNode", 300, "T\0\x01AnimCurveNode", "", vec![
+                    node("Properties70", vec![], vec![number("d|X", 7.0), number("d|Y", 8.0), number("d|Z", 9.0)]),
+                ]),
+                // Linear from `0` at 0s to `10` at 1s.
+                object("AnimationCurve", 400, "\0\x01AnimCurve", "", vec![
+                    node("KeyTime", vec![Property::VecI64(vec![0, TICKS_PER_SECOND])], vec![]),
+                    node("KeyValueFloat", vec![Property::VecF32(vec![0.0, 10.0])], vec![]),
+                    node("KeyAttrFlags", vec![Property::VecI32(vec![0x4])], vec![]),
+                    node("KeyAttrDataFloat", vec![Property::VecF32(vec![0.0; 4])], vec![]),
+                    node("KeyAttrRefCount", vec![Property::VecI32(vec![2])], vec![]),
+                ]),
+            ]),
+            node("Connections", vec![], vec![
+                connection(1, 0),
+                connection(200, 100),
+                connection(300, 200),
+                op(300, 1, "Lcl Translation"),
+                op(400, 300, "d|X"),
+            ]),
+        ]);
+        let graph = ObjectGraph::from_tree(&tree);
+        let stack = &animation_stacks(&graph).unwrap()[0];
+        let time = FbxTime::from_seconds(0.25);
+        let mut model = TransformProperties::from_object(graph.object(1).unwrap()).unwrap();
+        model.apply_animation(stack, 1, time);
+        assert_eq!(model.translation, [2.5, 8.0, 9.0]);
+        // Properties without curve nodes are kept.
+        assert_eq!(model.rotation, [0.0, 0.0, 90.0]);
+        assert_eq!(model.scaling, [1.0; 3]);
+
+        let definitions = Definitions::from_tree(&tree).unwrap();
+        let transforms = model_transforms(&graph, &definitions, Some((stack, time))).unwrap();
+        assert_eq!(transforms[0].properties, model);
+        assert_matrix_eq(&transforms[0].local, &matrix::mul(&matrix::translation([2.5, 8.0, 9.0]), &matrix::axis_rotation(2, 90.0)));
+        let still = model_transforms(&graph, &definitions, None).unwrap();
+        assert_eq!(still[0].properties.translation, [7.0, 8.0, 9.0]);
+    }
+}
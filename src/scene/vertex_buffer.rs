@@ -0,0 +1,457 @@
+//! Contains conversion of polygon vertex data into indexed vertex buffers.
+//!
+//! FBX meshes have attributes (normals, UVs, ...) per polygon vertex, but GPUs need unique
+//! vertices and an index buffer.
+//! [`Welder`](struct.Welder.html) deduplicates polygon vertices with the same control point
+//! and the same attribute values.
+
+use std::collections::HashMap;
+use error::{Error, Result};
+use scene::layer_element::{LayerElementKind, layer_elements};
+use scene::mesh::Mesh;
+use tree::Node;
+
+
+/// Per-polygon-vertex attributes of a mesh.
+///
+/// Each array should have a value for each polygon vertex of the mesh.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VertexAttributes {
+    /// Normals.
+    pub normals: Option<Vec<[f64; 3]>>,
+    /// Tangents.
+    pub tangents: Option<Vec<[f64; 3]>>,
+    /// Binormals.
+    pub binormals: Option<Vec<[f64; 3]>>,
+    /// UV sets.
+    pub uvs: Vec<Vec<[f64; 2]>>,
+    /// Vertex colors.
+    pub colors: Option<Vec<[f64; 4]>>,
+}
+
+impl VertexAttributes {
+    /// Resolves attributes of the `Geometry` node.
+    ///
+    /// The first layer is used for normals, tangents, binormals and colors, and all UV layers
+    /// are used in order of layer index.
+    pub fn from_geometry(geometry: &Node, mesh: &Mesh) -> Result<Self> {
+        let first_vec3 = |kind| -> Result<Option<Vec<[f64; 3]>>> {
+            match layer_elements(geometry, kind)?.first() {
+                Some(element) => element.resolve_vec3(mesh).map(Some),
+                None => Ok(None),
+            }
+        };
+        Ok(VertexAttributes {
+            normals: first_vec3(LayerElementKind::Normal)?,
+            tangents: first_vec3(LayerElementKind::Tangent)?,
+            binormals: first_vec3(LayerElementKind::Binormal)?,
+            uvs: layer_elements(geometry, LayerElementKind::Uv)?.iter()
+                .map(|element| element.resolve_vec2(mesh))
+                .collect::<Result<_>>()?,
+            colors: match layer_elements(geometry, LayerElementKind::Color)?.first() {
+                Some(element) => Some(element.resolve_vec4(mesh)?),
+                None => None,
+            },
+        })
+    }
+
+    fn check_len(&self, len: usize) -> Result<()> {
+        let lens = [
+            self.normals.as_ref().map(Vec::len),
+            self.tangents.as_ref().map(Vec::len),
+            self.binormals.as_ref().map(Vec::len),
+            self.colors.as_ref().map(Vec::len),
+        ];
+        let uv_lens = self.uvs.iter().map(|uvs| Some(uvs.len()));
+        if let Some(actual) = lens.iter().cloned().chain(uv_lens).flatten().find(|&actual| actual != len) {
+            return Err(Error::DataError(format!("Attribute has {} values, but the mesh has {} polygon vertices", actual, len)));
+        }
+        Ok(())
+    }
+}
+
+
+/// Index buffer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexBuffer {
+    /// 16-bit indices.
+    U16(Vec<u16>),
+    /// 32-bit indices.
+    U32(Vec<u32>),
+}
+
+impl IndexBuffer {
+    /// Creates an index buffer with the smallest index type for the given number of vertices.
+    ///
+    /// 16-bit indices are used only if they are less than `0xFFFF`, which is the primitive
+    /// restart index in graphics APIs.
+    /// All indices should be less than `num_vertices`.
+    pub fn new(indices: Vec<u32>, num_vertices: usize) -> Self {
+        debug_assert!(indices.iter().all(|&i| (i as usize) < num_vertices), "Index is out of range (number of vertices is {})", num_vertices);
+        if num_vertices <= u16::MAX as usize {
+            IndexBuffer::U16(indices.into_iter().map(|i| i as u16).collect())
+        } else {
+            IndexBuffer::U32(indices)
+        }
+    }
+
+    /// Returns the number of indices.
+    pub fn len(&self) -> usize {
+        match *self {
+            IndexBuffer::U16(ref v) => v.len(),
+            IndexBuffer::U32(ref v) => v.len(),
+        }
+    }
+
+    /// Returns whether there are no indices.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the indices as `u32`.
+    pub fn to_u32(&self) -> Vec<u32> {
+        match *self {
+            IndexBuffer::U16(ref v) => v.iter().map(|&i| i as u32).collect(),
+            IndexBuffer::U32(ref v) => v.clone(),
+        }
+    }
+}
+
+
+/// Vertex buffers in struct-of-arrays layout.
+///
+/// Arrays of absent attributes are empty.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VertexBuffers {
+    /// Positions.
+    pub positions: Vec<[f32; 3]>,
+    /// Normals.
+    pub normals: Vec<[f32; 3]>,
+    /// Tangents.
+    pub tangents: Vec<[f32; 3]>,
+    /// Binormals.
+    pub binormals: Vec<[f32; 3]>,
+    /// UV sets.
+    pub uvs: Vec<Vec<[f32; 2]>>,
+    /// Vertex colors.
+    pub colors: Vec<[f32; 4]>,
+    /// Source control point index of each vertex, for skinning and morphing.
+    pub control_points: Vec<u32>,
+}
+
+impl VertexBuffers {
+    /// Returns the number of vertices.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns whether there are no vertices.
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Returns the number of floats per vertex in interleaved layout.
+    pub fn stride(&self) -> usize {
+        let mut stride = 3;
+        if !self.normals.is_empty() {
+            stride += 3;
+        }
+        if !self.tangents.is_empty() {
+            stride += 3;
+        }
+        if !self.binormals.is_empty() {
+            stride += 3;
+        }
+        stride += 2 * self.uvs.len();
+        if !self.colors.is_empty() {
+            stride += 4;
+        }
+        stride
+    }
+
+    /// Returns the vertices in interleaved layout.
+    ///
+    /// Each vertex consists of position, normal, tangent, binormal, UVs and color in this
+    /// order, and absent attributes are omitted.
+    /// See [`stride()`](#method.stride) for the number of floats per vertex.
+    pub fn interleaved(&self) -> Vec<f32> {
+        let mut data = Vec::with_capacity(self.len() * self.stride());
+        for i in 0..self.len() {
+            data.extend_from_slice(&self.positions[i]);
+            if let Some(v) = self.normals.get(i) {
+                data.extend_from_slice(v);
+            }
+            if let Some(v) = self.tangents.get(i) {
+                data.extend_from_slice(v);
+            }
+            if let Some(v) = self.binormals.get(i) {
+                data.extend_from_slice(v);
+            }
+            for uvs in &self.uvs {
+                data.extend_from_slice(&uvs[i]);
+            }
+            if let Some(v) = self.colors.get(i) {
+                data.extend_from_slice(v);
+            }
+        }
+        data
+    }
+}
+
+
+/// Vertex welder.
+///
+/// A welder can be used for multiple index lists (such as submeshes) to share a vertex buffer.
+#[derive(Debug)]
+pub struct Welder<'a> {
+    mesh: &'a Mesh,
+    attributes: &'a VertexAttributes,
+    buffers: VertexBuffers,
+    /// Vertex indices by key (control point index and bit patterns of attribute values).
+    vertices: HashMap<Vec<u32>, u32>,
+    key: Vec<u32>,
+}
+
+impl<'a> Welder<'a> {
+    /// Creates a new welder.
+    pub fn new(mesh: &'a Mesh, attributes: &'a VertexAttributes) -> Result<Self> {
+        attributes.check_len(mesh.polygon_vertices.len())?;
+        Ok(Welder {
+            mesh,
+            attributes,
+            buffers: VertexBuffers {
+                uvs: vec![Vec::new(); attributes.uvs.len()],
+                ..VertexBuffers::default()
+            },
+            vertices: HashMap::new(),
+            key: Vec::new(),
+        })
+    }
+
+    /// Adds vertices for the polygon vertex indices, and returns the vertex indices.
+    pub fn add(&mut self, polygon_vertices: &[u32]) -> Result<Vec<u32>> {
+        polygon_vertices.iter().map(|&pv| self.add_vertex(pv as usize)).collect()
+    }
+
+    /// Adds vertices for the polygon vertex indices, and returns the index buffer.
+    ///
+    /// Note that the index type is determined by the number of vertices added so far, so when
+    /// the welder is used for multiple index lists, buffers added earlier may be
+    /// `IndexBuffer::U16` while later ones are `IndexBuffer::U32`.
+    pub fn add_index_buffer(&mut self, polygon_vertices: &[u32]) -> Result<IndexBuffer> {
+        let indices = self.add(polygon_vertices)?;
+        Ok(IndexBuffer::new(indices, self.buffers.len()))
+    }
+
+    /// Returns the vertex buffers.
+    pub fn finish(self) -> VertexBuffers {
+        self.buffers
+    }
+
+    fn add_vertex(&mut self, pv: usize) -> Result<u32> {
+        let control_point = match self.mesh.polygon_vertices.get(pv) {
+            Some(&control_point) => control_point,
+            None => return Err(Error::DataError(format!("Polygon vertex index {} is out of range", pv))),
+        };
+        let position = to_f32(&self.mesh.vertices[control_point as usize]);
+        let normal = self.attributes.normals.as_ref().map(|v| to_f32(&v[pv]));
+        let tangent = self.attributes.tangents.as_ref().map(|v| to_f32(&v[pv]));
+        let binormal = self.attributes.binormals.as_ref().map(|v| to_f32(&v[pv]));
+        let uvs: Vec<[f32; 2]> = self.attributes.uvs.iter().map(|v| to_f32(&v[pv])).collect();
+        let color = self.attributes.colors.as_ref().map(|v| to_f32(&v[pv]));
+
+        self.key.clear();
+        self.key.push(control_point);
+        {
+            let key = &mut self.key;
+            let mut push = |values: &[f32]| key.extend(values.iter().map(|&v| if v == 0.0 { 0 } else { v.to_bits() }));
+            push(&position);
+            for v in normal.iter().chain(&tangent).chain(&binormal) {
+                push(v);
+            }
+            for uv in &uvs {
+                push(uv);
+            }
+            if let Some(ref v) = color {
+                push(v);
+            }
+        }
+        if let Some(&index) = self.vertices.get(&self.key) {
+            return Ok(index);
+        }
+
+        let index = self.buffers.len() as u32;
+        let buffers = &mut self.buffers;
+        buffers.positions.push(position);
+        buffers.normals.extend(normal);
+        buffers.tangents.extend(tangent);
+        buffers.binormals.extend(binormal);
+        for (dest, uv) in buffers.uvs.iter_mut().zip(uvs) {
+            dest.push(uv);
+        }
+        buffers.colors.extend(color);
+        buffers.control_points.push(control_point);
+        self.vertices.insert(self.key.clone(), index);
+        Ok(index)
+    }
+}
+
+
+/// Welds the polygon vertices into vertex buffers and an index buffer.
+///
+/// `polygon_vertices` is a list of polygon vertex indices, such as flattened triangles from
+/// [`Mesh::triangulate()`](../mesh/struct.Mesh.html#method.triangulate) or
+/// [`Submesh::polygon_vertices`](../submesh/struct.Submesh.html#structfield.polygon_vertices).
+pub fn weld(mesh: &Mesh, attributes: &VertexAttributes, polygon_vertices: &[u32]) -> Result<(VertexBuffers, IndexBuffer)> {
+    let mut welder = Welder::new(mesh, attributes)?;
+    let indices = welder.add(polygon_vertices)?;
+    let buffers = welder.finish();
+    let indices = IndexBuffer::new(indices, buffers.len());
+    Ok((buffers, indices))
+}
+
+fn to_f32<const N: usize>(v: &[f64; N]) -> [f32; N] {
+    let mut result = [0.0; N];
+    for (dest, &src) in result.iter_mut().zip(v) {
+        *dest = src as f32;
+    }
+    result
+}
+
+
+#[cfg(test)]
+mod tests {
+    use scene::mesh::Mesh;
+    use super::{IndexBuffer, VertexAttributes, Welder, weld};
+
+    /// Returns two triangles `[0, 1, 2]` and `[2, 1, 3]` sharing an edge.
+    fn quad() -> Mesh {
+        Mesh {
+            vertices: vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0]],
+            polygon_vertices: vec![0, 1, 2, 2, 1, 3],
+            polygon_starts: vec![0, 3, 6],
+            edges: Vec::new(),
+        }
+    }
+
+    /// Returns per-polygon-vertex normals, all `+Z`.
+    fn flat_normals() -> Vec<[f64; 3]> {
+        vec![[0.0, 0.0, 1.0]; 6]
+    }
+
+    #[test]
+    fn index_type_by_vertex_count() {
+        assert_eq!(IndexBuffer::new(vec![0, 0xFFFE], 0xFFFF), IndexBuffer::U16(vec![0, 0xFFFE]));
+        // `0xFFFF` is reserved for primitive restart.
+        assert_eq!(IndexBuffer::new(vec![0, 0xFFFF], 0x1_0000), IndexBuffer::U32(vec![0, 0xFFFF]));
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn index_out_of_range() {
+        IndexBuffer::new(vec![0, 3], 3);
+    }
+
+    #[test]
+    fn weld_identical_vertices() {
+        let mesh = quad();
+        let attributes = VertexAttributes {
+            normals: Some(flat_normals()),
+            ..VertexAttributes::default()
+        };
+        let (buffers, indices) = weld(&mesh, &attributes, &[0, 1, 2, 3, 4, 5]).unwrap();
+        assert_eq!(buffers.len(), 4);
+        assert_eq!(buffers.control_points, [0, 1, 2, 3]);
+        assert_eq!(buffers.positions[3], [1.0, 1.0, 0.0]);
+        assert_eq!(indices, IndexBuffer::U16(vec![0, 1, 2, 2, 1, 3]));
+    }
+
+    #[test]
+    fn split_on_different_normal() {
+        let mesh = quad();
+        let mut normals = flat_normals();
+        // Control point 1 of the second triangle has another normal.
+        normals[4] = [0.0, 1.0, 0.0];
+        let attributes = VertexAttributes {
+            normals: Some(normals),
+            ..VertexAttributes::default()
+        };
+        let (buffers, indices) = weld(&mesh, &attributes, &[0, 1, 2, 3, 4, 5]).unwrap();
+        assert_eq!(buffers.control_points, [0, 1, 2, 1, 3]);
+        assert_eq!(buffers.normals[1], [0.0, 0.0, 1.0]);
+        assert_eq!(buffers.normals[3], [0.0, 1.0, 0.0]);
+        assert_eq!(indices.to_u32(), [0, 1, 2, 2, 3, 4]);
+    }
+
+    #[test]
+    fn split_on_different_uv() {
+        let mesh = quad();
+        let mut uvs = vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [0.0, 1.0], [1.0, 0.0], [1.0, 1.0]];
+        let attributes = VertexAttributes {
+            uvs: vec![uvs.clone()],
+            ..VertexAttributes::default()
+        };
+        assert_eq!(weld(&mesh, &attributes, &[0, 1, 2, 3, 4, 5]).unwrap().0.len(), 4);
+
+        // UV seam at control point 2.
+        uvs[3] = [0.5, 0.5];
+        let attributes = VertexAttributes {
+            uvs: vec![uvs],
+            ..VertexAttributes::default()
+        };
+        let (buffers, indices) = weld(&mesh, &attributes, &[0, 1, 2, 3, 4, 5]).unwrap();
+        assert_eq!(buffers.control_points, [0, 1, 2, 2, 3]);
+        assert_eq!(buffers.uvs[0][3], [0.5, 0.5]);
+        assert_eq!(indices.to_u32(), [0, 1, 2, 3, 1, 4]);
+    }
+
+    #[test]
+    fn shared_across_index_lists() {
+        let mesh = quad();
+        let attributes = VertexAttributes::default();
+        let mut welder = Welder::new(&mesh, &attributes).unwrap();
+        assert_eq!(welder.add(&[0, 1, 2]).unwrap(), [0, 1, 2]);
+        assert_eq!(welder.add_index_buffer(&[3, 4, 5]).unwrap(), IndexBuffer::U16(vec![2, 1, 3]));
+        assert_eq!(welder.finish().control_points, [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn attribute_length_mismatch() {
+        let mesh = quad();
+        let attributes = VertexAttributes {
+            normals: Some(vec![[0.0, 0.0, 1.0]; 5]),
+            ..VertexAttributes::default()
+        };
+        assert!(Welder::new(&mesh, &attributes).is_err());
+        let attributes = VertexAttributes::default();
+        assert!(weld(&mesh, &attributes, &[6]).is_err());
+    }
+
+    #[test]
+    fn interleaved_layout() {
+        let mesh = quad();
+        let attributes = VertexAttributes {
+            normals: Some(flat_normals()),
+            uvs: vec![vec![[0.25, 0.5]; 6], vec![[0.75, 1.0]; 6]],
+            colors: Some(vec![[1.0, 0.0, 0.0, 1.0]; 6]),
+            ..VertexAttributes::default()
+        };
+        let (buffers, _) = weld(&mesh, &attributes, &[0, 5]).unwrap();
+        // Position, normal, 2 UVs and color.
+        assert_eq!(buffers.stride(), 3 + 3 + 2 * 2 + 4);
+        let data = buffers.interleaved();
+        assert_eq!(data.len(), 2 * buffers.stride());
+        assert_eq!(&data[14..], [
+            1.0, 1.0, 0.0,
+            0.0, 0.0, 1.0,
+            0.25, 0.5,
+            0.75, 1.0,
+            1.0, 0.0, 0.0, 1.0,
+        ]);
+        // Positions only.
+        let (buffers, _) = weld(&mesh, &VertexAttributes::default(), &[0, 5]).unwrap();
+        assert_eq!(buffers.stride(), 3);
+        assert_eq!(buffers.interleaved(), [0.0, 0.0, 0.0, 1.0, 1.0, 0.0]);
+    }
+}
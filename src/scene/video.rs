@@ -0,0 +1,515 @@
+//! Contains media embedded in `Video` objects.
+//!
+//! FBX files exported with "embed media" option have the contents of texture images (or movies)
+//! as `Content` binary property of `Video` objects, such as:
+//!
+//! ```text
+//! Video: 3456, "Video::wood\x00\x01Video", "Clip" {
+//!     Type: "Clip"
+//!     Filename: "C:/textures/wood.png"
+//!     RelativeFilename: "textures\wood.png"
+//!     Content: <binary>
+//! }
+//! ```
+//!
+//! `Video` objects without embedded data have an empty `Content` (or no `Content` at all).
+
+use std::io::{self, Read, Seek, Write};
+use byteorder::{LittleEndian, ReadBytesExt};
+use error::{Error, Result};
+use event::FbxEvent;
+use property::DelayedProperties;
+use reader::{EventReader, NodeSpan, ParserConfig};
+use scene::{check_class, child_property, child_string};
+use scene::object_graph::{Object, ObjectGraph};
+use tree::Node;
+
+
+/// Properties larger than this are not buffered by
+/// [`stream_embedded_media()`](fn.stream_embedded_media.html).
+const STREAMING_PROPERTY_BUFFER_SIZE: u64 = 64 * 1024;
+
+/// Number of bytes required to sniff image formats.
+const SNIFF_LEN: usize = 16;
+
+
+/// Image format of embedded media.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// PNG.
+    Png,
+    /// JPEG.
+    Jpeg,
+    /// GIF.
+    Gif,
+    /// BMP.
+    Bmp,
+    /// TIFF.
+    Tiff,
+    /// DirectDraw Surface.
+    Dds,
+    /// Photoshop document.
+    Psd,
+    /// WebP.
+    WebP,
+    /// KTX (version 1).
+    Ktx,
+    /// KTX 2.
+    Ktx2,
+    /// Radiance HDR.
+    Hdr,
+    /// OpenEXR.
+    Exr,
+    /// Truevision TGA.
+    ///
+    /// TGA has no magic bytes, so this is detected only from file names.
+    Tga,
+    /// Unknown format.
+    Unknown,
+}
+
+impl ImageFormat {
+    /// Detects the image format from the leading bytes of the data.
+    ///
+    /// 16 bytes are enough to detect all supported formats.
+    pub fn sniff(data: &[u8]) -> Self {
+        if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+            ImageFormat::Png
+        } else if data.starts_with(b"\xff\xd8\xff") {
+            ImageFormat::Jpeg
+        } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+            ImageFormat::Gif
+        } else if data.starts_with(b"BM") {
+            ImageFormat::Bmp
+        } else if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+            ImageFormat::Tiff
+        } else if data.starts_with(b"DDS ") {
+            ImageFormat::Dds
+        } else if data.starts_with(b"8BPS") {
+            ImageFormat::Psd
+        } else if data.len() >= 12 && data.starts_with(b"RIFF") && &data[8..12] == b"WEBP" {
+            ImageFormat::WebP
+        } else if data.starts_with(b"\xabKTX 11\xbb\r\n\x1a\n") {
+            ImageFormat::Ktx
+        } else if data.starts_with(b"\xabKTX 20\xbb\r\n\x1a\n") {
+            ImageFormat::Ktx2
+        } else if data.starts_with(b"#?RADIANCE") || data.starts_with(b"#?RGBE") {
+            ImageFormat::Hdr
+        } else if data.starts_with(b"\x76\x2f\x31\x01") {
+            ImageFormat::Exr
+        } else {
+            ImageFormat::Unknown
+        }
+    }
+
+    /// Guesses the image format from the extension of the file name.
+    ///
+    /// Both `/` and `\` are accepted as path separators.
+    pub fn from_file_name(file_name: &str) -> Self {
+        let base = file_name.rsplit(['/', '\\']).next().unwrap_or(file_name);
+        let ext = match base.rfind('.') {
+            Some(pos) => base[pos + 1..].to_lowercase(),
+            None => return ImageFormat::Unknown,
+        };
+        match &ext[..] {
+            "png" => ImageFormat::Png,
+            "jpg" | "jpeg" | "jpe" => ImageFormat::Jpeg,
+            "gif" => ImageFormat::Gif,
+            "bmp" | "dib" => ImageFormat::Bmp,
+            "tif" | "tiff" => ImageFormat::Tiff,
+            "dds" => ImageFormat::Dds,
+            "psd" => ImageFormat::Psd,
+            "webp" => ImageFormat::WebP,
+            "ktx" => ImageFormat::Ktx,
+            "ktx2" => ImageFormat::Ktx2,
+            "hdr" => ImageFormat::Hdr,
+            "exr" => ImageFormat::Exr,
+            "tga" | "targa" => ImageFormat::Tga,
+            _ => ImageFormat::Unknown,
+        }
+    }
+
+    /// Detects the image format from the leading bytes, or from the file names if unknown.
+    fn detect(data: &[u8], file_names: &[Option<&str>]) -> Self {
+        let format = ImageFormat::sniff(data);
+        if format != ImageFormat::Unknown {
+            return format;
+        }
+        file_names.iter()
+            .filter_map(|name| name.map(ImageFormat::from_file_name))
+            .find(|&format| format != ImageFormat::Unknown)
+            .unwrap_or(ImageFormat::Unknown)
+    }
+
+    /// Returns the usual file extension (without a dot) of the format.
+    pub fn extension(&self) -> Option<&'static str> {
+        Some(match *self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Gif => "gif",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Tiff => "tif",
+            ImageFormat::Dds => "dds",
+            ImageFormat::Psd => "psd",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Ktx => "ktx",
+            ImageFormat::Ktx2 => "ktx2",
+            ImageFormat::Hdr => "hdr",
+            ImageFormat::Exr => "exr",
+            ImageFormat::Tga => "tga",
+            ImageFormat::Unknown => return None,
+        })
+    }
+}
+
+
+/// Media embedded in a `Video` object.
+#[derive(Debug, Clone)]
+pub struct EmbeddedMedia<'a> {
+    /// Object ID.
+    pub id: i64,
+    /// Video name.
+    pub name: &'a str,
+    /// `Filename` (absolute path of the original file).
+    pub file_name: Option<String>,
+    /// `RelativeFilename`.
+    pub relative_file_name: Option<String>,
+    /// Embedded data.
+    pub content: &'a [u8],
+}
+
+impl<'a> EmbeddedMedia<'a> {
+    /// Decodes the embedded media of the `Video` object.
+    ///
+    /// Returns `Ok(None)` if the video has no embedded data.
+    pub fn from_object(object: &Object<'a>) -> Result<Option<Self>> {
+        check_class(object, "Video")?;
+        let node = object.node;
+        let content = match child_property(node, "Content") {
+            Some(prop) => match prop.get_binary() {
+                Some(content) => content,
+                // Empty string is used when the media is not embedded.
+                None if prop.get_string() == Some("") => &[][..],
+                None => return Err(Error::UnexpectedValue(format!("Expected binary value for `{}/Content`, but got {:?}", node.name, prop))),
+            },
+            None => &[][..],
+        };
+        if content.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(EmbeddedMedia {
+            id: object.id,
+            name: object.name,
+            file_name: file_name(node)?,
+            relative_file_name: child_string(node, "RelativeFilename")?,
+            content,
+        }))
+    }
+
+    /// Returns the image format of the content.
+    ///
+    /// The format is detected from the content, and guessed from the file names if the content
+    /// has no known magic bytes.
+    pub fn format(&self) -> ImageFormat {
+        let file_names = [self.relative_file_name.as_ref().map(|s| &s[..]), self.file_name.as_ref().map(|s| &s[..])];
+        ImageFormat::detect(self.content, &file_names)
+    }
+
+    /// Writes the content to the writer.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(self.content)?;
+        Ok(())
+    }
+}
+
+/// Returns all media embedded in `Video` objects.
+pub fn embedded_media<'a>(graph: &ObjectGraph<'a>) -> Result<Vec<EmbeddedMedia<'a>>> {
+    let mut media = Vec::new();
+    for object in graph.objects().filter(|object| object.class == "Video") {
+        if let Some(m) = EmbeddedMedia::from_object(object)? {
+            media.push(m);
+        }
+    }
+    Ok(media)
+}
+
+
+/// Information of media embedded in a `Video` object, used by
+/// [`stream_embedded_media()`](fn.stream_embedded_media.html).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaInfo {
+    /// Object ID.
+    pub id: i64,
+    /// Video name.
+    pub name: String,
+    /// `Filename` (absolute path of the original file).
+    pub file_name: Option<String>,
+    /// `RelativeFilename`.
+    pub relative_file_name: Option<String>,
+    /// Image format of the content.
+    pub format: ImageFormat,
+    /// Byte size of the content.
+    pub len: u64,
+}
+
+/// Location of the content of a `Video` being scanned.
+enum ContentSource {
+    /// Content small enough to be read by the parser.
+    Buffered(Vec<u8>),
+    /// Content skipped by the parser.
+    Skipped(NodeSpan),
+}
+
+/// Calls `f` for each media embedded in `Video` objects of the FBX data, with a stream of the
+/// content.
+///
+/// Unlike [`embedded_media()`](fn.embedded_media.html), this does not load the whole FBX data
+/// nor the whole content into memory: large properties are skipped while parsing, and the
+/// content is read from `source` again when `f` is called.
+/// `f` may read the stream partially or not at all.
+///
+/// `source` should be positioned at the beginning of the FBX data.
+pub fn stream_embedded_media<R, F>(source: R, mut f: F) -> Result<()>
+    where R: Read + Seek,
+          F: FnMut(&MediaInfo, &mut dyn Read) -> Result<()>
+{
+    let mut reader = ParserConfig::new()
+        .max_property_buffer_size(Some(STREAMING_PROPERTY_BUFFER_SIZE))
+        .create_reader(source);
+    loop {
+        match reader.next()? {
+            FbxEvent::StartFbx(_) => {},
+            FbxEvent::EndFbx => return Ok(()),
+            FbxEvent::StartNode { ref name, .. } if &**name == "Objects" => {
+                loop {
+                    match reader.next()? {
+                        FbxEvent::StartNode { name: ref node_name, ref properties } if &**node_name == "Video" => {
+                            stream_video(&mut reader, properties, &mut f)?;
+                        },
+                        FbxEvent::StartNode { .. } => reader.skip_node()?,
+                        FbxEvent::EndNode => break,
+                        ev => return Err(unexpected_event(ev)),
+                    }
+                }
+            },
+            FbxEvent::StartNode { .. } => reader.skip_node()?,
+            ev => return Err(unexpected_event(ev)),
+        }
+    }
+}
+
+/// Reads children of a `Video` node, and calls `f` if it has embedded media.
+fn stream_video<R, F>(reader: &mut EventReader<R>, properties: &DelayedProperties, f: &mut F) -> Result<()>
+    where R: Read + Seek,
+          F: FnMut(&MediaInfo, &mut dyn Read) -> Result<()>
+{
+    let mut props = properties.iter();
+    let id = props.next().and_then(|prop| prop.as_i64())
+        .ok_or_else(|| Error::UnexpectedValue("Expected object ID as the first property of `Video`".to_owned()))?;
+    let name = props.next().and_then(|prop| prop.get_name_class()).map_or("", |(name, _)| name).to_owned();
+    let mut file_name = None;
+    let mut relative_file_name = None;
+    let mut content = None;
+    loop {
+        match reader.next()? {
+            FbxEvent::StartNode { name: ref node_name, ref properties } => {
+                let first_string = || properties.iter().next().and_then(|prop| prop.get_string().map(ToOwned::to_owned));
+                match &**node_name {
+                    "Filename" | "FileName" => file_name = first_string(),
+                    "RelativeFilename" => relative_file_name = first_string(),
+                    "Content" => {
+                        content = match reader.skipped_properties() {
+                            Some(span) => Some(ContentSource::Skipped(span)),
+                            None => properties.iter().next().and_then(|prop| prop.get_binary()).map(|data| ContentSource::Buffered(data.to_vec())),
+                        };
+                    },
+                    _ => {},
+                }
+                reader.skip_node()?;
+            },
+            FbxEvent::EndNode => break,
+            ev => return Err(unexpected_event(ev)),
+        }
+    }
+
+    let mut info = MediaInfo {
+        id,
+        name,
+        file_name,
+        relative_file_name,
+        format: ImageFormat::Unknown,
+        len: 0,
+    };
+    match content {
+        Some(ContentSource::Buffered(data)) => {
+            if data.is_empty() {
+                return Ok(());
+            }
+            info.format = detect_format(&data, &info);
+            info.len = data.len() as u64;
+            f(&info, &mut &data[..])
+        },
+        Some(ContentSource::Skipped(span)) => reader.read_span_with(span, |source| {
+            // Properties of `Content` are a single binary (`R`) property: type code, 32-bit
+            // length and data.
+            let type_code = source.read_u8()?;
+            let len = source.read_u32::<LittleEndian>()? as u64;
+            if type_code != b'R' {
+                return Err(Error::UnexpectedValue(format!("Expected binary value for `Video/Content`, but got type code {:?}", type_code as char)));
+            }
+            if len == 0 {
+                return Ok(());
+            }
+            let mut head = Vec::with_capacity(SNIFF_LEN);
+            (&mut *source).take(SNIFF_LEN as u64).read_to_end(&mut head)?;
+            if (head.len() as u64) < len.min(SNIFF_LEN as u64) {
+                return Err(Error::UnexpectedEof);
+            }
+            info.format = detect_format(&head, &info);
+            info.len = len;
+            let rest = source.take(len - head.len() as u64);
+            f(&info, &mut io::Cursor::new(head).chain(rest))
+        }),
+        None => Ok(()),
+    }
+}
+
+fn detect_format(data: &[u8], info: &MediaInfo) -> ImageFormat {
+    let file_names = [info.relative_file_name.as_ref().map(|s| &s[..]), info.file_name.as_ref().map(|s| &s[..])];
+    ImageFormat::detect(data, &file_names)
+}
+
+fn unexpected_event(ev: FbxEvent) -> Error {
+    Error::UnexpectedValue(format!("Expected `StartNode` or `EndNode` event, but got {:?}", ev))
+}
+
+/// Returns `Filename` (or `FileName`) of the node.
+fn file_name(node: &Node) -> Result<Option<String>> {
+    match child_string(node, "Filename")? {
+        Some(name) => Ok(Some(name)),
+        None => child_string(node, "FileName"),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use property::Property;
+    use scene::object_graph::ObjectGraph;
+    use test_util::{fbx, node, object, tree, TestNode};
+    use super::*;
+
+    /// Returns data starting with PNG magic, larger than the streaming buffer size if `large`.
+    fn png(large: bool) -> Vec<u8> {
+        let len = if large { STREAMING_PROPERTY_BUFFER_SIZE as usize * 2 + 3 } else { 100 };
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        data.extend((0..len).map(|i| (i * 7 % 251) as u8));
+        data
+    }
+
+    fn video<'a>(id: i64, file_name: &'static str, content: Property<'a>) -> TestNode<'a> {
+        object("Video", id, "wood\0\x01Video", "Clip", vec![
+            node("Type", vec![Property::String(Ok("Clip"))], vec![]),
+            node("Filename", vec![Property::String(Ok(file_name))], vec![]),
+            node("Content", vec![content], vec![]),
+        ])
+    }
+
+    /// Returns nodes with videos of the small and the large PNG data.
+    fn nodes<'a>(small: &'a [u8], large: &'a [u8]) -> Vec<TestNode<'a>> {
+        vec![
+            node("Definitions", vec![], vec![]),
+            node("Objects", vec![], vec![
+                object("Model", 1, "Cube\0\x01Model", "Mesh", vec![]),
+                video(10, "C:/small.png", Property::Binary(small)),
+                video(11, "C:/large.png", Property::Binary(large)),
+                // Not embedded.
+                video(12, "C:/external.png", Property::String(Ok(""))),
+                // No magic bytes.
+                video(13, "C:/image.TGA", Property::Binary(b"\0\0\x02\0")),
+            ]),
+            node("Connections", vec![], vec![]),
+        ]
+    }
+
+    #[test]
+    fn stream_equals_buffered() {
+        let (small, large) = (png(false), png(true));
+        for &version in &[7400, 7500] {
+            let tree = tree(version, &nodes(&small, &large));
+            let graph = ObjectGraph::from_tree(&tree);
+            let buffered = embedded_media(&graph).unwrap().iter().map(|media| {
+                let mut data = Vec::new();
+                media.write_to(&mut data).unwrap();
+                (media.id, media.format(), data)
+            }).collect::<Vec<_>>();
+            assert_eq!(buffered.iter().map(|m| (m.0, m.1)).collect::<Vec<_>>(), [(10, ImageFormat::Png), (11, ImageFormat::Png), (13, ImageFormat::Tga)]);
+
+            let mut streamed = Vec::new();
+            stream_embedded_media(Cursor::new(fbx(version, &nodes(&small, &large))), |info, content| {
+                let mut data = Vec::new();
+                content.read_to_end(&mut data)?;
+                assert_eq!(info.len, data.len() as u64);
+                streamed.push((info.id, info.format, data));
+                Ok(())
+            }).unwrap();
+            assert_eq!(streamed, buffered);
+        }
+    }
+
+    #[test]
+    fn stream_partially_read() {
+        // Contents not read by the callback don't affect the following media.
+        let (small, large) = (png(false), png(true));
+        let mut ids = Vec::new();
+        stream_embedded_media(Cursor::new(fbx(7400, &nodes(&small, &large))), |info, content| {
+            let mut head = [0; 4];
+            content.read_exact(&mut head)?;
+            assert_eq!(&head, if info.format == ImageFormat::Tga { b"\0\0\x02\0" } else { b"\x89PNG" });
+            ids.push(info.id);
+            Ok(())
+        }).unwrap();
+        assert_eq!(ids, [10, 11, 13]);
+    }
+
+    #[test]
+    fn sniff() {
+        let cases: &[(&[u8], ImageFormat)] = &[
+            (b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR", ImageFormat::Png),
+            (b"\xff\xd8\xff\xe0\0\x10JFIF", ImageFormat::Jpeg),
+            (b"GIF87a", ImageFormat::Gif),
+            (b"GIF89a\x01\0", ImageFormat::Gif),
+            (b"BM6\0\0\0", ImageFormat::Bmp),
+            (b"II*\0\x08\0\0\0", ImageFormat::Tiff),
+            (b"MM\0*\0\0\0\x08", ImageFormat::Tiff),
+            (b"DDS |\0\0\0", ImageFormat::Dds),
+            (b"8BPS\0\x01", ImageFormat::Psd),
+            (b"RIFF\x24\0\0\0WEBPVP8 ", ImageFormat::WebP),
+            (b"\xabKTX 11\xbb\r\n\x1a\n", ImageFormat::Ktx),
+            (b"\xabKTX 20\xbb\r\n\x1a\n", ImageFormat::Ktx2),
+            (b"#?RADIANCE\n", ImageFormat::Hdr),
+            (b"#?RGBE\n", ImageFormat::Hdr),
+            (b"\x76\x2f\x31\x01\x02\0\0\0", ImageFormat::Exr),
+            // RIFF without WebP.
+            (b"RIFF\x24\0\0\0WAVEfmt ", ImageFormat::Unknown),
+            (b"RIFF", ImageFormat::Unknown),
+            (b"\x89PN", ImageFormat::Unknown),
+            (b"", ImageFormat::Unknown),
+        ];
+        for &(data, format) in cases {
+            assert_eq!(ImageFormat::sniff(data), format, "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn from_file_name() {
+        assert_eq!(ImageFormat::from_file_name("C:\\textures\\wood.JPEG"), ImageFormat::Jpeg);
+        assert_eq!(ImageFormat::from_file_name("textures/wood.tga"), ImageFormat::Tga);
+        assert_eq!(ImageFormat::from_file_name("wood.ktx2"), ImageFormat::Ktx2);
+        // Dot in directory names is not an extension.
+        assert_eq!(ImageFormat::from_file_name("textures.png/wood"), ImageFormat::Unknown);
+        assert_eq!(ImageFormat::from_file_name("wood.xyz"), ImageFormat::Unknown);
+        assert_eq!(ImageFormat::from_file_name(""), ImageFormat::Unknown);
+    }
+}
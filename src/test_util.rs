@@ -0,0 +1,176 @@
+//! Contains helpers to build FBX binary data for tests.
+
+use std::io::Write;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use property::Property;
+use reader::EventReader;
+use tree::Tree;
+
+
+/// A node to be written as FBX binary.
+#[derive(Debug, Clone)]
+pub struct TestNode<'a> {
+    pub name: &'static str,
+    pub properties: Vec<Property<'a>>,
+    pub children: Vec<TestNode<'a>>,
+}
+
+/// Creates a string property.
+pub fn string(s: &'static str) -> Property<'static> {
+    Property::String(Ok(s))
+}
+
+/// Creates a node.
+pub fn node<'a>(name: &'static str, properties: Vec<Property<'a>>, children: Vec<TestNode<'a>>) -> TestNode<'a> {
+    TestNode { name, properties, children }
+}
+
+/// Creates an object node, such as `Model: 1, "Cube\x00\x01Model", "Mesh"`.
+///
+/// `name_class` is the name and the class joined by `"\x00\x01"`.
+pub fn object<'a>(node_name: &'static str, id: i64, name_class: &'static str, subclass: &'static str, children: Vec<TestNode<'a>>) -> TestNode<'a> {
+    node(node_name, vec![Property::I64(id), Property::String(Ok(name_class)), Property::String(Ok(subclass))], children)
+}
+
+/// Creates an `OO` connection node.
+pub fn connection(child: i64, parent: i64) -> TestNode<'static> {
+    node("C", vec![Property::String(Ok("OO")), Property::I64(child), Property::I64(parent)], vec![])
+}
+
+/// Creates an `OP` connection node.
+pub fn op(child: i64, parent: i64, property: &'static str) -> TestNode<'static> {
+    node("C", vec![Property::String(Ok("OP")), Property::I64(child), Property::I64(parent), Property::String(Ok(property))], vec![])
+}
+
+/// Creates a `P` record of `Properties70` with an empty label.
+pub fn p(name: &'static str, type_name: &'static str, flags: &'static str, values: Vec<Property<'static>>) -> TestNode<'static> {
+    let mut properties = vec![Property::String(Ok(name)), Property::String(Ok(type_name)), Property::String(Ok("")), Property::String(Ok(flags))];
+    properties.extend(values);
+    node("P", properties, vec![])
+}
+
+/// Returns FBX binary of the given top-level nodes.
+///
+/// Arrays are written without compression.
+pub fn fbx(version: i32, nodes: &[TestNode]) -> Vec<u8> {
+    fbx_with_compression(version, nodes, None)
+}
+
+/// Returns FBX binary of the given top-level nodes.
+///
+/// Arrays are zlib compressed with the given level, or written without compression if `None`.
+pub fn fbx_with_compression(version: i32, nodes: &[TestNode], compression: Option<Compression>) -> Vec<u8> {
+    let mut data = b"Kaydara FBX Binary  \0\x1a\0".to_vec();
+    data.extend_from_slice(&version.to_le_bytes());
+    for node in nodes {
+        write_node(&mut data, node, version, compression);
+    }
+    // Null record as the end marker of the implicit root node.
+    data.extend(vec![0; null_record_len(version)]);
+    data
+}
+
+/// Reads the tree of the FBX binary of the given top-level nodes.
+pub fn tree(version: i32, nodes: &[TestNode]) -> Tree {
+    let data = fbx(version, nodes);
+    Tree::read_from(&mut EventReader::new(&data[..])).unwrap()
+}
+
+/// Returns the length of a null record (and of a node record header).
+pub fn null_record_len(version: i32) -> usize {
+    if version < 7500 { 13 } else { 25 }
+}
+
+fn write_node(data: &mut Vec<u8>, node: &TestNode, version: i32, compression: Option<Compression>) {
+    let start = data.len();
+    let properties = node.properties.iter().fold(Vec::new(), |mut buf, prop| {
+        write_property(&mut buf, prop, compression);
+        buf
+    });
+    write_header_field(data, version, 0);
+    write_header_field(data, version, node.properties.len() as u64);
+    write_header_field(data, version, properties.len() as u64);
+    data.push(node.name.len() as u8);
+    data.extend_from_slice(node.name.as_bytes());
+    data.extend(properties);
+    if !node.children.is_empty() {
+        for child in &node.children {
+            write_node(data, child, version, compression);
+        }
+        data.extend(vec![0; null_record_len(version)]);
+    }
+    // Patch the end offset.
+    let end = data.len() as u64;
+    let mut field = Vec::new();
+    write_header_field(&mut field, version, end);
+    data[start..start + field.len()].copy_from_slice(&field);
+}
+
+fn write_header_field(data: &mut Vec<u8>, version: i32, value: u64) {
+    if version < 7500 {
+        data.extend_from_slice(&(value as u32).to_le_bytes());
+    } else {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn write_property(data: &mut Vec<u8>, prop: &Property, compression: Option<Compression>) {
+    let array = |data: &mut Vec<u8>, code: u8, len: usize, bytes: Vec<u8>| {
+        let (encoding, bytes) = match compression {
+            Some(level) => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), level);
+                encoder.write_all(&bytes).unwrap();
+                (1_u32, encoder.finish().unwrap())
+            },
+            None => (0, bytes),
+        };
+        data.push(code);
+        data.extend_from_slice(&(len as u32).to_le_bytes());
+        data.extend_from_slice(&encoding.to_le_bytes());
+        data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        data.extend(bytes);
+    };
+    match *prop {
+        Property::Bool(v) => data.extend_from_slice(&[b'C', if v { b'Y' } else { b'T' }]),
+        Property::I16(v) => {
+            data.push(b'Y');
+            data.extend_from_slice(&v.to_le_bytes());
+        },
+        Property::I32(v) => {
+            data.push(b'I');
+            data.extend_from_slice(&v.to_le_bytes());
+        },
+        Property::I64(v) => {
+            data.push(b'L');
+            data.extend_from_slice(&v.to_le_bytes());
+        },
+        Property::F32(v) => {
+            data.push(b'F');
+            data.extend_from_slice(&v.to_le_bytes());
+        },
+        Property::F64(v) => {
+            data.push(b'D');
+            data.extend_from_slice(&v.to_le_bytes());
+        },
+        Property::String(v) => {
+            let bytes = match v {
+                Ok(s) => s.as_bytes(),
+                Err(bytes) => bytes,
+            };
+            data.push(b'S');
+            data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            data.extend_from_slice(bytes);
+        },
+        Property::Binary(bytes) => {
+            data.push(b'R');
+            data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            data.extend_from_slice(bytes);
+        },
+        Property::VecBool(ref v) => array(data, b'b', v.len(), v.iter().map(|&v| v as u8).collect()),
+        Property::VecI32(ref v) => array(data, b'i', v.len(), v.iter().flat_map(|v| v.to_le_bytes()).collect()),
+        Property::VecI64(ref v) => array(data, b'l', v.len(), v.iter().flat_map(|v| v.to_le_bytes()).collect()),
+        Property::VecF32(ref v) => array(data, b'f', v.len(), v.iter().flat_map(|v| v.to_le_bytes()).collect()),
+        Property::VecF64(ref v) => array(data, b'd', v.len(), v.iter().flat_map(|v| v.to_le_bytes()).collect()),
+    }
+}
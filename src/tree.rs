@@ -0,0 +1,125 @@
+//! Contains in-memory tree of FBX nodes.
+//!
+//! Event reader is fast and memory-efficient, but some operations (such as comparing files or
+//! resolving references between objects) need random access to nodes.
+//! [`Tree`](struct.Tree.html) reads the whole FBX data and keeps them in memory.
+
+use std::io::Read;
+use error::{Error, Result};
+use event::{FbxEvent, NodeName};
+use property::DelayedProperties;
+use reader::EventReader;
+
+
+/// Whole FBX data.
+#[derive(Debug, Clone)]
+pub struct Tree {
+    /// FBX version.
+    pub version: i32,
+    /// Top-level nodes.
+    pub children: Vec<Node>,
+}
+
+impl Tree {
+    /// Reads the whole FBX data from the reader.
+    ///
+    /// The reader should be at the beginning of the FBX stream (i.e. `StartFbx` event should not
+    /// have been read yet).
+    pub fn read_from<R: Read>(reader: &mut EventReader<R>) -> Result<Self> {
+        let version = match reader.next()? {
+            FbxEvent::StartFbx(header) => header.version,
+            ev => return Err(Error::UnexpectedValue(format!("Expected `StartFbx` event, but got {:?}", ev))),
+        };
+        let mut children = Vec::new();
+        loop {
+            match reader.next()? {
+                FbxEvent::StartNode { name, properties } => {
+                    children.push(Node::read_from(reader, name, properties)?);
+                },
+                FbxEvent::EndFbx => break,
+                ev => return Err(Error::UnexpectedValue(format!("Expected `StartNode` or `EndFbx` event, but got {:?}", ev))),
+            }
+        }
+        Ok(Tree { version, children })
+    }
+
+    /// Returns an iterator of top-level nodes with the given name.
+    pub fn children_by_name<'a>(&'a self, name: &'a str) -> ChildrenByName<'a> {
+        ChildrenByName {
+            iter: self.children.iter(),
+            name,
+        }
+    }
+
+    /// Returns the first top-level node with the given name.
+    pub fn first_child_by_name(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|node| &*node.name == name)
+    }
+}
+
+
+/// A node and its descendants.
+#[derive(Debug, Clone)]
+pub struct Node {
+    /// Node name.
+    pub name: NodeName,
+    /// Node properties.
+    pub properties: DelayedProperties,
+    /// Child nodes.
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    /// Reads children of the node and constructs the node.
+    ///
+    /// `name` and `properties` should be the values of the `StartNode` event just read from the
+    /// reader.
+    /// This reads events up to and including the `EndNode` event of the node.
+    pub fn read_from<R: Read>(reader: &mut EventReader<R>, name: NodeName, properties: DelayedProperties) -> Result<Self> {
+        let mut children = Vec::new();
+        loop {
+            match reader.next()? {
+                FbxEvent::StartNode { name, properties } => {
+                    children.push(Node::read_from(reader, name, properties)?);
+                },
+                FbxEvent::EndNode => break,
+                ev => return Err(Error::UnexpectedValue(format!("Expected `StartNode` or `EndNode` event, but got {:?}", ev))),
+            }
+        }
+        Ok(Node {
+            name,
+            properties,
+            children,
+        })
+    }
+
+    /// Returns an iterator of child nodes with the given name.
+    pub fn children_by_name<'a>(&'a self, name: &'a str) -> ChildrenByName<'a> {
+        ChildrenByName {
+            iter: self.children.iter(),
+            name,
+        }
+    }
+
+    /// Returns the first child node with the given name.
+    pub fn first_child_by_name(&self, name: &str) -> Option<&Node> {
+        self.children.iter().find(|node| &*node.name == name)
+    }
+}
+
+
+/// An iterator of nodes with the specific name.
+#[derive(Debug, Clone)]
+pub struct ChildrenByName<'a> {
+    iter: ::std::slice::Iter<'a, Node>,
+    name: &'a str,
+}
+
+impl<'a> Iterator for ChildrenByName<'a> {
+    type Item = &'a Node;
+
+    fn next(&mut self) -> Option<&'a Node> {
+        let name = self.name;
+        self.iter.by_ref().find(|node| &*node.name == name)
+    }
+}